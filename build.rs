@@ -1,7 +1,10 @@
 const ICON_PATH: &str = "resource/lurk.ico";
 
 fn main() {
-    compile_resource();
+    // The resource compiler is only there when building for Windows.
+    if std::env::var("CARGO_CFG_TARGET_OS").is_ok_and(|os| os == "windows") {
+        compile_resource();
+    }
     extract_runtime_icon();
 }
 
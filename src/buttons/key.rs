@@ -1,7 +1,11 @@
 // Copyright 2025 Jordan Johnson
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
+#[cfg(target_os = "linux")]
+use evdev::KeyCode;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+#[cfg(windows)]
 use windows::Win32::UI::Input::KeyboardAndMouse;
 
 #[derive(
@@ -19,6 +23,7 @@ use windows::Win32::UI::Input::KeyboardAndMouse;
     num_derive::ToPrimitive,
     Serialize,
     Deserialize,
+    JsonSchema,
 )]
 #[repr(u8)]
 #[allow(non_camel_case_types, clippy::upper_case_acronyms)] // Just for here, to be consistent with VK codes...
@@ -230,6 +235,7 @@ impl KeyButton {
         self.to_u8()
             .expect("button should always be convertable to virtual key code")
     }
+    #[cfg(windows)]
     pub fn to_keydown_input(self) -> KeyboardAndMouse::INPUT {
         use KeyboardAndMouse as KBM;
         let vk = self.to_vk();
@@ -246,6 +252,7 @@ impl KeyButton {
             },
         }
     }
+    #[cfg(windows)]
     pub fn to_keyup_input(self) -> KeyboardAndMouse::INPUT {
         use KeyboardAndMouse as KBM;
         let vk = self.to_vk();
@@ -262,6 +269,341 @@ impl KeyButton {
             },
         }
     }
+    /// The Linux key code for this key, if Linux has one. The generic Shift, Ctrl and Alt send the
+    /// left one. The mouse-button-as-key keys send the mouse button.
+    #[cfg(target_os = "linux")]
+    pub fn to_evdev(self) -> Option<KeyCode> {
+        let code = match self {
+            Self::LBUTTON => KeyCode::BTN_LEFT,
+            Self::RBUTTON => KeyCode::BTN_RIGHT,
+            Self::CANCEL => KeyCode::KEY_CANCEL,
+            Self::MBUTTON => KeyCode::BTN_MIDDLE,
+            Self::XBUTTON1 => KeyCode::BTN_SIDE,
+            Self::XBUTTON2 => KeyCode::BTN_EXTRA,
+            Self::BACK => KeyCode::KEY_BACKSPACE,
+            Self::TAB => KeyCode::KEY_TAB,
+            Self::CLEAR => KeyCode::KEY_CLEAR,
+            Self::RETURN => KeyCode::KEY_ENTER,
+            Self::SHIFT => KeyCode::KEY_LEFTSHIFT,
+            Self::CONTROL => KeyCode::KEY_LEFTCTRL,
+            Self::MENU => KeyCode::KEY_LEFTALT,
+            Self::PAUSE => KeyCode::KEY_PAUSE,
+            Self::CAPITAL => KeyCode::KEY_CAPSLOCK,
+            Self::KANA_HANGUL => KeyCode::KEY_HANGEUL,
+            Self::IME_ON => return None,
+            Self::JUNJA => return None,
+            Self::FINAL => return None,
+            Self::HANJA_KANJI => KeyCode::KEY_HANJA,
+            Self::IME_OFF => return None,
+            Self::ESCAPE => KeyCode::KEY_ESC,
+            Self::CONVERT => KeyCode::KEY_HENKAN,
+            Self::NONCONVERT => KeyCode::KEY_MUHENKAN,
+            Self::ACCEPT => return None,
+            Self::MODECHANGE => return None,
+            Self::SPACE => KeyCode::KEY_SPACE,
+            Self::PRIOR => KeyCode::KEY_PAGEUP,
+            Self::NEXT => KeyCode::KEY_PAGEDOWN,
+            Self::END => KeyCode::KEY_END,
+            Self::HOME => KeyCode::KEY_HOME,
+            Self::LEFT => KeyCode::KEY_LEFT,
+            Self::UP => KeyCode::KEY_UP,
+            Self::RIGHT => KeyCode::KEY_RIGHT,
+            Self::DOWN => KeyCode::KEY_DOWN,
+            Self::SELECT => KeyCode::KEY_SELECT,
+            Self::PRINT => KeyCode::KEY_PRINT,
+            Self::EXECUTE => return None,
+            Self::SNAPSHOT => KeyCode::KEY_SYSRQ,
+            Self::INSERT => KeyCode::KEY_INSERT,
+            Self::DELETE => KeyCode::KEY_DELETE,
+            Self::HELP => KeyCode::KEY_HELP,
+            Self::KEY0 => KeyCode::KEY_0,
+            Self::KEY1 => KeyCode::KEY_1,
+            Self::KEY2 => KeyCode::KEY_2,
+            Self::KEY3 => KeyCode::KEY_3,
+            Self::KEY4 => KeyCode::KEY_4,
+            Self::KEY5 => KeyCode::KEY_5,
+            Self::KEY6 => KeyCode::KEY_6,
+            Self::KEY7 => KeyCode::KEY_7,
+            Self::KEY8 => KeyCode::KEY_8,
+            Self::KEY9 => KeyCode::KEY_9,
+            Self::A => KeyCode::KEY_A,
+            Self::B => KeyCode::KEY_B,
+            Self::C => KeyCode::KEY_C,
+            Self::D => KeyCode::KEY_D,
+            Self::E => KeyCode::KEY_E,
+            Self::F => KeyCode::KEY_F,
+            Self::G => KeyCode::KEY_G,
+            Self::H => KeyCode::KEY_H,
+            Self::I => KeyCode::KEY_I,
+            Self::J => KeyCode::KEY_J,
+            Self::K => KeyCode::KEY_K,
+            Self::L => KeyCode::KEY_L,
+            Self::M => KeyCode::KEY_M,
+            Self::N => KeyCode::KEY_N,
+            Self::O => KeyCode::KEY_O,
+            Self::P => KeyCode::KEY_P,
+            Self::Q => KeyCode::KEY_Q,
+            Self::R => KeyCode::KEY_R,
+            Self::S => KeyCode::KEY_S,
+            Self::T => KeyCode::KEY_T,
+            Self::U => KeyCode::KEY_U,
+            Self::V => KeyCode::KEY_V,
+            Self::W => KeyCode::KEY_W,
+            Self::X => KeyCode::KEY_X,
+            Self::Y => KeyCode::KEY_Y,
+            Self::Z => KeyCode::KEY_Z,
+            Self::LWIN => KeyCode::KEY_LEFTMETA,
+            Self::RWIN => KeyCode::KEY_RIGHTMETA,
+            Self::APPS => KeyCode::KEY_COMPOSE,
+            Self::SLEEP => KeyCode::KEY_SLEEP,
+            Self::NUMPAD0 => KeyCode::KEY_KP0,
+            Self::NUMPAD1 => KeyCode::KEY_KP1,
+            Self::NUMPAD2 => KeyCode::KEY_KP2,
+            Self::NUMPAD3 => KeyCode::KEY_KP3,
+            Self::NUMPAD4 => KeyCode::KEY_KP4,
+            Self::NUMPAD5 => KeyCode::KEY_KP5,
+            Self::NUMPAD6 => KeyCode::KEY_KP6,
+            Self::NUMPAD7 => KeyCode::KEY_KP7,
+            Self::NUMPAD8 => KeyCode::KEY_KP8,
+            Self::NUMPAD9 => KeyCode::KEY_KP9,
+            Self::MULTIPLY => KeyCode::KEY_KPASTERISK,
+            Self::ADD => KeyCode::KEY_KPPLUS,
+            Self::SEPARATOR => KeyCode::KEY_KPCOMMA,
+            Self::SUBTRACT => KeyCode::KEY_KPMINUS,
+            Self::DECIMAL => KeyCode::KEY_KPDOT,
+            Self::DIVIDE => KeyCode::KEY_KPSLASH,
+            Self::F1 => KeyCode::KEY_F1,
+            Self::F2 => KeyCode::KEY_F2,
+            Self::F3 => KeyCode::KEY_F3,
+            Self::F4 => KeyCode::KEY_F4,
+            Self::F5 => KeyCode::KEY_F5,
+            Self::F6 => KeyCode::KEY_F6,
+            Self::F7 => KeyCode::KEY_F7,
+            Self::F8 => KeyCode::KEY_F8,
+            Self::F9 => KeyCode::KEY_F9,
+            Self::F10 => KeyCode::KEY_F10,
+            Self::F11 => KeyCode::KEY_F11,
+            Self::F12 => KeyCode::KEY_F12,
+            Self::F13 => KeyCode::KEY_F13,
+            Self::F14 => KeyCode::KEY_F14,
+            Self::F15 => KeyCode::KEY_F15,
+            Self::F16 => KeyCode::KEY_F16,
+            Self::F17 => KeyCode::KEY_F17,
+            Self::F18 => KeyCode::KEY_F18,
+            Self::F19 => KeyCode::KEY_F19,
+            Self::F20 => KeyCode::KEY_F20,
+            Self::F21 => KeyCode::KEY_F21,
+            Self::F22 => KeyCode::KEY_F22,
+            Self::F23 => KeyCode::KEY_F23,
+            Self::F24 => KeyCode::KEY_F24,
+            Self::NUMLOCK => KeyCode::KEY_NUMLOCK,
+            Self::SCROLL => KeyCode::KEY_SCROLLLOCK,
+            Self::LSHIFT => KeyCode::KEY_LEFTSHIFT,
+            Self::RSHIFT => KeyCode::KEY_RIGHTSHIFT,
+            Self::LCONTROL => KeyCode::KEY_LEFTCTRL,
+            Self::RCONTROL => KeyCode::KEY_RIGHTCTRL,
+            Self::LMENU => KeyCode::KEY_LEFTALT,
+            Self::RMENU => KeyCode::KEY_RIGHTALT,
+            Self::BROWSER_BACK => KeyCode::KEY_BACK,
+            Self::BROWSER_FORWARD => KeyCode::KEY_FORWARD,
+            Self::BROWSER_REFRESH => KeyCode::KEY_REFRESH,
+            Self::BROWSER_STOP => KeyCode::KEY_STOP,
+            Self::BROWSER_SEARCH => KeyCode::KEY_SEARCH,
+            Self::BROWSER_FAVORITES => KeyCode::KEY_BOOKMARKS,
+            Self::BROWSER_HOME => KeyCode::KEY_HOMEPAGE,
+            Self::VOLUME_MUTE => KeyCode::KEY_MUTE,
+            Self::VOLUME_DOWN => KeyCode::KEY_VOLUMEDOWN,
+            Self::VOLUME_UP => KeyCode::KEY_VOLUMEUP,
+            Self::MEDIA_NEXT_TRACK => KeyCode::KEY_NEXTSONG,
+            Self::MEDIA_PREV_TRACK => KeyCode::KEY_PREVIOUSSONG,
+            Self::MEDIA_STOP => KeyCode::KEY_STOPCD,
+            Self::MEDIA_PLAY_PAUSE => KeyCode::KEY_PLAYPAUSE,
+            Self::LAUNCH_MAIL => KeyCode::KEY_MAIL,
+            Self::LAUNCH_MEDIA_SELECT => KeyCode::KEY_MEDIA,
+            Self::LAUNCH_APP1 => KeyCode::KEY_COMPUTER,
+            Self::LAUNCH_APP2 => KeyCode::KEY_CALC,
+            Self::OEM_1 => KeyCode::KEY_SEMICOLON,
+            Self::OEM_PLUS => KeyCode::KEY_EQUAL,
+            Self::OEM_COMMA => KeyCode::KEY_COMMA,
+            Self::OEM_MINUS => KeyCode::KEY_MINUS,
+            Self::OEM_PERIOD => KeyCode::KEY_DOT,
+            Self::OEM_2 => KeyCode::KEY_SLASH,
+            Self::OEM_3 => KeyCode::KEY_GRAVE,
+            Self::OEM_4 => KeyCode::KEY_LEFTBRACE,
+            Self::OEM_5 => KeyCode::KEY_BACKSLASH,
+            Self::OEM_6 => KeyCode::KEY_RIGHTBRACE,
+            Self::OEM_7 => KeyCode::KEY_APOSTROPHE,
+            Self::OEM_8 => return None,
+            Self::OEM_102 => KeyCode::KEY_102ND,
+            Self::PROCESSKEY => return None,
+            Self::PACKET => return None,
+            Self::ATTN => return None,
+            Self::CRSEL => return None,
+            Self::EXSEL => return None,
+            Self::EREOF => return None,
+            Self::PLAY => KeyCode::KEY_PLAY,
+            Self::ZOOM => KeyCode::KEY_ZOOM,
+            Self::NONAME => return None,
+            Self::PA1 => return None,
+            Self::OEM_CLEAR => return None,
+        };
+        Some(code)
+    }
+    /// The key a Linux key code stands for. Mouse buttons are not keys here; see MouseButton.
+    #[cfg(target_os = "linux")]
+    pub fn from_evdev(code: KeyCode) -> Option<Self> {
+        let key = match code {
+            KeyCode::KEY_CANCEL => Self::CANCEL,
+            KeyCode::KEY_BACKSPACE => Self::BACK,
+            KeyCode::KEY_TAB => Self::TAB,
+            KeyCode::KEY_CLEAR => Self::CLEAR,
+            KeyCode::KEY_ENTER => Self::RETURN,
+            KeyCode::KEY_PAUSE => Self::PAUSE,
+            KeyCode::KEY_CAPSLOCK => Self::CAPITAL,
+            KeyCode::KEY_HANGEUL => Self::KANA_HANGUL,
+            KeyCode::KEY_HANJA => Self::HANJA_KANJI,
+            KeyCode::KEY_ESC => Self::ESCAPE,
+            KeyCode::KEY_HENKAN => Self::CONVERT,
+            KeyCode::KEY_MUHENKAN => Self::NONCONVERT,
+            KeyCode::KEY_SPACE => Self::SPACE,
+            KeyCode::KEY_PAGEUP => Self::PRIOR,
+            KeyCode::KEY_PAGEDOWN => Self::NEXT,
+            KeyCode::KEY_END => Self::END,
+            KeyCode::KEY_HOME => Self::HOME,
+            KeyCode::KEY_LEFT => Self::LEFT,
+            KeyCode::KEY_UP => Self::UP,
+            KeyCode::KEY_RIGHT => Self::RIGHT,
+            KeyCode::KEY_DOWN => Self::DOWN,
+            KeyCode::KEY_SELECT => Self::SELECT,
+            KeyCode::KEY_PRINT => Self::PRINT,
+            KeyCode::KEY_SYSRQ => Self::SNAPSHOT,
+            KeyCode::KEY_INSERT => Self::INSERT,
+            KeyCode::KEY_DELETE => Self::DELETE,
+            KeyCode::KEY_HELP => Self::HELP,
+            KeyCode::KEY_0 => Self::KEY0,
+            KeyCode::KEY_1 => Self::KEY1,
+            KeyCode::KEY_2 => Self::KEY2,
+            KeyCode::KEY_3 => Self::KEY3,
+            KeyCode::KEY_4 => Self::KEY4,
+            KeyCode::KEY_5 => Self::KEY5,
+            KeyCode::KEY_6 => Self::KEY6,
+            KeyCode::KEY_7 => Self::KEY7,
+            KeyCode::KEY_8 => Self::KEY8,
+            KeyCode::KEY_9 => Self::KEY9,
+            KeyCode::KEY_A => Self::A,
+            KeyCode::KEY_B => Self::B,
+            KeyCode::KEY_C => Self::C,
+            KeyCode::KEY_D => Self::D,
+            KeyCode::KEY_E => Self::E,
+            KeyCode::KEY_F => Self::F,
+            KeyCode::KEY_G => Self::G,
+            KeyCode::KEY_H => Self::H,
+            KeyCode::KEY_I => Self::I,
+            KeyCode::KEY_J => Self::J,
+            KeyCode::KEY_K => Self::K,
+            KeyCode::KEY_L => Self::L,
+            KeyCode::KEY_M => Self::M,
+            KeyCode::KEY_N => Self::N,
+            KeyCode::KEY_O => Self::O,
+            KeyCode::KEY_P => Self::P,
+            KeyCode::KEY_Q => Self::Q,
+            KeyCode::KEY_R => Self::R,
+            KeyCode::KEY_S => Self::S,
+            KeyCode::KEY_T => Self::T,
+            KeyCode::KEY_U => Self::U,
+            KeyCode::KEY_V => Self::V,
+            KeyCode::KEY_W => Self::W,
+            KeyCode::KEY_X => Self::X,
+            KeyCode::KEY_Y => Self::Y,
+            KeyCode::KEY_Z => Self::Z,
+            KeyCode::KEY_LEFTMETA => Self::LWIN,
+            KeyCode::KEY_RIGHTMETA => Self::RWIN,
+            KeyCode::KEY_COMPOSE => Self::APPS,
+            KeyCode::KEY_SLEEP => Self::SLEEP,
+            KeyCode::KEY_KP0 => Self::NUMPAD0,
+            KeyCode::KEY_KP1 => Self::NUMPAD1,
+            KeyCode::KEY_KP2 => Self::NUMPAD2,
+            KeyCode::KEY_KP3 => Self::NUMPAD3,
+            KeyCode::KEY_KP4 => Self::NUMPAD4,
+            KeyCode::KEY_KP5 => Self::NUMPAD5,
+            KeyCode::KEY_KP6 => Self::NUMPAD6,
+            KeyCode::KEY_KP7 => Self::NUMPAD7,
+            KeyCode::KEY_KP8 => Self::NUMPAD8,
+            KeyCode::KEY_KP9 => Self::NUMPAD9,
+            KeyCode::KEY_KPASTERISK => Self::MULTIPLY,
+            KeyCode::KEY_KPPLUS => Self::ADD,
+            KeyCode::KEY_KPCOMMA => Self::SEPARATOR,
+            KeyCode::KEY_KPMINUS => Self::SUBTRACT,
+            KeyCode::KEY_KPDOT => Self::DECIMAL,
+            KeyCode::KEY_KPSLASH => Self::DIVIDE,
+            KeyCode::KEY_F1 => Self::F1,
+            KeyCode::KEY_F2 => Self::F2,
+            KeyCode::KEY_F3 => Self::F3,
+            KeyCode::KEY_F4 => Self::F4,
+            KeyCode::KEY_F5 => Self::F5,
+            KeyCode::KEY_F6 => Self::F6,
+            KeyCode::KEY_F7 => Self::F7,
+            KeyCode::KEY_F8 => Self::F8,
+            KeyCode::KEY_F9 => Self::F9,
+            KeyCode::KEY_F10 => Self::F10,
+            KeyCode::KEY_F11 => Self::F11,
+            KeyCode::KEY_F12 => Self::F12,
+            KeyCode::KEY_F13 => Self::F13,
+            KeyCode::KEY_F14 => Self::F14,
+            KeyCode::KEY_F15 => Self::F15,
+            KeyCode::KEY_F16 => Self::F16,
+            KeyCode::KEY_F17 => Self::F17,
+            KeyCode::KEY_F18 => Self::F18,
+            KeyCode::KEY_F19 => Self::F19,
+            KeyCode::KEY_F20 => Self::F20,
+            KeyCode::KEY_F21 => Self::F21,
+            KeyCode::KEY_F22 => Self::F22,
+            KeyCode::KEY_F23 => Self::F23,
+            KeyCode::KEY_F24 => Self::F24,
+            KeyCode::KEY_NUMLOCK => Self::NUMLOCK,
+            KeyCode::KEY_SCROLLLOCK => Self::SCROLL,
+            KeyCode::KEY_LEFTSHIFT => Self::LSHIFT,
+            KeyCode::KEY_RIGHTSHIFT => Self::RSHIFT,
+            KeyCode::KEY_LEFTCTRL => Self::LCONTROL,
+            KeyCode::KEY_RIGHTCTRL => Self::RCONTROL,
+            KeyCode::KEY_LEFTALT => Self::LMENU,
+            KeyCode::KEY_RIGHTALT => Self::RMENU,
+            KeyCode::KEY_BACK => Self::BROWSER_BACK,
+            KeyCode::KEY_FORWARD => Self::BROWSER_FORWARD,
+            KeyCode::KEY_REFRESH => Self::BROWSER_REFRESH,
+            KeyCode::KEY_STOP => Self::BROWSER_STOP,
+            KeyCode::KEY_SEARCH => Self::BROWSER_SEARCH,
+            KeyCode::KEY_BOOKMARKS => Self::BROWSER_FAVORITES,
+            KeyCode::KEY_HOMEPAGE => Self::BROWSER_HOME,
+            KeyCode::KEY_MUTE => Self::VOLUME_MUTE,
+            KeyCode::KEY_VOLUMEDOWN => Self::VOLUME_DOWN,
+            KeyCode::KEY_VOLUMEUP => Self::VOLUME_UP,
+            KeyCode::KEY_NEXTSONG => Self::MEDIA_NEXT_TRACK,
+            KeyCode::KEY_PREVIOUSSONG => Self::MEDIA_PREV_TRACK,
+            KeyCode::KEY_STOPCD => Self::MEDIA_STOP,
+            KeyCode::KEY_PLAYPAUSE => Self::MEDIA_PLAY_PAUSE,
+            KeyCode::KEY_MAIL => Self::LAUNCH_MAIL,
+            KeyCode::KEY_MEDIA => Self::LAUNCH_MEDIA_SELECT,
+            KeyCode::KEY_COMPUTER => Self::LAUNCH_APP1,
+            KeyCode::KEY_CALC => Self::LAUNCH_APP2,
+            KeyCode::KEY_SEMICOLON => Self::OEM_1,
+            KeyCode::KEY_EQUAL => Self::OEM_PLUS,
+            KeyCode::KEY_COMMA => Self::OEM_COMMA,
+            KeyCode::KEY_MINUS => Self::OEM_MINUS,
+            KeyCode::KEY_DOT => Self::OEM_PERIOD,
+            KeyCode::KEY_SLASH => Self::OEM_2,
+            KeyCode::KEY_GRAVE => Self::OEM_3,
+            KeyCode::KEY_LEFTBRACE => Self::OEM_4,
+            KeyCode::KEY_BACKSLASH => Self::OEM_5,
+            KeyCode::KEY_RIGHTBRACE => Self::OEM_6,
+            KeyCode::KEY_APOSTROPHE => Self::OEM_7,
+            KeyCode::KEY_102ND => Self::OEM_102,
+            KeyCode::KEY_PLAY => Self::PLAY,
+            KeyCode::KEY_ZOOM => Self::ZOOM,
+            _ => return None,
+        };
+        Some(key)
+    }
     pub fn key_type(self) -> KeyType {
         match self {
             Self::LBUTTON => KeyType::Rare,
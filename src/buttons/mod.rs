@@ -142,6 +142,7 @@ use key::KeyButton;
 pub mod mouse;
 use mouse::MouseButton;
 pub mod wheel;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use wheel::MouseWheelButton;
 
@@ -150,7 +151,18 @@ use wheel::MouseWheelButton;
 // -------------------- Hold Button --------------------
 
 #[derive(
-    Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, enum_map::Enum, Serialize, Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    Hash,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    enum_map::Enum,
+    Serialize,
+    Deserialize,
+    JsonSchema,
 )]
 pub enum HoldButton {
     Key(KeyButton),
@@ -204,7 +216,18 @@ impl std::fmt::Display for TapButton {
 // -------------------- Button --------------------
 
 #[derive(
-    Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, enum_map::Enum, Serialize, Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    Hash,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    enum_map::Enum,
+    Serialize,
+    Deserialize,
+    JsonSchema,
 )]
 pub enum Button {
     Key(KeyButton),
@@ -257,6 +280,36 @@ impl std::fmt::Display for Button {
     }
 }
 
+// -------------------- Input Event --------------------
+
+/// One thing a keyboard or mouse does: a button pressed or released, or a wheel tap. The remap
+/// engine takes these in and hands back the ones to send in their place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputEvent {
+    Down(HoldButton),
+    Up(HoldButton),
+    Tap(TapButton),
+}
+
+impl InputEvent {
+    /// Pressing a button. The wheel has no down, so it is tapped.
+    pub fn press(button: Button) -> Self {
+        match button {
+            Button::Key(key) => Self::Down(key.into()),
+            Button::Mouse(mouse) => Self::Down(mouse.into()),
+            Button::Wheel(wheel) => Self::Tap(wheel.into()),
+        }
+    }
+    /// Letting go of a button. None for the wheel, which was already done when pressed.
+    pub fn release(button: Button) -> Option<Self> {
+        match button {
+            Button::Key(key) => Some(Self::Up(key.into())),
+            Button::Mouse(mouse) => Some(Self::Up(mouse.into())),
+            Button::Wheel(_) => None,
+        }
+    }
+}
+
 // -------------------- ListableButton Trait --------------------
 // (the name is bad)
 
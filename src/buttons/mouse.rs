@@ -1,7 +1,11 @@
 // Copyright 2025 Jordan Johnson
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
+#[cfg(target_os = "linux")]
+use evdev::KeyCode;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+#[cfg(windows)]
 use windows::Win32::UI::Input::KeyboardAndMouse;
 
 #[derive(
@@ -17,6 +21,7 @@ use windows::Win32::UI::Input::KeyboardAndMouse;
     strum::EnumIter,
     Serialize,
     Deserialize,
+    JsonSchema,
 )]
 pub enum MouseButton {
     Left,
@@ -27,6 +32,7 @@ pub enum MouseButton {
 }
 
 impl MouseButton {
+    #[cfg(windows)]
     pub fn to_mousedown_input(self) -> KeyboardAndMouse::INPUT {
         use KeyboardAndMouse as KBM;
         use windows::Win32::UI::WindowsAndMessaging as WM;
@@ -53,6 +59,7 @@ impl MouseButton {
             },
         }
     }
+    #[cfg(windows)]
     pub fn to_mouseup_input(self) -> KeyboardAndMouse::INPUT {
         use KeyboardAndMouse as KBM;
         use windows::Win32::UI::WindowsAndMessaging as WM;
@@ -79,6 +86,27 @@ impl MouseButton {
             },
         }
     }
+    #[cfg(target_os = "linux")]
+    pub fn to_evdev(self) -> KeyCode {
+        match self {
+            Self::Left => KeyCode::BTN_LEFT,
+            Self::Middle => KeyCode::BTN_MIDDLE,
+            Self::Right => KeyCode::BTN_RIGHT,
+            Self::X1 => KeyCode::BTN_SIDE,
+            Self::X2 => KeyCode::BTN_EXTRA,
+        }
+    }
+    #[cfg(target_os = "linux")]
+    pub fn from_evdev(code: KeyCode) -> Option<Self> {
+        match code {
+            KeyCode::BTN_LEFT => Some(Self::Left),
+            KeyCode::BTN_MIDDLE => Some(Self::Middle),
+            KeyCode::BTN_RIGHT => Some(Self::Right),
+            KeyCode::BTN_SIDE => Some(Self::X1),
+            KeyCode::BTN_EXTRA => Some(Self::X2),
+            _ => None,
+        }
+    }
 }
 
 impl std::fmt::Display for MouseButton {
@@ -1,7 +1,11 @@
 // Copyright 2025 Jordan Johnson
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
+#[cfg(target_os = "linux")]
+use evdev::RelativeAxisCode;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+#[cfg(windows)]
 use windows::Win32::UI::Input::KeyboardAndMouse;
 
 #[derive(
@@ -17,6 +21,7 @@ use windows::Win32::UI::Input::KeyboardAndMouse;
     strum::EnumIter,
     Serialize,
     Deserialize,
+    JsonSchema,
 )]
 pub enum MouseWheelButton {
     Up,
@@ -27,6 +32,7 @@ pub enum MouseWheelButton {
 
 impl MouseWheelButton {
     // note: not an impl From<> to be consistent with the other button types
+    #[cfg(windows)]
     pub fn to_input(self) -> KeyboardAndMouse::INPUT {
         use KeyboardAndMouse as KBM;
         use windows::Win32::UI::WindowsAndMessaging as WM;
@@ -57,6 +63,27 @@ impl MouseWheelButton {
             },
         }
     }
+    // One notch of the wheel on Linux. Positive is up, or right.
+    #[cfg(target_os = "linux")]
+    pub fn to_evdev(self) -> (RelativeAxisCode, i32) {
+        match self {
+            Self::Up => (RelativeAxisCode::REL_WHEEL, 1),
+            Self::Down => (RelativeAxisCode::REL_WHEEL, -1),
+            Self::HorzRight => (RelativeAxisCode::REL_HWHEEL, 1),
+            Self::HorzLeft => (RelativeAxisCode::REL_HWHEEL, -1),
+        }
+    }
+    // The direction of a wheel movement on Linux. The number of notches is up to the caller.
+    #[cfg(target_os = "linux")]
+    pub fn from_evdev(axis: RelativeAxisCode, value: i32) -> Option<Self> {
+        match (axis, value.signum()) {
+            (RelativeAxisCode::REL_WHEEL, 1) => Some(Self::Up),
+            (RelativeAxisCode::REL_WHEEL, -1) => Some(Self::Down),
+            (RelativeAxisCode::REL_HWHEEL, 1) => Some(Self::HorzRight),
+            (RelativeAxisCode::REL_HWHEEL, -1) => Some(Self::HorzLeft),
+            _ => None,
+        }
+    }
 }
 
 impl std::fmt::Display for MouseWheelButton {
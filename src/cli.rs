@@ -0,0 +1,454 @@
+// Copyright 2025 Jordan Johnson
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Subcommands that work on the configuration without the UI, for scripting.
+//!
+//! They read and write the same configuration file the UI does. Changes are saved the same way
+//! too, with a backup of the old file, and a running Reemap picks them up on its own.
+
+use crate::config;
+use crate::config_file;
+use crate::config_formats::{self, Format, SchemaKind};
+use crate::ipc;
+use crate::lint;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(clap::Subcommand, Debug)]
+pub enum Command {
+    /// Manage the profiles in Reemap's configuration
+    Profile {
+        #[command(subcommand)]
+        command: ProfileCommand,
+    },
+
+    /// Inspect Reemap's configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+
+    /// Control a running Reemap
+    Control {
+        #[command(subcommand)]
+        command: ControlCommand,
+    },
+
+    /// Measure how long Reemap takes to decide what to do with each input
+    #[cfg(feature = "bench")]
+    Bench {
+        #[clap(
+            long,
+            default_value_t = 10_000,
+            help = "How many times to run each stream of inputs"
+        )]
+        rounds: usize,
+    },
+
+    /// Print the JSON Schema for configurations or profiles written as JSON or TOML
+    Schema {
+        #[clap(value_enum, default_value_t = SchemaKind::Config)]
+        kind: SchemaKind,
+
+        #[clap(long, short, value_name = "FILE", help = "Write to a file instead")]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum ProfileCommand {
+    /// List every profile in priority order, and whether it is enabled
+    List,
+
+    /// Print a profile, or write it to a file
+    Export {
+        name: String,
+
+        #[clap(long, short, value_name = "FILE", help = "Write to a file instead")]
+        output: Option<PathBuf>,
+
+        #[clap(
+            long,
+            value_enum,
+            help = "[default: guessed from the file extension, else RON]"
+        )]
+        format: Option<Format>,
+    },
+
+    /// Add a profile from a file
+    Import {
+        file: PathBuf,
+
+        #[clap(
+            long,
+            value_enum,
+            help = "[default: guessed from the file extension, else RON]"
+        )]
+        format: Option<Format>,
+
+        #[clap(
+            long,
+            action,
+            help = "Replace a profile with the same name instead of failing"
+        )]
+        replace: bool,
+    },
+
+    /// Enable a profile
+    Enable { name: String },
+
+    /// Disable a profile
+    Disable { name: String },
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Look for problems in a configuration file
+    Check {
+        #[clap(
+            value_name = "FILE",
+            help = "Configuration to check [default: the one Reemap uses]"
+        )]
+        file: Option<PathBuf>,
+    },
+
+    /// Print where Reemap keeps its configuration file
+    Path,
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum ControlCommand {
+    /// Print the active profiles and layers as JSON
+    Status,
+
+    /// Keep a profile active no matter which window is in focus
+    Pin { name: String },
+
+    /// Go back to choosing profiles by the window in focus
+    Unpin,
+
+    /// Stop remapping until resumed
+    Pause,
+
+    /// Start remapping again
+    Resume,
+
+    /// Load the configuration file again
+    Reload,
+
+    /// Bring up Reemap's window
+    Show,
+
+    /// Print each change to the active profiles, layers, pin or pause as a line of JSON, until
+    /// Reemap exits
+    Subscribe {
+        #[clap(long, action, help = "Also print every button pressed and released")]
+        inputs: bool,
+    },
+}
+
+/// Reemap is a windowed program, so it has no console of its own to print to. Borrow the one it
+/// was started from, if any. Call this before a subcommand prints anything, `reemap schema` and
+/// `reemap config check` included, or the output goes nowhere.
+pub fn attach_console() {
+    #[cfg(windows)]
+    {
+        use windows::Win32::System::Console;
+        let _ = unsafe { Console::AttachConsole(Console::ATTACH_PARENT_PROCESS) };
+    }
+}
+
+/// Run a subcommand. Returns false if it failed.
+pub fn run(command: Command) -> bool {
+    let config_path = config_file::config_path();
+    let result = match command {
+        Command::Profile { command } => match command {
+            ProfileCommand::List => list_profiles(&config_path),
+            ProfileCommand::Export {
+                name,
+                output,
+                format,
+            } => export_profile(&config_path, &name, output.as_deref(), format),
+            ProfileCommand::Import {
+                file,
+                format,
+                replace,
+            } => import_profile(&config_path, &file, format, replace),
+            ProfileCommand::Enable { name } => set_profile_enabled(&config_path, &name, true),
+            ProfileCommand::Disable { name } => set_profile_enabled(&config_path, &name, false),
+        },
+        Command::Config {
+            command: ConfigCommand::Check { file },
+        } => {
+            return check_config(&file.unwrap_or(config_path));
+        }
+        Command::Config {
+            command: ConfigCommand::Path,
+        } => {
+            println!("{}", config_path.display());
+            Ok(())
+        }
+        Command::Control { command } => control(command),
+        #[cfg(feature = "bench")]
+        Command::Bench { rounds } => {
+            bench(rounds);
+            Ok(())
+        }
+        Command::Schema { kind, output } => {
+            write_output(output.as_deref(), &config_formats::schema(kind))
+        }
+    };
+    match result {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("error: {e}");
+            false
+        }
+    }
+}
+
+// Print any problems found in a configuration file. Returns false if there were any.
+fn check_config(path: &Path) -> bool {
+    let format = Format::from_path(path).unwrap_or(Format::Ron);
+    let config_str = match std::fs::read_to_string(path) {
+        Ok(s) => s,
+        Err(e) => {
+            println!("{}: could not read file: {e}", path.display());
+            return false;
+        }
+    };
+    let config = match config_formats::config_from_str(&config_str, format) {
+        Ok(versioned_config) => config::Config::from(versioned_config),
+        Err(e) => {
+            println!("{}: {e}", path.display());
+            return false;
+        }
+    };
+
+    let mut ok = true;
+    if let Err(e) = config.resolve_inheritance() {
+        println!("{}: {e}", path.display());
+        ok = false;
+    }
+    for warning in lint::check(&config) {
+        println!("{}: {warning}", path.display());
+        ok = false;
+    }
+    if ok {
+        println!("{}: no problems found", path.display());
+    }
+    ok
+}
+
+fn list_profiles(config_path: &Path) -> Result<()> {
+    let config = load(config_path)?;
+    for profile in config.profiles.iter() {
+        let state = if profile.enabled {
+            "enabled"
+        } else {
+            "disabled"
+        };
+        println!("{state}\t{}", profile.name);
+    }
+    Ok(())
+}
+
+fn export_profile(
+    config_path: &Path,
+    name: &str,
+    output: Option<&Path>,
+    format: Option<Format>,
+) -> Result<()> {
+    let config = load(config_path)?;
+    let profile = find_profile(&config, name)?;
+    let format = format
+        .or_else(|| output.and_then(Format::from_path))
+        .unwrap_or(Format::Ron);
+    let profile_str = config_formats::profile_to_string(
+        &config::VersionedProfile::from(profile.clone()),
+        format,
+    )?;
+    write_output(output, &profile_str)
+}
+
+fn import_profile(
+    config_path: &Path,
+    file: &Path,
+    format: Option<Format>,
+    replace: bool,
+) -> Result<()> {
+    let format = format
+        .or_else(|| Format::from_path(file))
+        .unwrap_or(Format::Ron);
+    let profile_str =
+        std::fs::read_to_string(file).map_err(|e| Error::CannotRead(file.to_path_buf(), e))?;
+    let (versioned_profile, skipped) =
+        config::count_skipped_remaps(|| config_formats::profile_from_str(&profile_str, format));
+    if skipped > 0 {
+        return Err(Error::WouldLoseRemaps(file.to_path_buf(), skipped));
+    }
+    let profile = config::Profile::from(versioned_profile?);
+
+    let mut config = load_for_editing(config_path)?;
+    let existing = config
+        .profiles
+        .iter()
+        .position(|existing| existing.name == profile.name);
+    match existing {
+        Some(idx) if replace => {
+            println!("replacing profile \"{}\"", profile.name);
+            config.profiles[idx] = profile;
+        }
+        Some(_) => return Err(Error::ProfileExists(profile.name)),
+        None => {
+            println!("adding profile \"{}\"", profile.name);
+            config.profiles.push(profile);
+        }
+    }
+    save(config_path, &config)
+}
+
+fn set_profile_enabled(config_path: &Path, name: &str, enabled: bool) -> Result<()> {
+    let mut config = load_for_editing(config_path)?;
+    let idx = config
+        .profiles
+        .iter()
+        .position(|profile| profile.name == name)
+        .ok_or_else(|| Error::NoSuchProfile(name.to_string()))?;
+    let state = if enabled { "enabled" } else { "disabled" };
+    // Leave the file alone, and skip the backup, if there is nothing to change.
+    if config.profiles[idx].enabled == enabled {
+        println!("profile \"{name}\" is already {state}");
+        return Ok(());
+    }
+    config.profiles[idx].enabled = enabled;
+    save(config_path, &config)?;
+    println!("profile \"{name}\" is now {state}");
+    Ok(())
+}
+
+fn control(command: ControlCommand) -> Result<()> {
+    let mut client = ipc::Client::connect()?;
+    let request = match command {
+        ControlCommand::Status => ipc::Request::Status,
+        ControlCommand::Pin { name } => ipc::Request::Pin {
+            profile: Some(name),
+        },
+        ControlCommand::Unpin => ipc::Request::Pin { profile: None },
+        ControlCommand::Pause => ipc::Request::Pause,
+        ControlCommand::Resume => ipc::Request::Resume,
+        ControlCommand::Reload => ipc::Request::Reload,
+        ControlCommand::Show => ipc::Request::Show,
+        ControlCommand::Subscribe { inputs } => {
+            for event in client.events(inputs)? {
+                println!(
+                    "{}",
+                    serde_json::to_string(&event?).expect("events should always serialize")
+                );
+            }
+            return Ok(());
+        }
+    };
+    if let ipc::Response::Status(status) = client.request(&request)? {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&status).expect("status should always serialize")
+        );
+    }
+    Ok(())
+}
+
+// Print a table of latency percentiles, one row per stream of inputs.
+#[cfg(feature = "bench")]
+fn bench(rounds: usize) {
+    println!("running each stream {rounds} times; inputs are not sent to Windows");
+    println!(
+        "{:<14}{:>10}{:>10}{:>10}{:>10}{:>10}{:>10}",
+        "stream", "inputs", "p50", "p90", "p99", "p99.9", "max"
+    );
+    for report in crate::hooks::bench::run(rounds) {
+        let cells = [report.p50, report.p90, report.p99, report.p999, report.max]
+            .map(|time| format!("{time:?}"));
+        println!(
+            "{:<14}{:>10}{:>10}{:>10}{:>10}{:>10}{:>10}",
+            report.stream, report.inputs, cells[0], cells[1], cells[2], cells[3], cells[4]
+        );
+    }
+}
+
+fn find_profile<'a>(config: &'a config::Config, name: &str) -> Result<&'a config::Profile> {
+    config
+        .profiles
+        .iter()
+        .find(|profile| profile.name == name)
+        .ok_or_else(|| Error::NoSuchProfile(name.to_string()))
+}
+
+// A missing configuration file is treated the same way as on first launch: as the default one.
+// This way profiles can be deployed to a machine before Reemap has ever run there.
+pub fn load(config_path: &Path) -> Result<config::Config> {
+    if !config_path.exists() {
+        return Ok(config::Config::from(config::VersionedConfig::default()));
+    }
+    let config_str = std::fs::read_to_string(config_path)
+        .map_err(|e| Error::CannotRead(config_path.to_path_buf(), e))?;
+    let versioned_config = config_formats::config_from_str(&config_str, Format::Ron)?;
+    Ok(config::Config::from(versioned_config))
+}
+
+// Loading a configuration to change and save it again. Remaps skipped while reading would be gone
+// from the saved file, so the file is left as it is instead.
+fn load_for_editing(config_path: &Path) -> Result<config::Config> {
+    let (config, skipped) = config::count_skipped_remaps(|| load(config_path));
+    if skipped > 0 {
+        return Err(Error::WouldLoseRemaps(config_path.to_path_buf(), skipped));
+    }
+    config
+}
+
+fn save(config_path: &Path, config: &config::Config) -> Result<()> {
+    if let Some(dir) = config_path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| Error::CannotWrite(dir.to_path_buf(), e))?;
+    }
+    config_file::save_config(config_path, config)?;
+    Ok(())
+}
+
+// Write to a file if one was given, else print.
+fn write_output(output: Option<&Path>, contents: &str) -> Result<()> {
+    match output {
+        Some(path) => {
+            std::fs::write(path, contents).map_err(|e| Error::CannotWrite(path.to_path_buf(), e))
+        }
+        None => {
+            println!("{contents}");
+            Ok(())
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("could not read {}: {}", .0.display(), .1)]
+    CannotRead(PathBuf, #[source] std::io::Error),
+    #[error("could not write {}: {}", .0.display(), .1)]
+    CannotWrite(PathBuf, #[source] std::io::Error),
+    #[error(transparent)]
+    Format(#[from] config_formats::Error),
+    #[error(transparent)]
+    Save(#[from] config_file::Error),
+    #[error(transparent)]
+    Control(#[from] ipc::Error),
+    #[error(
+        "{} has remaps this version of Reemap does not understand ({} skipped); saving would lose them",
+        .0.display(),
+        .1
+    )]
+    WouldLoseRemaps(PathBuf, usize),
+    #[error("no profile is named \"{0}\"")]
+    NoSuchProfile(String),
+    #[error("a profile named \"{0}\" already exists; pass --replace to replace it")]
+    ProfileExists(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
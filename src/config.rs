@@ -2,41 +2,42 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
 use crate::buttons;
+use crate::config_migrations;
 use enum_map::EnumMap;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
+use thiserror::Error;
 
 pub const REMAP_SMALLVEC_LEN: usize = 8;
 pub type Output = SmallVec<[buttons::Button; REMAP_SMALLVEC_LEN]>;
 
 // -------------------- VersionedConfig --------------------
-// Preparing for the future when the Config struct may change.
+// One variant per version of the config format. See config_migrations.rs before changing.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 #[serde(tag = "version")]
 pub enum VersionedConfig {
-    V1(Config),
+    V1(config_migrations::v1::Config),
+    V2(Config),
 }
 
 impl Default for VersionedConfig {
     fn default() -> Self {
-        Self::V1(Config::default())
+        Self::V2(Config::default())
     }
 }
 
 // Even with future versions, this From<> will be from the latest Config to VersionedConfig.
 impl From<Config> for VersionedConfig {
     fn from(value: Config) -> Self {
-        Self::V1(value)
+        Self::V2(value)
     }
 }
 
-// In future versions, this From<> will need a little more logic to do migration to the newest
-// config version. Right now, as there's only one version of the config, there are no migrations.
+// Migrates older versions to the latest Config.
 impl From<VersionedConfig> for Config {
     fn from(value: VersionedConfig) -> Self {
-        match value {
-            VersionedConfig::V1(config_ui) => config_ui,
-        }
+        config_migrations::migrate_config(value)
     }
 }
 
@@ -46,21 +47,157 @@ pub struct Config {
     pub background: Background,
     pub show_rare_keys: bool,
     pub profiles: Vec<Profile>,
+    #[serde(default)]
+    pub pin_hotkey: Option<Hotkey>,
+    // Serve the input overlay for streaming on this port.
+    #[serde(default)]
+    pub overlay_port: Option<u16>,
 }
 
-// -------------------- Background --------------------
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
-pub enum Background {
-    Ginso,
-    Gradient,
+impl Config {
+    /// Produce a copy of this configuration where every profile has its parent's remaps and layers
+    /// merged in. This is what the hook thread runs; it never sees the inheritance itself.
+    pub fn resolve_inheritance(&self) -> Result<Config, InheritanceError> {
+        let profiles = (0..self.profiles.len())
+            .map(|profile_idx| self.resolve_profile(profile_idx))
+            .collect::<Result<_, _>>()?;
+        Ok(Config {
+            profiles,
+            ..self.clone()
+        })
+    }
+
+    /// Resolve a single profile, merging in everything it inherits.
+    pub fn resolve_profile(&self, profile_idx: usize) -> Result<Profile, InheritanceError> {
+        // Walk up the chain of parents, remembering where we've been to catch cycles.
+        let mut chain = vec![profile_idx];
+        let mut current = profile_idx;
+        while let Some(parent_name) = &self.profiles[current].parent {
+            let Some(parent_idx) = self.find_profile(parent_name) else {
+                return Err(InheritanceError::MissingParent {
+                    profile: self.profiles[current].name.clone(),
+                    parent: parent_name.clone(),
+                });
+            };
+            if chain.contains(&parent_idx) {
+                return Err(InheritanceError::Cycle {
+                    profile: self.profiles[profile_idx].name.clone(),
+                });
+            }
+            chain.push(parent_idx);
+            current = parent_idx;
+        }
+
+        // Then apply each profile on top of its parent, starting from the top-most template.
+        let mut chain = chain.into_iter().rev();
+        let root = chain
+            .next()
+            .expect("chain always contains the profile itself");
+        let mut resolved = self.profiles[root].clone();
+        for idx in chain {
+            resolved = self.profiles[idx].inherit_from(&resolved);
+        }
+        Ok(resolved)
+    }
+
+    /// The fully-resolved parent of a profile, if it has one.
+    pub fn resolve_parent(&self, profile_idx: usize) -> Result<Option<Profile>, InheritanceError> {
+        let Some(parent_name) = &self.profiles[profile_idx].parent else {
+            return Ok(None);
+        };
+        let Some(parent_idx) = self.find_profile(parent_name) else {
+            return Err(InheritanceError::MissingParent {
+                profile: self.profiles[profile_idx].name.clone(),
+                parent: parent_name.clone(),
+            });
+        };
+        if parent_idx == profile_idx {
+            return Err(InheritanceError::Cycle {
+                profile: self.profiles[profile_idx].name.clone(),
+            });
+        }
+        self.resolve_profile(parent_idx).map(Some)
+    }
+
+    // Profile names are not guaranteed to be unique. The first one wins.
+    fn find_profile(&self, name: &str) -> Option<usize> {
+        self.profiles
+            .iter()
+            .position(|profile| profile.name == name)
+    }
+}
+
+// -------------------- InheritanceError --------------------
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum InheritanceError {
+    #[error("profile {profile} inherits from {parent}, which does not exist")]
+    MissingParent { profile: String, parent: String },
+    #[error("profile {profile} ends up inheriting from itself")]
+    Cycle { profile: String },
+}
+
+// -------------------- Hotkey --------------------
+// A global hotkey. Used to pin the current profile so it stays active no matter which window is in
+// focus.
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
+)]
+pub struct Hotkey {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub win: bool,
+    pub key: buttons::key::KeyButton,
 }
 
-impl Default for Background {
+impl Default for Hotkey {
     fn default() -> Self {
-        Self::Ginso
+        Self {
+            ctrl: true,
+            alt: true,
+            shift: false,
+            win: false,
+            key: buttons::key::KeyButton::P,
+        }
+    }
+}
+
+impl std::fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let modifiers = [
+            (self.ctrl, "Ctrl"),
+            (self.alt, "Alt"),
+            (self.shift, "Shift"),
+            (self.win, "Win"),
+        ];
+        for (_, name) in modifiers.iter().filter(|(held, _)| *held) {
+            write!(f, "{name} + ")?;
+        }
+        write!(f, "{}", self.key)
     }
 }
 
+// -------------------- Background --------------------
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+pub enum Background {
+    #[default]
+    Ginso,
+    Gradient,
+}
+
 impl std::fmt::Display for Background {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -72,33 +209,31 @@ impl std::fmt::Display for Background {
 
 // -------------------- VersionedProfile --------------------
 // A separate versioned Profile is necessary, because profiles can be shared independently.
-// Preparing for the future when the Profile struct may change.
+// Versioned the same way as VersionedConfig.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 #[serde(tag = "version")]
 pub enum VersionedProfile {
-    V1(Profile),
+    V1(config_migrations::v1::Profile),
+    V2(Profile),
 }
 
 impl Default for VersionedProfile {
     fn default() -> Self {
-        Self::V1(Profile::default())
+        Self::V2(Profile::default())
     }
 }
 
 // Even with future versions, this From<> will be from the latest Profile to VersionedProfile.
 impl From<Profile> for VersionedProfile {
     fn from(value: Profile) -> Self {
-        Self::V1(value)
+        Self::V2(value)
     }
 }
 
-// In future versions, this From<> will need a little more logic to do migration to the newest
-// config version. Right now, as there's only one version of the config, there are no migrations.
+// Migrates older versions to the latest Profile.
 impl From<VersionedProfile> for Profile {
     fn from(value: VersionedProfile) -> Self {
-        match value {
-            VersionedProfile::V1(profile) => profile,
-        }
+        config_migrations::migrate_profile(value)
     }
 }
 
@@ -111,6 +246,12 @@ pub struct Profile {
     pub base: BaseLayer,
     pub layers: Vec<Layer>,
     pub clip_cursor: bool,
+    #[serde(default)]
+    pub parent: Option<String>,
+    #[serde(default)]
+    pub clip_area: ClipArea,
+    #[serde(default)]
+    pub clip_inset: i32, // Pixels to shrink the clip area by on each side
 }
 
 impl Default for Profile {
@@ -122,23 +263,103 @@ impl Default for Profile {
             base: BaseLayer::default(),
             layers: Vec::new(),
             clip_cursor: false,
+            parent: None,
+            clip_area: ClipArea::default(),
+            clip_inset: 0,
         }
     }
 }
 
+impl Profile {
+    // Merge a (resolved) parent profile underneath this one.
+    //  -   Buttons this profile's base layer leaves as Inherit take the parent's policy. Any other
+    //      policy, NoRemap included, overrides the parent's.
+    //  -   A layer with the same name as one of the parent's layers takes the parent's policy for
+    //      every button it defers. A layer's Defer means "not set" in both places: to the layers
+    //      below it and to the parent's layer. NoRemap overrides the parent's layer.
+    //  -   The parent's remaining layers come after this profile's own layers.
+    // Everything else, like the name and condition, belongs to this profile.
+    fn inherit_from(&self, parent: &Profile) -> Profile {
+        let mut result = self.clone();
+        for (button, policy) in result.base.policy.iter_mut() {
+            if *policy == BaseRemapPolicy::Inherit {
+                *policy = parent.base.policy[button].clone();
+            }
+        }
+        for layer in result.layers.iter_mut() {
+            if let Some(parent_layer) = parent.layers.iter().find(|pl| pl.name == layer.name) {
+                for (button, policy) in layer.policy.iter_mut() {
+                    if *policy == RemapPolicy::Defer {
+                        *policy = parent_layer.policy[button].clone();
+                    }
+                }
+            }
+        }
+        result.layers.extend(
+            parent
+                .layers
+                .iter()
+                .filter(|pl| !self.layers.iter().any(|layer| layer.name == pl.name))
+                .cloned(),
+        );
+        result
+    }
+}
+
 impl std::fmt::Display for Profile {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.name)
     }
 }
 
+// -------------------- ClipArea --------------------
+// Where to confine the cursor when a profile clips it.
+#[derive(
+    Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
+)]
+pub enum ClipArea {
+    // The whole window, including borders and the title bar
+    #[default]
+    Window,
+    // Just the window's contents
+    ClientArea,
+    // The monitor the window is on
+    Monitor,
+    // A small box of this many pixels in the middle of the window
+    CenterLock {
+        size: i32,
+    },
+}
+
+impl ClipArea {
+    pub const DEFAULT_CENTER_LOCK_SIZE: i32 = 100;
+}
+
+impl std::fmt::Display for ClipArea {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Window => write!(f, "Window"),
+            Self::ClientArea => write!(f, "Window contents"),
+            Self::Monitor => write!(f, "Monitor"),
+            Self::CenterLock { .. } => write!(f, "Center box"),
+        }
+    }
+}
+
 // -------------------- ProfileCondition --------------------
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
+)]
 pub enum ProfileCondition {
     Always,
     TitleAndProcess { title: String, process: String },
     Title { title: String },
     Process { process: String },
+    WindowClass { class: String },
+    ExecutablePath { path: String },
+    AncestorProcess { process: String },
+    All(Vec<ProfileCondition>),
+    Any(Vec<ProfileCondition>),
 }
 
 impl Default for ProfileCondition {
@@ -153,7 +374,9 @@ impl Default for ProfileCondition {
 impl ProfileCondition {
     pub fn helper_text(&self) -> String {
         match self {
-            Self::Always => "Always active, regardless of which window is in focus".to_string(),
+            Self::Always => {
+                "Always active, underneath any profile that matches the window in focus".to_string()
+            }
             Self::TitleAndProcess { title, process } => {
                 format!("Active when {title} ({process}) is in focus")
             }
@@ -163,6 +386,45 @@ impl ProfileCondition {
             Self::Process { process } => {
                 format!("Active when the process {process} is in focus")
             }
+            Self::WindowClass { class } => {
+                format!("Active when a window of class {class} is in focus")
+            }
+            Self::ExecutablePath { path } => {
+                format!("Active when the program {path} is in focus")
+            }
+            Self::AncestorProcess { process } => {
+                format!("Active when a program launched by {process} is in focus")
+            }
+            Self::All(_) | Self::Any(_) => {
+                format!(
+                    "Active when the focused window matches {}",
+                    self.criteria_text()
+                )
+            }
+        }
+    }
+
+    // A short description of what this condition checks, used to describe combined conditions.
+    fn criteria_text(&self) -> String {
+        fn join(conditions: &[ProfileCondition]) -> String {
+            itertools::Itertools::intersperse(
+                conditions.iter().map(|condition| condition.criteria_text()),
+                String::from(", "),
+            )
+            .collect()
+        }
+        match self {
+            Self::Always => "anything".to_string(),
+            Self::TitleAndProcess { title, process } => {
+                format!("title {title} and process {process}")
+            }
+            Self::Title { title } => format!("title {title}"),
+            Self::Process { process } => format!("process {process}"),
+            Self::WindowClass { class } => format!("class {class}"),
+            Self::ExecutablePath { path } => format!("path {path}"),
+            Self::AncestorProcess { process } => format!("launched by {process}"),
+            Self::All(conditions) => format!("all of ({})", join(conditions)),
+            Self::Any(conditions) => format!("any of ({})", join(conditions)),
         }
     }
 }
@@ -170,6 +432,7 @@ impl ProfileCondition {
 // -------------------- BaseLayer --------------------
 #[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct BaseLayer {
+    #[serde(deserialize_with = "deserialize_policy")]
     pub policy: EnumMap<buttons::Button, BaseRemapPolicy>,
 }
 
@@ -180,7 +443,11 @@ pub struct Layer {
     pub enabled: bool,
     pub layer_type: LayerType,
     pub condition: Vec<buttons::HoldButton>,
+    #[serde(deserialize_with = "deserialize_policy")]
     pub policy: EnumMap<buttons::Button, RemapPolicy>,
+    // Confine the cursor while this layer is active, using the profile's clip area.
+    #[serde(default)]
+    pub clip_cursor: bool,
 }
 
 impl Default for Layer {
@@ -191,6 +458,7 @@ impl Default for Layer {
             layer_type: LayerType::default(),
             condition: Vec::new(),
             policy: EnumMap::default(),
+            clip_cursor: false,
         }
     }
 }
@@ -224,8 +492,11 @@ impl Layer {
 }
 
 // -------------------- LayerType --------------------
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[derive(
+    Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
+)]
 pub enum LayerType {
+    #[default]
     Modifier,
     Toggle,
 }
@@ -239,29 +510,25 @@ impl std::fmt::Display for LayerType {
     }
 }
 
-impl Default for LayerType {
-    fn default() -> Self {
-        Self::Modifier
-    }
-}
-
 // -------------------- BaseRemapPolicy --------------------
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[derive(
+    Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
+)]
 pub enum BaseRemapPolicy {
+    // Not decided here: the parent profile's policy if it has one, or else whatever the next
+    // profile in the stack does. Nothing touches the button if no profile decides.
+    #[default]
+    Inherit,
+    // Send the button as it is, even if a parent or a profile lower in the stack remaps it.
     NoRemap,
     Remap(Output),
     Suppress,
 }
 
-impl Default for BaseRemapPolicy {
-    fn default() -> Self {
-        Self::NoRemap
-    }
-}
-
 impl std::fmt::Display for BaseRemapPolicy {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            Self::Inherit => write!(f, "(not set)"),
             Self::NoRemap => write!(f, "(do not remap)"),
             Self::Remap(maps) => {
                 if maps.is_empty() {
@@ -280,8 +547,11 @@ impl std::fmt::Display for BaseRemapPolicy {
 }
 
 // -------------------- RemapPolicy --------------------
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[derive(
+    Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
+)]
 pub enum RemapPolicy {
+    #[default]
     Defer,
     NoRemap,
     Remap(Output),
@@ -309,8 +579,502 @@ impl std::fmt::Display for RemapPolicy {
     }
 }
 
-impl Default for RemapPolicy {
-    fn default() -> Self {
-        Self::Defer
+// -------------------- Reading policies --------------------
+// A configuration saved by a newer version of Reemap may name buttons or policies this version
+// does not know. Rather than refusing the whole file, skip just those entries. Buttons missing from
+// the file get the default policy.
+//
+// Buttons and policies are read by name and matched by hand, since a wrapper that tries one type
+// and falls back to another would have to buffer each entry first. A policy this version does not
+// know can only be skipped if it holds no data; one that does still fails the file, which then goes
+// through config_salvage.rs.
+pub(crate) fn deserialize_policy<'de, D, P>(
+    deserializer: D,
+) -> Result<EnumMap<buttons::Button, P>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    P: PolicyVariants,
+{
+    struct PolicyVisitor<P>(std::marker::PhantomData<P>);
+
+    impl<'de, P> serde::de::Visitor<'de> for PolicyVisitor<P>
+    where
+        P: PolicyVariants,
+    {
+        type Value = EnumMap<buttons::Button, P>;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "a map from buttons to remap policies")
+        }
+
+        fn visit_map<A>(self, mut access: A) -> Result<Self::Value, A::Error>
+        where
+            A: serde::de::MapAccess<'de>,
+        {
+            let mut policy = EnumMap::default();
+            while let Some(NamedButton(button)) = access.next_key()? {
+                let button = match button {
+                    Ok(button) => button,
+                    Err(name) => {
+                        access.next_value::<serde::de::IgnoredAny>()?;
+                        SKIPPED_REMAPS.set(SKIPPED_REMAPS.get() + 1);
+                        tracing::warn!(button = name, "skipped a remap of an unknown button");
+                        continue;
+                    }
+                };
+                match access.next_value::<NamedPolicy<P>>()?.0 {
+                    Ok(button_policy) => policy[button] = button_policy,
+                    Err(name) => {
+                        SKIPPED_REMAPS.set(SKIPPED_REMAPS.get() + 1);
+                        tracing::warn!(%button, policy = name, "skipped an unknown remap policy");
+                    }
+                }
+            }
+            Ok(policy)
+        }
+    }
+
+    deserializer.deserialize_map(PolicyVisitor(std::marker::PhantomData))
+}
+
+thread_local! {
+    // Remaps skipped on this thread so far.
+    static SKIPPED_REMAPS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// Run `read`, and count the remaps it skipped because this version of Reemap does not understand
+/// them. Whatever was read that way must not be saved over the original.
+pub fn count_skipped_remaps<T>(read: impl FnOnce() -> T) -> (T, usize) {
+    let before = SKIPPED_REMAPS.get();
+    let value = read();
+    (value, SKIPPED_REMAPS.get() - before)
+}
+
+// Both policy types: the variants without data are found by name, and remaps are built here.
+pub(crate) trait PolicyVariants: serde::de::DeserializeOwned + Default {
+    fn remap(output: Output) -> Self;
+}
+
+impl PolicyVariants for BaseRemapPolicy {
+    fn remap(output: Output) -> Self {
+        Self::Remap(output)
+    }
+}
+
+impl PolicyVariants for RemapPolicy {
+    fn remap(output: Output) -> Self {
+        Self::Remap(output)
+    }
+}
+
+// A variant name, or a unit variant, as written in the file.
+struct Identifier(String);
+
+impl<'de> Deserialize<'de> for Identifier {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct IdentifierVisitor;
+
+        impl serde::de::Visitor<'_> for IdentifierVisitor {
+            type Value = Identifier;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "a name")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(Identifier(v.to_string()))
+            }
+        }
+
+        deserializer.deserialize_identifier(IdentifierVisitor)
+    }
+}
+
+// Look up a unit variant by its name.
+fn from_name<T: serde::de::DeserializeOwned>(name: &str) -> Option<T> {
+    use serde::de::IntoDeserializer;
+    let deserializer: serde::de::value::StrDeserializer<serde::de::value::Error> =
+        name.into_deserializer();
+    T::deserialize(deserializer).ok()
+}
+
+// A button, or how it was written if this version does not know it.
+struct NamedButton(Result<buttons::Button, String>);
+
+impl<'de> Deserialize<'de> for NamedButton {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ButtonVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for ButtonVisitor {
+            type Value = NamedButton;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "a button")
+            }
+
+            fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::EnumAccess<'de>,
+            {
+                use serde::de::VariantAccess;
+                let (Identifier(kind), variant) = data.variant()?;
+                let button = match kind.as_str() {
+                    "Key" | "Mouse" | "Wheel" => {
+                        let Identifier(name) = variant.newtype_variant()?;
+                        match kind.as_str() {
+                            "Key" => from_name(&name).map(buttons::Button::Key),
+                            "Mouse" => from_name(&name).map(buttons::Button::Mouse),
+                            _ => from_name(&name).map(buttons::Button::Wheel),
+                        }
+                        .ok_or(format!("{kind}({name})"))
+                    }
+                    // Every kind of button holds the button's name.
+                    _ => {
+                        variant.newtype_variant::<serde::de::IgnoredAny>()?;
+                        Err(format!("{kind}(..)"))
+                    }
+                };
+                Ok(NamedButton(button))
+            }
+        }
+
+        deserializer.deserialize_enum("Button", &["Key", "Mouse", "Wheel"], ButtonVisitor)
+    }
+}
+
+// A policy, or its name if this version does not know it or a button it remaps to.
+struct NamedPolicy<P>(Result<P, String>);
+
+impl<'de, P: PolicyVariants> Deserialize<'de> for NamedPolicy<P> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct PolicyVisitor<P>(std::marker::PhantomData<P>);
+
+        impl<'de, P: PolicyVariants> serde::de::Visitor<'de> for PolicyVisitor<P> {
+            type Value = NamedPolicy<P>;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "a remap policy")
+            }
+
+            fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::EnumAccess<'de>,
+            {
+                use serde::de::VariantAccess;
+                let (Identifier(name), variant) = data.variant()?;
+                if name == "Remap" {
+                    let outputs: Vec<NamedButton> = variant.newtype_variant()?;
+                    let output: Result<Output, String> = outputs
+                        .into_iter()
+                        .map(|NamedButton(button)| button)
+                        .collect();
+                    return Ok(NamedPolicy(
+                        output
+                            .map(P::remap)
+                            .map_err(|button| format!("Remap to {button}")),
+                    ));
+                }
+                variant.unit_variant()?;
+                Ok(NamedPolicy(from_name(&name).ok_or(name)))
+            }
+        }
+
+        deserializer.deserialize_enum(
+            "Policy",
+            &["Defer", "Inherit", "NoRemap", "Remap", "Suppress"],
+            PolicyVisitor(std::marker::PhantomData),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buttons::Button;
+    use crate::buttons::key::KeyButton;
+    use crate::buttons::mouse::MouseButton;
+    use crate::buttons::wheel::MouseWheelButton;
+    use smallvec::smallvec;
+
+    fn save(config: &Config) -> String {
+        ron::ser::to_string_pretty(
+            &VersionedConfig::from(config.clone()),
+            ron::ser::PrettyConfig::new(),
+        )
+        .unwrap()
+    }
+
+    fn load(config_str: &str) -> Config {
+        Config::from(ron::from_str::<VersionedConfig>(config_str).unwrap())
+    }
+
+    #[test]
+    fn nested_conditions_are_described_in_full() {
+        let condition = ProfileCondition::All(vec![
+            ProfileCondition::WindowClass {
+                class: String::from("UnityWndClass"),
+            },
+            ProfileCondition::Any(vec![
+                ProfileCondition::AncestorProcess {
+                    process: String::from("steam.exe"),
+                },
+                ProfileCondition::ExecutablePath {
+                    path: String::from(r"C:\Games\game.exe"),
+                },
+            ]),
+        ]);
+        assert_eq!(
+            condition.helper_text(),
+            r"Active when the focused window matches all of (class UnityWndClass, any of (launched by steam.exe, path C:\Games\game.exe))"
+        );
+    }
+
+    #[test]
+    fn remaps_survive_saving_and_loading() {
+        let mut profile = Profile {
+            name: String::from("Game"),
+            ..Profile::default()
+        };
+        profile.base.policy[Button::Key(KeyButton::CAPITAL)] =
+            BaseRemapPolicy::Remap(smallvec![Button::Key(KeyButton::ESCAPE)]);
+        profile.base.policy[Button::Mouse(MouseButton::X1)] = BaseRemapPolicy::Suppress;
+        let mut layer = Layer::default();
+        layer.policy[Button::Wheel(MouseWheelButton::Up)] = RemapPolicy::Remap(smallvec![
+            Button::Key(KeyButton::CONTROL),
+            Button::Key(KeyButton::TAB)
+        ]);
+        layer.policy[Button::Key(KeyButton::Q)] = RemapPolicy::NoRemap;
+        profile.layers.push(layer);
+        let config = Config {
+            profiles: vec![profile],
+            ..Config::default()
+        };
+
+        assert_eq!(load(&save(&config)), config);
+    }
+
+    #[test]
+    fn unknown_remaps_are_skipped_and_the_rest_kept() {
+        let config_str = r#"(
+            version: "V2",
+            background: Gradient,
+            show_rare_keys: false,
+            profiles: [(
+                name: "Game",
+                enabled: true,
+                condition: Always,
+                base: (policy: {
+                    Key(NOT_A_KEY): Suppress,
+                    Key(F1): Remap([Key(F2)]),
+                    Gamepad(A): Remap([Key(SPACE)]),
+                    Key(F3): Remap([Key(NOT_A_KEY)]),
+                    Key(F4): Suppress,
+                    Key(F5): SomethingNew,
+                    Key(F6): Remap([Mouse(Left), Wheel(Down)]),
+                }),
+                layers: [(
+                    name: "Layer",
+                    enabled: true,
+                    layer_type: Modifier,
+                    condition: [],
+                    policy: {Mouse(NOT_A_BUTTON): Defer, Key(A): NoRemap},
+                )],
+                clip_cursor: false,
+            )],
+        )"#;
+        let (config, skipped) = count_skipped_remaps(|| load(config_str));
+        assert_eq!(skipped, 5);
+        let policy = &config.profiles[0].base.policy;
+
+        assert_eq!(
+            policy[Button::Key(KeyButton::F1)],
+            BaseRemapPolicy::Remap(smallvec![Button::Key(KeyButton::F2)])
+        );
+        assert_eq!(
+            policy[Button::Key(KeyButton::F4)],
+            BaseRemapPolicy::Suppress
+        );
+        assert_eq!(
+            policy[Button::Key(KeyButton::F6)],
+            BaseRemapPolicy::Remap(smallvec![
+                Button::Mouse(MouseButton::Left),
+                Button::Wheel(MouseWheelButton::Down)
+            ])
+        );
+        assert_eq!(policy[Button::Key(KeyButton::F3)], BaseRemapPolicy::Inherit);
+        assert_eq!(policy[Button::Key(KeyButton::F5)], BaseRemapPolicy::Inherit);
+        let remaps = policy
+            .values()
+            .filter(|policy| **policy != BaseRemapPolicy::Inherit)
+            .count();
+        assert_eq!(remaps, 3);
+
+        let layer = &config.profiles[0].layers[0].policy;
+        assert_eq!(layer[Button::Key(KeyButton::A)], RemapPolicy::NoRemap);
+    }
+
+    fn inheriting(name: &str, parent: Option<&str>) -> Profile {
+        Profile {
+            name: name.to_string(),
+            parent: parent.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn profiles_inherit_through_every_level() {
+        let remap = |key| BaseRemapPolicy::Remap(smallvec![Button::Key(key)]);
+        let mut template = inheriting("Template", None);
+        template.base.policy[Button::Key(KeyButton::A)] = remap(KeyButton::X);
+        template.base.policy[Button::Key(KeyButton::B)] = remap(KeyButton::X);
+        template.base.policy[Button::Key(KeyButton::C)] = remap(KeyButton::X);
+        let mut shared_layer = Layer {
+            name: String::from("Shift"),
+            ..Default::default()
+        };
+        shared_layer.policy[Button::Key(KeyButton::D)] =
+            RemapPolicy::Remap(smallvec![Button::Key(KeyButton::X)]);
+        shared_layer.policy[Button::Key(KeyButton::E)] =
+            RemapPolicy::Remap(smallvec![Button::Key(KeyButton::X)]);
+        template.layers.push(shared_layer);
+        template.layers.push(Layer {
+            name: String::from("Template only"),
+            ..Default::default()
+        });
+
+        let mut middle = inheriting("Middle", Some("Template"));
+        middle.base.policy[Button::Key(KeyButton::B)] = remap(KeyButton::Y);
+        middle.base.policy[Button::Key(KeyButton::C)] = BaseRemapPolicy::NoRemap;
+        let mut middle_layer = Layer {
+            name: String::from("Shift"),
+            ..Default::default()
+        };
+        middle_layer.policy[Button::Key(KeyButton::E)] = RemapPolicy::NoRemap;
+        middle.layers.push(middle_layer);
+
+        let mut game = inheriting("Game", Some("Middle"));
+        game.base.policy[Button::Key(KeyButton::C)] = remap(KeyButton::Z);
+        let config = Config {
+            profiles: vec![game, middle, template],
+            ..Default::default()
+        };
+
+        let resolved = config.resolve_inheritance().unwrap();
+        let game = &resolved.profiles[0];
+        assert_eq!(game.name, "Game");
+        assert_eq!(
+            game.base.policy[Button::Key(KeyButton::A)],
+            remap(KeyButton::X)
+        );
+        assert_eq!(
+            game.base.policy[Button::Key(KeyButton::B)],
+            remap(KeyButton::Y)
+        );
+        assert_eq!(
+            game.base.policy[Button::Key(KeyButton::C)],
+            remap(KeyButton::Z)
+        );
+        assert_eq!(
+            game.base.policy[Button::Key(KeyButton::F)],
+            BaseRemapPolicy::Inherit
+        );
+        // NoRemap in the middle overrides the template's remap.
+        assert_eq!(
+            resolved.profiles[1].base.policy[Button::Key(KeyButton::C)],
+            BaseRemapPolicy::NoRemap
+        );
+
+        let layer_names: Vec<_> = game
+            .layers
+            .iter()
+            .map(|layer| layer.name.as_str())
+            .collect();
+        assert_eq!(layer_names, ["Shift", "Template only"]);
+        assert_eq!(
+            game.layers[0].policy[Button::Key(KeyButton::D)],
+            RemapPolicy::Remap(smallvec![Button::Key(KeyButton::X)])
+        );
+        assert_eq!(
+            game.layers[0].policy[Button::Key(KeyButton::E)],
+            RemapPolicy::NoRemap
+        );
+        assert_eq!(resolved.profiles[2], config.profiles[2]);
+    }
+
+    #[test]
+    fn v1_profiles_inherit_their_parents_remaps() {
+        // V1 saved every button it did not remap as NoRemap.
+        let fixture = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join("profile")
+            .join("v1-initial.ron");
+        let versioned: VersionedProfile =
+            ron::from_str(&std::fs::read_to_string(fixture).unwrap()).unwrap();
+        let mut game = Profile::from(versioned);
+        game.parent = Some(String::from("Template"));
+
+        let remap = |key| BaseRemapPolicy::Remap(smallvec![Button::Key(key)]);
+        let mut template = inheriting("Template", None);
+        template.base.policy[Button::Key(KeyButton::A)] = remap(KeyButton::X);
+        template.base.policy[Button::Key(KeyButton::CAPITAL)] = remap(KeyButton::X);
+        let config = Config {
+            profiles: vec![game, template],
+            ..Default::default()
+        };
+
+        let resolved = config.resolve_inheritance().unwrap();
+        let game = &resolved.profiles[0].base.policy;
+        assert_eq!(game[Button::Key(KeyButton::A)], remap(KeyButton::X));
+        // The profile's own remaps still win.
+        assert_eq!(
+            game[Button::Key(KeyButton::CAPITAL)],
+            remap(KeyButton::ESCAPE)
+        );
+    }
+
+    #[test]
+    fn broken_inheritance_is_reported() {
+        let missing = Config {
+            profiles: vec![
+                inheriting("Game", Some("Middle")),
+                inheriting("Middle", Some("Gone")),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            missing.resolve_inheritance(),
+            Err(InheritanceError::MissingParent {
+                profile: String::from("Middle"),
+                parent: String::from("Gone"),
+            })
+        );
+
+        let cycle = Config {
+            profiles: vec![
+                inheriting("Game", Some("A")),
+                inheriting("A", Some("B")),
+                inheriting("B", Some("A")),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            cycle.resolve_inheritance(),
+            Err(InheritanceError::Cycle {
+                profile: String::from("Game"),
+            })
+        );
+
+        let own_parent = Config {
+            profiles: vec![inheriting("Game", Some("Game"))],
+            ..Default::default()
+        };
+        assert_eq!(
+            own_parent.resolve_parent(0),
+            Err(InheritanceError::Cycle {
+                profile: String::from("Game"),
+            })
+        );
+        assert!(own_parent.resolve_inheritance().is_err());
     }
 }
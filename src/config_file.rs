@@ -0,0 +1,320 @@
+// Copyright 2025 Jordan Johnson
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Saving the configuration file without ever leaving a half-written one behind, and keeping
+//! backups of the ones it replaces.
+
+use crate::config;
+use etcetera::BaseStrategy;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use tracing::{debug, warn};
+
+// How many old configurations to keep. The oldest are deleted first.
+pub const MAX_BACKUPS: usize = 10;
+
+const BACKUP_DIR: &str = "backups";
+const BACKUP_PREFIX: &str = "config-";
+
+/// %APPDATA%\Reemap
+pub fn reemap_dir() -> PathBuf {
+    etcetera::choose_base_strategy()
+        .unwrap()
+        .config_dir()
+        .join("Reemap")
+}
+
+/// %APPDATA%\Reemap\config.ron
+pub fn config_path() -> PathBuf {
+    reemap_dir().join("config.ron")
+}
+
+/// The local time, like "2025-06-01_14-03-22". These sort in order and are safe in file names.
+#[cfg(windows)]
+pub fn timestamp() -> String {
+    use windows::Win32::System::SystemInformation as SI;
+
+    let now = unsafe { SI::GetLocalTime() };
+    format!(
+        "{:04}-{:02}-{:02}_{:02}-{:02}-{:02}",
+        now.wYear, now.wMonth, now.wDay, now.wHour, now.wMinute, now.wSecond
+    )
+}
+
+/// The time in UTC, like "2025-06-01_14-03-22". The standard library knows nothing of time zones,
+/// and UTC sorts just as well.
+#[cfg(not(windows))]
+pub fn timestamp() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let (days, secs) = (secs / 86_400, secs % 86_400);
+
+    // Days since 1970-01-01 to a date. From Howard Hinnant's civil_from_days:
+    // https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}_{:02}-{:02}-{:02}",
+        secs / 3_600,
+        secs % 3_600 / 60,
+        secs % 60
+    )
+}
+
+/// Replace a file's contents all at once. The new contents are written to a temporary file next
+/// to it, then renamed over it, so a crash or full disk leaves either the old file or the new one.
+pub fn write_atomic(path: &Path, contents: &str) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    let temp_path = PathBuf::from(temp_path);
+
+    let result = (|| {
+        let mut file = std::fs::File::create(&temp_path)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+        drop(file);
+        std::fs::rename(&temp_path, path)
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Copy a configuration file that was not read in full to config.<why>-<time>.ron next to it,
+/// before saving gets a chance to replace it. Unlike backups, these copies are never deleted.
+pub fn copy_aside(config_path: &Path, why: &str) -> std::io::Result<PathBuf> {
+    let stem = config_path.file_stem().map_or_else(
+        || String::from("config"),
+        |stem| stem.to_string_lossy().into_owned(),
+    );
+    let copy_path = config_path.with_file_name(format!("{stem}.{why}-{}.ron", timestamp()));
+    std::fs::copy(config_path, &copy_path)?;
+    Ok(copy_path)
+}
+
+/// Save the configuration, first backing up whatever was there before. Saving the same
+/// configuration again does nothing, so backups are only taken of real changes.
+pub fn save_config(config_path: &Path, config: &config::Config) -> Result<()> {
+    let config_str = ron::ser::to_string_pretty(
+        &config::VersionedConfig::from(config.clone()),
+        ron::ser::PrettyConfig::new(),
+    )
+    .expect("configuration should always serialize");
+    match std::fs::read_to_string(config_path) {
+        Ok(old_str) if old_str == config_str => {
+            debug!("configuration unchanged, not saving");
+            return Ok(());
+        }
+        Ok(_) => back_up(config_path)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+        // Not readable as text, but there may still be something worth keeping.
+        Err(_) => back_up(config_path)?,
+    }
+    write_atomic(config_path, &config_str).map_err(Error::CannotWrite)?;
+    prune_backups(config_path);
+    Ok(())
+}
+
+// Backups live in a folder next to the configuration file.
+fn backup_dir(config_path: &Path) -> PathBuf {
+    config_path
+        .parent()
+        .expect("configuration file should be inside a directory")
+        .join(BACKUP_DIR)
+}
+
+fn back_up(config_path: &Path) -> Result<()> {
+    let dir = backup_dir(config_path);
+    std::fs::create_dir_all(&dir).map_err(Error::CannotBackUp)?;
+    // Timestamps only go down to the second, so saves within the same second are numbered. The
+    // numbers sort after the first backup of that second.
+    let stamp = timestamp();
+    let backup_path = (0..)
+        .map(|n| match n {
+            0 => dir.join(format!("{BACKUP_PREFIX}{stamp}.ron")),
+            n => dir.join(format!("{BACKUP_PREFIX}{stamp}_{n:03}.ron")),
+        })
+        .find(|path| !path.exists())
+        .expect("some backup name should be free");
+    debug!("backing up configuration to {}", backup_path.display());
+    std::fs::copy(config_path, backup_path).map_err(Error::CannotBackUp)?;
+    Ok(())
+}
+
+fn prune_backups(config_path: &Path) {
+    let backups = backup_paths(config_path);
+    for old in backups.iter().skip(MAX_BACKUPS) {
+        if let Err(e) = std::fs::remove_file(old) {
+            warn!("could not remove old backup {}: {e}", old.display());
+        }
+    }
+}
+
+// Newest first.
+fn backup_paths(config_path: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(backup_dir(config_path)) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(BACKUP_PREFIX) && name.ends_with(".ron"))
+        })
+        .collect();
+    // The timestamps sort the same as the times they stand for.
+    paths.sort();
+    paths.reverse();
+    paths
+}
+
+// -------------------- Backup --------------------
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backup {
+    pub path: PathBuf,
+    // When the backup was taken, for display.
+    pub taken: String,
+    // None if the backup could not be read.
+    pub config: Option<config::Config>,
+}
+
+impl Backup {
+    pub fn summary(&self) -> String {
+        match &self.config {
+            None => String::from("(could not be read)"),
+            Some(config) if config.profiles.is_empty() => String::from("No profiles"),
+            Some(config) => {
+                let names: Vec<&str> = config
+                    .profiles
+                    .iter()
+                    .map(|profile| profile.name.as_str())
+                    .collect();
+                let noun = if names.len() == 1 {
+                    "profile"
+                } else {
+                    "profiles"
+                };
+                format!("{} {noun}: {}", names.len(), names.join(", "))
+            }
+        }
+    }
+}
+
+/// Every backup of the configuration, newest first.
+pub fn list_backups(config_path: &Path) -> Vec<Backup> {
+    backup_paths(config_path)
+        .into_iter()
+        .map(|path| {
+            let config = std::fs::read_to_string(&path)
+                .ok()
+                .and_then(|config_str| ron::from_str::<config::VersionedConfig>(&config_str).ok())
+                .map(config::Config::from);
+            // config-2025-06-01_14-03-22.ron, or config-2025-06-01_14-03-22_001.ron
+            // -> 2025-06-01 14:03:22
+            let taken = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.strip_prefix(BACKUP_PREFIX))
+                .and_then(|stamp| stamp.split_once('_'))
+                .map(|(date, time)| {
+                    let time = time.split('_').next().unwrap_or_default();
+                    format!("{date} {}", time.replace('-', ":"))
+                })
+                .unwrap_or_default();
+            Backup {
+                path,
+                taken,
+                config,
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("could not back up the old configuration: {0}")]
+    CannotBackUp(#[source] std::io::Error),
+    #[error("could not write the configuration file: {0}")]
+    CannotWrite(#[source] std::io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_changes_are_backed_up() {
+        let dir = std::env::temp_dir().join(format!("reemap-backups-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let config_path = dir.join("config.ron");
+        let mut config = config::Config::default();
+
+        save_config(&config_path, &config).unwrap();
+        assert!(list_backups(&config_path).is_empty());
+        save_config(&config_path, &config).unwrap();
+        assert!(list_backups(&config_path).is_empty());
+
+        // Several saves in the same second each keep their own backup.
+        for n in 0..3 {
+            config.profiles.push(config::Profile {
+                name: format!("Profile {n}"),
+                ..Default::default()
+            });
+            save_config(&config_path, &config).unwrap();
+        }
+        let backups = list_backups(&config_path);
+        let profile_counts: Vec<usize> = backups
+            .iter()
+            .map(|backup| backup.config.as_ref().unwrap().profiles.len())
+            .collect();
+        assert_eq!(profile_counts[..3], [2, 1, 0]);
+        assert!(backups.iter().all(|backup| backup.taken.len() == 19));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn copies_aside_keep_the_original() {
+        let dir = std::env::temp_dir().join(format!("reemap-copy-aside-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let config_path = dir.join("config.ron");
+        std::fs::write(&config_path, "(version: \"V9\")").unwrap();
+
+        let copy_path = copy_aside(&config_path, "newer").unwrap();
+        assert_eq!(copy_path.parent(), Some(dir.as_path()));
+        let name = copy_path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .into_owned();
+        assert!(
+            name.starts_with("config.newer-") && name.ends_with(".ron"),
+            "{name}"
+        );
+        assert_eq!(
+            std::fs::read_to_string(&copy_path).unwrap(),
+            "(version: \"V9\")"
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,514 @@
+// Copyright 2025 Jordan Johnson
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Reading and writing configurations and profiles as RON, JSON, or TOML.
+//!
+//! RON is Reemap's own format. JSON and TOML are here for tools that generate profiles. Neither
+//! allows a button as a map key, so in those formats the remaps are written as a list holding only
+//! the buttons that are actually remapped.
+//!
+//! The JSON Schema for those formats is derived from the same types, so it cannot drift from what
+//! Reemap actually reads.
+
+use crate::buttons;
+use crate::config;
+use crate::config_migrations::v1;
+use enum_map::EnumMap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, clap::ValueEnum)]
+pub enum Format {
+    Ron,
+    Json,
+    Toml,
+}
+
+impl Format {
+    pub const EXTENSIONS: [&str; 3] = ["ron", "json", "toml"];
+
+    /// Guess the format from a file's extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "ron" => Some(Self::Ron),
+            "json" => Some(Self::Json),
+            "toml" => Some(Self::Toml),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ron => write!(f, "RON"),
+            Self::Json => write!(f, "JSON"),
+            Self::Toml => write!(f, "TOML"),
+        }
+    }
+}
+
+pub fn config_to_string(config: &config::VersionedConfig, format: Format) -> Result<String> {
+    match format {
+        Format::Ron => Ok(ron::ser::to_string_pretty(
+            config,
+            ron::ser::PrettyConfig::new(),
+        )?),
+        Format::Json => Ok(serde_json::to_string_pretty(&PortableConfig::from(config))?),
+        Format::Toml => Ok(toml::to_string_pretty(&PortableConfig::from(config))?),
+    }
+}
+
+pub fn config_from_str(s: &str, format: Format) -> Result<config::VersionedConfig> {
+    match format {
+        Format::Ron => Ok(ron::from_str(s)?),
+        Format::Json => Ok(serde_json::from_str::<PortableConfig>(s)?.into()),
+        Format::Toml => Ok(toml::from_str::<PortableConfig>(s)?.into()),
+    }
+}
+
+pub fn profile_to_string(profile: &config::VersionedProfile, format: Format) -> Result<String> {
+    match format {
+        Format::Ron => Ok(ron::ser::to_string_pretty(
+            profile,
+            ron::ser::PrettyConfig::new(),
+        )?),
+        Format::Json => Ok(serde_json::to_string_pretty(&PortableProfile::from(
+            profile,
+        ))?),
+        Format::Toml => Ok(toml::to_string_pretty(&PortableProfile::from(profile))?),
+    }
+}
+
+pub fn profile_from_str(s: &str, format: Format) -> Result<config::VersionedProfile> {
+    match format {
+        Format::Ron => Ok(ron::from_str(s)?),
+        Format::Json => Ok(serde_json::from_str::<PortableProfile>(s)?.into()),
+        Format::Toml => Ok(toml::from_str::<PortableProfile>(s)?.into()),
+    }
+}
+
+// -------------------- JSON Schema --------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, clap::ValueEnum)]
+pub enum SchemaKind {
+    Config,
+    Profile,
+}
+
+/// The JSON Schema for configurations or profiles written as JSON or TOML.
+pub fn schema(kind: SchemaKind) -> String {
+    let schema = match kind {
+        SchemaKind::Config => schemars::schema_for!(PortableConfig),
+        SchemaKind::Profile => schemars::schema_for!(PortableProfile),
+    };
+    serde_json::to_string_pretty(&schema).expect("a schema should always serialize")
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("could not write RON: {0}")]
+    RonSer(#[from] ron::Error),
+    #[error("could not read RON: {0}")]
+    RonDe(#[from] ron::error::SpannedError),
+    #[error("could not read or write JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("could not write TOML: {0}")]
+    TomlSer(#[from] toml::ser::Error),
+    #[error("could not read TOML: {0}")]
+    TomlDe(#[from] toml::de::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+// -------------------- Portable types --------------------
+// These mirror the types in config.rs, except remaps are sparse lists instead of maps.
+// Like the config itself, they are tagged with a version.
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "version")]
+#[schemars(rename = "Config", title = "Reemap configuration")]
+enum PortableConfig {
+    V1(PortableConfigV1),
+    V2(PortableConfigV2),
+}
+
+impl From<&config::VersionedConfig> for PortableConfig {
+    fn from(value: &config::VersionedConfig) -> Self {
+        match value {
+            config::VersionedConfig::V1(config) => Self::V1(PortableConfigV1::from(config)),
+            config::VersionedConfig::V2(config) => Self::V2(PortableConfigV2::from(config)),
+        }
+    }
+}
+
+impl From<PortableConfig> for config::VersionedConfig {
+    fn from(value: PortableConfig) -> Self {
+        match value {
+            PortableConfig::V1(config) => Self::V1(v1::Config::from(config)),
+            PortableConfig::V2(config) => Self::V2(config::Config::from(config)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "ConfigV2")]
+struct PortableConfigV2 {
+    background: config::Background,
+    show_rare_keys: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pin_hotkey: Option<config::Hotkey>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    overlay_port: Option<u16>,
+    #[serde(default)]
+    profiles: Vec<PortableProfileV2>,
+}
+
+impl From<&config::Config> for PortableConfigV2 {
+    fn from(value: &config::Config) -> Self {
+        Self {
+            background: value.background,
+            show_rare_keys: value.show_rare_keys,
+            pin_hotkey: value.pin_hotkey.clone(),
+            overlay_port: value.overlay_port,
+            profiles: value.profiles.iter().map(PortableProfileV2::from).collect(),
+        }
+    }
+}
+
+impl From<PortableConfigV2> for config::Config {
+    fn from(value: PortableConfigV2) -> Self {
+        Self {
+            background: value.background,
+            show_rare_keys: value.show_rare_keys,
+            pin_hotkey: value.pin_hotkey,
+            overlay_port: value.overlay_port,
+            profiles: value
+                .profiles
+                .into_iter()
+                .map(config::Profile::from)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "version")]
+#[schemars(rename = "Profile", title = "Reemap profile")]
+enum PortableProfile {
+    V1(PortableProfileV1),
+    V2(PortableProfileV2),
+}
+
+impl From<&config::VersionedProfile> for PortableProfile {
+    fn from(value: &config::VersionedProfile) -> Self {
+        match value {
+            config::VersionedProfile::V1(profile) => Self::V1(PortableProfileV1::from(profile)),
+            config::VersionedProfile::V2(profile) => Self::V2(PortableProfileV2::from(profile)),
+        }
+    }
+}
+
+impl From<PortableProfile> for config::VersionedProfile {
+    fn from(value: PortableProfile) -> Self {
+        match value {
+            PortableProfile::V1(profile) => Self::V1(v1::Profile::from(profile)),
+            PortableProfile::V2(profile) => Self::V2(config::Profile::from(profile)),
+        }
+    }
+}
+
+// Scalars come first; TOML wants plain values before any tables.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "ProfileV2")]
+struct PortableProfileV2 {
+    name: String,
+    #[serde(default = "default_true")]
+    enabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parent: Option<String>,
+    #[serde(default)]
+    clip_cursor: bool,
+    #[serde(default)]
+    clip_inset: i32,
+    #[serde(default)]
+    clip_area: config::ClipArea,
+    #[serde(default)]
+    condition: config::ProfileCondition,
+    #[serde(default)]
+    base: Vec<PortableRemap<config::BaseRemapPolicy>>,
+    #[serde(default)]
+    layers: Vec<PortableLayer>,
+}
+
+impl From<&config::Profile> for PortableProfileV2 {
+    fn from(value: &config::Profile) -> Self {
+        Self {
+            name: value.name.clone(),
+            enabled: value.enabled,
+            parent: value.parent.clone(),
+            clip_cursor: value.clip_cursor,
+            clip_inset: value.clip_inset,
+            clip_area: value.clip_area.clone(),
+            condition: value.condition.clone(),
+            base: to_sparse(&value.base.policy),
+            layers: value.layers.iter().map(PortableLayer::from).collect(),
+        }
+    }
+}
+
+impl From<PortableProfileV2> for config::Profile {
+    fn from(value: PortableProfileV2) -> Self {
+        Self {
+            name: value.name,
+            enabled: value.enabled,
+            condition: value.condition,
+            base: config::BaseLayer {
+                policy: from_sparse(value.base),
+            },
+            layers: value.layers.into_iter().map(config::Layer::from).collect(),
+            clip_cursor: value.clip_cursor,
+            parent: value.parent,
+            clip_area: value.clip_area,
+            clip_inset: value.clip_inset,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "Layer")]
+struct PortableLayer {
+    name: String,
+    #[serde(default = "default_true")]
+    enabled: bool,
+    #[serde(default)]
+    clip_cursor: bool,
+    #[serde(default)]
+    layer_type: config::LayerType,
+    #[serde(default)]
+    condition: Vec<buttons::HoldButton>,
+    #[serde(default)]
+    remaps: Vec<PortableRemap<config::RemapPolicy>>,
+}
+
+impl From<&config::Layer> for PortableLayer {
+    fn from(value: &config::Layer) -> Self {
+        Self {
+            name: value.name.clone(),
+            enabled: value.enabled,
+            clip_cursor: value.clip_cursor,
+            layer_type: value.layer_type.clone(),
+            condition: value.condition.clone(),
+            remaps: to_sparse(&value.policy),
+        }
+    }
+}
+
+impl From<PortableLayer> for config::Layer {
+    fn from(value: PortableLayer) -> Self {
+        Self {
+            name: value.name,
+            enabled: value.enabled,
+            layer_type: value.layer_type,
+            condition: value.condition,
+            policy: from_sparse(value.remaps),
+            clip_cursor: value.clip_cursor,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "Remap_for_{P}")]
+struct PortableRemap<P> {
+    input: buttons::Button,
+    policy: P,
+}
+
+// Buttons left at the default policy (inherit for the base layer, defer for layers) are left out.
+fn to_sparse<P>(map: &EnumMap<buttons::Button, P>) -> Vec<PortableRemap<P>>
+where
+    P: Clone + Default + PartialEq,
+{
+    map.iter()
+        .filter(|(_, policy)| **policy != P::default())
+        .map(|(input, policy)| PortableRemap {
+            input,
+            policy: policy.clone(),
+        })
+        .collect()
+}
+
+// If the same button shows up more than once, the last one wins.
+fn from_sparse<P>(remaps: Vec<PortableRemap<P>>) -> EnumMap<buttons::Button, P>
+where
+    P: Default,
+{
+    let mut map = EnumMap::default();
+    for remap in remaps {
+        map[remap.input] = remap.policy;
+    }
+    map
+}
+
+fn default_true() -> bool {
+    true
+}
+
+// -------------------- Portable V1 --------------------
+// Frozen along with config_migrations::v1. The base layer has no Inherit here.
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "ConfigV1")]
+struct PortableConfigV1 {
+    background: config::Background,
+    show_rare_keys: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pin_hotkey: Option<config::Hotkey>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    overlay_port: Option<u16>,
+    #[serde(default)]
+    profiles: Vec<PortableProfileV1>,
+}
+
+impl From<&v1::Config> for PortableConfigV1 {
+    fn from(value: &v1::Config) -> Self {
+        Self {
+            background: value.background,
+            show_rare_keys: value.show_rare_keys,
+            pin_hotkey: value.pin_hotkey.clone(),
+            overlay_port: value.overlay_port,
+            profiles: value.profiles.iter().map(PortableProfileV1::from).collect(),
+        }
+    }
+}
+
+impl From<PortableConfigV1> for v1::Config {
+    fn from(value: PortableConfigV1) -> Self {
+        Self {
+            background: value.background,
+            show_rare_keys: value.show_rare_keys,
+            pin_hotkey: value.pin_hotkey,
+            overlay_port: value.overlay_port,
+            profiles: value.profiles.into_iter().map(v1::Profile::from).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "ProfileV1")]
+struct PortableProfileV1 {
+    name: String,
+    #[serde(default = "default_true")]
+    enabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parent: Option<String>,
+    #[serde(default)]
+    clip_cursor: bool,
+    #[serde(default)]
+    clip_inset: i32,
+    #[serde(default)]
+    clip_area: config::ClipArea,
+    #[serde(default)]
+    condition: config::ProfileCondition,
+    #[serde(default)]
+    base: Vec<PortableRemap<v1::BaseRemapPolicy>>,
+    #[serde(default)]
+    layers: Vec<PortableLayer>,
+}
+
+impl From<&v1::Profile> for PortableProfileV1 {
+    fn from(value: &v1::Profile) -> Self {
+        Self {
+            name: value.name.clone(),
+            enabled: value.enabled,
+            parent: value.parent.clone(),
+            clip_cursor: value.clip_cursor,
+            clip_inset: value.clip_inset,
+            clip_area: value.clip_area.clone(),
+            condition: value.condition.clone(),
+            base: to_sparse(&value.base.policy),
+            layers: value.layers.iter().map(PortableLayer::from).collect(),
+        }
+    }
+}
+
+impl From<PortableProfileV1> for v1::Profile {
+    fn from(value: PortableProfileV1) -> Self {
+        Self {
+            name: value.name,
+            enabled: value.enabled,
+            condition: value.condition,
+            base: v1::BaseLayer {
+                policy: from_sparse(value.base),
+            },
+            layers: value.layers.into_iter().map(config::Layer::from).collect(),
+            clip_cursor: value.clip_cursor,
+            parent: value.parent,
+            clip_area: value.clip_area,
+            clip_inset: value.clip_inset,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(kind: &str) -> String {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join(kind)
+            .join(crate::config_migrations::LATEST_FIXTURE);
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn configs_read_back_the_same_in_every_format() {
+        let config =
+            config::Config::from(config_from_str(&fixture("config"), Format::Ron).unwrap());
+        for format in [Format::Ron, Format::Json, Format::Toml] {
+            let config_str =
+                config_to_string(&config::VersionedConfig::from(config.clone()), format).unwrap();
+            let read_back = config::Config::from(config_from_str(&config_str, format).unwrap());
+            assert_eq!(read_back, config, "{format} changed the configuration");
+        }
+    }
+
+    #[test]
+    fn profiles_read_back_the_same_in_every_format() {
+        let profile =
+            config::Profile::from(profile_from_str(&fixture("profile"), Format::Ron).unwrap());
+        for format in [Format::Ron, Format::Json, Format::Toml] {
+            let profile_str =
+                profile_to_string(&config::VersionedProfile::from(profile.clone()), format)
+                    .unwrap();
+            let read_back = config::Profile::from(profile_from_str(&profile_str, format).unwrap());
+            assert_eq!(read_back, profile, "{format} changed the profile");
+        }
+    }
+
+    #[test]
+    fn only_buttons_with_a_policy_are_written() {
+        use crate::buttons::Button;
+        use crate::buttons::key::KeyButton;
+        use smallvec::smallvec;
+
+        let mut profile = config::Profile::default();
+        profile.base.policy[Button::Key(KeyButton::A)] =
+            config::BaseRemapPolicy::Remap(smallvec![Button::Key(KeyButton::B)]);
+        profile.base.policy[Button::Key(KeyButton::C)] = config::BaseRemapPolicy::NoRemap;
+        let mut layer = config::Layer::default();
+        layer.policy[Button::Key(KeyButton::D)] = config::RemapPolicy::Suppress;
+        profile.layers.push(layer);
+
+        let profile_str =
+            profile_to_string(&config::VersionedProfile::from(profile), Format::Json).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&profile_str).unwrap();
+        assert_eq!(json["base"].as_array().unwrap().len(), 2);
+        assert_eq!(json["layers"][0]["remaps"].as_array().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,227 @@
+// Copyright 2025 Jordan Johnson
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Brings configurations and profiles saved by older versions of Reemap up to date.
+
+/*
+    Every saved configuration and profile is tagged with the version of the format it was saved
+    in, and VersionedConfig and VersionedProfile have one variant per version. Loading a file
+    walks it forward one version at a time (V1 -> V2 -> ... -> latest), so each migration only has
+    to know about the version right before it.
+
+    Fields added with #[serde(default)] do not need a new version; older files still load, and
+    older versions of Reemap ignore the fields they do not know about.
+
+    V2 added Inherit to the base layer. V1 saved NoRemap for every button that was not remapped,
+    which now means "do not remap, whatever the parent or the profiles underneath do", so V1 files
+    are read into the frozen types in v1.rs and their NoRemap becomes Inherit.
+
+    When a change cannot be made that way:
+
+    1.  Copy the types as they are today into a new module here, e.g. config_migrations::v1. These
+        copies are frozen and never change again. Point VersionedConfig::V1 and
+        VersionedProfile::V1 at them. Types that did not change, like Layer, can stay shared
+        until they do.
+
+    2.  Make the change in config.rs. Add VersionedConfig::V2 and VersionedProfile::V2 holding the
+        live types, and have From<Config> for VersionedConfig produce V2.
+
+    3.  Write From<v1::Config> for the V2 types, and add a step to migrate_config and
+        migrate_profile below, like so:
+
+            VersionedConfig::V1(config) => migrate_config(VersionedConfig::V2(config.into())),
+
+    4.  Give the JSON and TOML forms in config_formats.rs the same treatment.
+
+    5.  Save a configuration and a profile with the old version of Reemap and add them to
+        tests/fixtures. Every file there must load into the latest types with nothing lost.
+
+    The v1-initial fixtures are what the first release saved, and v1-latest what the last release
+    with V1 saved. The fixtures for the newest version, now v2-latest, are what this version
+    saves. A test checks that byte for byte, so after adding a field they have to be saved again
+    (with the field's default) rather than edited by hand. Once a newer version exists, they are
+    frozen like the rest.
+*/
+
+pub mod v1;
+
+use crate::config::{Config, Profile, VersionedConfig, VersionedProfile};
+
+// Files saved by this version are checked against these fixtures.
+#[cfg(test)]
+pub(crate) const LATEST_FIXTURE: &str = "v2-latest.ron";
+
+pub fn migrate_config(versioned_config: VersionedConfig) -> Config {
+    match versioned_config {
+        VersionedConfig::V1(config) => migrate_config(VersionedConfig::V2(config.into())),
+        // The latest version.
+        VersionedConfig::V2(config) => config,
+    }
+}
+
+pub fn migrate_profile(versioned_profile: VersionedProfile) -> Profile {
+    match versioned_profile {
+        VersionedProfile::V1(profile) => migrate_profile(VersionedProfile::V2(profile.into())),
+        // The latest version.
+        VersionedProfile::V2(profile) => profile,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buttons::key::KeyButton;
+    use crate::buttons::mouse::MouseButton;
+    use crate::buttons::wheel::MouseWheelButton;
+    use crate::buttons::{Button, HoldButton};
+    use crate::config::{BaseRemapPolicy, LayerType, ProfileCondition, RemapPolicy};
+    use smallvec::smallvec;
+
+    // Every fixture in a directory, so a new fixture is tested as soon as it is added.
+    fn fixtures(kind: &str) -> Vec<(std::path::PathBuf, String)> {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join(kind);
+        let fixtures: Vec<_> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .map(|path| {
+                let contents = std::fs::read_to_string(&path).unwrap();
+                (path, contents)
+            })
+            .collect();
+        assert!(
+            fixtures
+                .iter()
+                .any(|(path, _)| path.file_name().is_some_and(|name| name == LATEST_FIXTURE)),
+            "no {kind} fixture for the latest version"
+        );
+        fixtures
+    }
+
+    // The latest version's fixtures are exactly what this version of Reemap saves, so they stay a
+    // real on-disk format. Adding a field with #[serde(default)] means saving them again.
+    fn assert_saved_as_latest(path: &std::path::Path, contents: &str, saved: &str) {
+        if path.file_name().is_some_and(|name| name == LATEST_FIXTURE) {
+            assert_eq!(
+                contents.replace("\r\n", "\n"),
+                saved,
+                "{} is not what Reemap saves now",
+                path.display()
+            );
+        }
+    }
+
+    // All fixtures hold the same "Game" profile, saved by different versions.
+    fn assert_game_profile(profile: &Profile) {
+        assert_eq!(profile.name, "Game");
+        assert!(profile.enabled);
+        assert_eq!(
+            profile.condition,
+            ProfileCondition::Process {
+                process: String::from("game.exe")
+            }
+        );
+
+        let base = &profile.base.policy;
+        assert_eq!(
+            base[Button::Key(KeyButton::CAPITAL)],
+            BaseRemapPolicy::Remap(smallvec![Button::Key(KeyButton::ESCAPE)])
+        );
+        assert_eq!(
+            base[Button::Key(KeyButton::F1)],
+            BaseRemapPolicy::Remap(smallvec![
+                Button::Key(KeyButton::CONTROL),
+                Button::Key(KeyButton::C)
+            ])
+        );
+        assert_eq!(
+            base[Button::Mouse(MouseButton::X1)],
+            BaseRemapPolicy::Suppress
+        );
+        // V1 saved every other button as NoRemap; they must come out as not set.
+        assert_eq!(base[Button::Key(KeyButton::A)], BaseRemapPolicy::Inherit);
+        let base_remaps = base
+            .values()
+            .filter(|policy| **policy != BaseRemapPolicy::Inherit)
+            .count();
+        assert_eq!(base_remaps, 3);
+
+        assert_eq!(profile.layers.len(), 1);
+        let layer = &profile.layers[0];
+        assert_eq!(layer.name, "Alt fire");
+        assert!(layer.enabled);
+        assert_eq!(layer.layer_type, LayerType::Modifier);
+        assert_eq!(layer.condition, vec![HoldButton::Mouse(MouseButton::Right)]);
+        assert_eq!(
+            layer.policy[Button::Mouse(MouseButton::Left)],
+            RemapPolicy::Remap(smallvec![Button::Key(KeyButton::F)])
+        );
+        assert_eq!(
+            layer.policy[Button::Key(KeyButton::Q)],
+            RemapPolicy::NoRemap
+        );
+        assert_eq!(
+            layer.policy[Button::Wheel(MouseWheelButton::Up)],
+            RemapPolicy::Suppress
+        );
+        let layer_remaps = layer
+            .policy
+            .values()
+            .filter(|policy| **policy != RemapPolicy::Defer)
+            .count();
+        assert_eq!(layer_remaps, 3);
+    }
+
+    #[test]
+    fn config_fixtures_load_into_latest() {
+        for (path, contents) in fixtures("config") {
+            let versioned_config: VersionedConfig = ron::from_str(&contents)
+                .unwrap_or_else(|e| panic!("{} did not parse: {e}", path.display()));
+            let config = migrate_config(versioned_config);
+
+            assert_eq!(config.background, crate::config::Background::Gradient);
+            assert!(config.show_rare_keys);
+            assert_eq!(config.profiles.len(), 2);
+            assert_game_profile(&config.profiles[0]);
+            let everywhere = &config.profiles[1];
+            assert_eq!(everywhere.name, "Everywhere");
+            assert!(!everywhere.enabled);
+            assert_eq!(everywhere.condition, ProfileCondition::Always);
+            assert_eq!(
+                everywhere.base.policy[Button::Wheel(MouseWheelButton::HorzLeft)],
+                BaseRemapPolicy::Remap(smallvec![Button::Key(KeyButton::BROWSER_BACK)])
+            );
+
+            // Saving and loading again must not change anything either.
+            let saved = ron::ser::to_string_pretty(
+                &VersionedConfig::from(config.clone()),
+                ron::ser::PrettyConfig::new(),
+            )
+            .unwrap();
+            let reloaded = migrate_config(ron::from_str(&saved).unwrap());
+            assert_eq!(reloaded, config, "{} changed after saving", path.display());
+            assert_saved_as_latest(&path, &contents, &saved);
+        }
+    }
+
+    #[test]
+    fn profile_fixtures_load_into_latest() {
+        for (path, contents) in fixtures("profile") {
+            let versioned_profile: VersionedProfile = ron::from_str(&contents)
+                .unwrap_or_else(|e| panic!("{} did not parse: {e}", path.display()));
+            let profile = migrate_profile(versioned_profile);
+            assert_game_profile(&profile);
+
+            let saved = ron::ser::to_string_pretty(
+                &VersionedProfile::from(profile.clone()),
+                ron::ser::PrettyConfig::new(),
+            )
+            .unwrap();
+            let reloaded = migrate_profile(ron::from_str(&saved).unwrap());
+            assert_eq!(reloaded, profile, "{} changed after saving", path.display());
+            assert_saved_as_latest(&path, &contents, &saved);
+        }
+    }
+}
@@ -0,0 +1,114 @@
+// Copyright 2025 Jordan Johnson
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! The types that changed after V1, as they were in V1. Frozen: these never change again.
+//!
+//! In V1 the base layer had no Inherit. Every button the user had not remapped was saved as
+//! NoRemap, so NoRemap there means "not set", not "do not remap".
+
+use crate::buttons;
+use crate::config::{self, ClipArea, Hotkey, Layer, ProfileCondition};
+use enum_map::EnumMap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// -------------------- Config --------------------
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Config {
+    pub background: config::Background,
+    pub show_rare_keys: bool,
+    pub profiles: Vec<Profile>,
+    #[serde(default)]
+    pub pin_hotkey: Option<Hotkey>,
+    #[serde(default)]
+    pub overlay_port: Option<u16>,
+}
+
+impl From<Config> for config::Config {
+    fn from(value: Config) -> Self {
+        Self {
+            background: value.background,
+            show_rare_keys: value.show_rare_keys,
+            profiles: value
+                .profiles
+                .into_iter()
+                .map(config::Profile::from)
+                .collect(),
+            pin_hotkey: value.pin_hotkey,
+            overlay_port: value.overlay_port,
+        }
+    }
+}
+
+// -------------------- Profile --------------------
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    pub enabled: bool,
+    pub condition: ProfileCondition,
+    pub base: BaseLayer,
+    pub layers: Vec<Layer>,
+    pub clip_cursor: bool,
+    #[serde(default)]
+    pub parent: Option<String>,
+    #[serde(default)]
+    pub clip_area: ClipArea,
+    #[serde(default)]
+    pub clip_inset: i32,
+}
+
+impl From<Profile> for config::Profile {
+    fn from(value: Profile) -> Self {
+        Self {
+            name: value.name,
+            enabled: value.enabled,
+            condition: value.condition,
+            base: value.base.into(),
+            layers: value.layers,
+            clip_cursor: value.clip_cursor,
+            parent: value.parent,
+            clip_area: value.clip_area,
+            clip_inset: value.clip_inset,
+        }
+    }
+}
+
+// -------------------- BaseLayer --------------------
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct BaseLayer {
+    #[serde(deserialize_with = "config::deserialize_policy")]
+    pub policy: EnumMap<buttons::Button, BaseRemapPolicy>,
+}
+
+// NoRemap was the only way to leave a button alone, so it becomes Inherit. Kept as NoRemap, every
+// button an app profile does not remap would hide the Always profiles underneath it and the
+// profile's parent.
+impl From<BaseLayer> for config::BaseLayer {
+    fn from(value: BaseLayer) -> Self {
+        Self {
+            policy: value.policy.map(|_, policy| match policy {
+                BaseRemapPolicy::NoRemap => config::BaseRemapPolicy::Inherit,
+                BaseRemapPolicy::Remap(output) => config::BaseRemapPolicy::Remap(output),
+                BaseRemapPolicy::Suppress => config::BaseRemapPolicy::Suppress,
+            }),
+        }
+    }
+}
+
+// -------------------- BaseRemapPolicy --------------------
+#[derive(
+    Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
+)]
+#[schemars(rename = "BaseRemapPolicyV1")]
+pub enum BaseRemapPolicy {
+    #[default]
+    NoRemap,
+    Remap(config::Output),
+    Suppress,
+}
+
+impl config::PolicyVariants for BaseRemapPolicy {
+    fn remap(output: config::Output) -> Self {
+        Self::Remap(output)
+    }
+}
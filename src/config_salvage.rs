@@ -0,0 +1,390 @@
+// Copyright 2025 Jordan Johnson
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Recovering what can be recovered from a configuration file that does not parse.
+//!
+//! A single bad profile, or a button that only a newer version of Reemap knows about, makes the
+//! whole file fail to parse. Rather than throw everything away, this splits the file into its
+//! settings and profiles by looking only at brackets, strings, and comments, then parses each
+//! piece on its own. Whatever parses is kept; the profiles that do not are reported.
+
+use crate::config::{Config, Profile};
+use crate::config_migrations::v1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Salvaged {
+    pub config: Config,
+    pub lost: Vec<LostProfile>,
+}
+
+// A profile that could not be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LostProfile {
+    // None if not even the name could be read.
+    pub name: Option<String>,
+    // Where the profile starts in the file, counting from 1.
+    pub line: usize,
+    pub error: String,
+}
+
+impl std::fmt::Display for LostProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "\"{name}\" (line {}): {}", self.line, self.error),
+            None => write!(f, "unnamed profile (line {}): {}", self.line, self.error),
+        }
+    }
+}
+
+/// Recover the settings and every profile that still parses. Returns None if the file does not
+/// look like a configuration at all.
+pub fn salvage(config_str: &str) -> Option<Salvaged> {
+    let (body_start, body) = enclosed(config_str)?;
+    let mut config = Config::default();
+    let mut lost = Vec::new();
+    let fields = split_top_level(body);
+    // Profiles saved as V1 need migrating, like the rest of the file would have been.
+    let v1 = fields
+        .iter()
+        .filter_map(|(_, field)| split_field(field))
+        .any(|(name, _, value)| {
+            name == "version" && ron::from_str::<String>(value).is_ok_and(|v| v == "V1")
+        });
+
+    // Settings that cannot be read keep their default.
+    for (field_start, field) in fields {
+        let Some((name, value_start, value)) = split_field(field) else {
+            continue;
+        };
+        match name {
+            "background" => {
+                if let Ok(background) = ron::from_str(value) {
+                    config.background = background;
+                }
+            }
+            "show_rare_keys" => {
+                if let Ok(show_rare_keys) = ron::from_str(value) {
+                    config.show_rare_keys = show_rare_keys;
+                }
+            }
+            "pin_hotkey" => {
+                if let Ok(pin_hotkey) = ron::from_str(value) {
+                    config.pin_hotkey = pin_hotkey;
+                }
+            }
+            "overlay_port" => {
+                if let Ok(overlay_port) = ron::from_str(value) {
+                    config.overlay_port = overlay_port;
+                }
+            }
+            "profiles" => {
+                let profiles_offset = body_start + field_start + value_start;
+                let Some((list_start, list)) = enclosed(value) else {
+                    continue;
+                };
+                for (profile_start, profile_str) in split_top_level(list) {
+                    let offset = profiles_offset + list_start + profile_start;
+                    let line = line_of(config_str, offset);
+                    let profile = if v1 {
+                        ron::from_str::<v1::Profile>(profile_str).map(Profile::from)
+                    } else {
+                        ron::from_str::<Profile>(profile_str)
+                    };
+                    match profile {
+                        Ok(profile) => config.profiles.push(profile),
+                        Err(e) => lost.push(LostProfile {
+                            name: profile_name(profile_str),
+                            line: line + e.span.start.line.saturating_sub(1),
+                            error: e.code.to_string(),
+                        }),
+                    }
+                }
+            }
+            // The version, and any settings from newer versions of Reemap.
+            _ => (),
+        }
+    }
+
+    Some(Salvaged { config, lost })
+}
+
+fn profile_name(profile_str: &str) -> Option<String> {
+    let (_, fields) = enclosed(profile_str)?;
+    split_top_level(fields)
+        .into_iter()
+        .filter_map(|(_, field)| split_field(field))
+        .find(|(name, _, _)| *name == "name")
+        .and_then(|(_, _, value)| ron::from_str(value).ok())
+}
+
+fn line_of(s: &str, offset: usize) -> usize {
+    s[..offset].bytes().filter(|&b| b == b'\n').count() + 1
+}
+
+// -------------------- Scanning --------------------
+// Just enough of RON to find where things start and end. Brackets inside strings, characters, and
+// comments do not count.
+
+// The contents of the bracketed group `s` starts with, with their offset in `s`.
+fn enclosed(s: &str) -> Option<(usize, &str)> {
+    let bytes = s.as_bytes();
+    let start = skip_trivia(bytes, 0);
+    if !matches!(bytes.get(start), Some(b'(' | b'[' | b'{')) {
+        return None;
+    }
+    let mut depth = 0;
+    let mut i = start;
+    while i < bytes.len() {
+        if let Some(end) = skip_literal(bytes, i) {
+            i = end;
+            continue;
+        }
+        match bytes[i] {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((start + 1, &s[start + 1..i]));
+                }
+            }
+            _ => (),
+        }
+        i += 1;
+    }
+    None
+}
+
+// Split the contents of a bracketed group at its commas, with the offset of each piece. Empty
+// pieces, like the one after a trailing comma, are left out.
+fn split_top_level(s: &str) -> Vec<(usize, &str)> {
+    let bytes = s.as_bytes();
+    let mut pieces = Vec::new();
+    let mut depth = 0;
+    let mut piece_start = 0;
+    let mut i = 0;
+    let mut push = |start: usize, end: usize| {
+        let start = skip_trivia(bytes, start);
+        if start < end {
+            pieces.push((start, s[start..end].trim_end()));
+        }
+    };
+    while i < bytes.len() {
+        if let Some(end) = skip_literal(bytes, i) {
+            i = end;
+            continue;
+        }
+        match bytes[i] {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth -= 1,
+            b',' if depth == 0 => {
+                push(piece_start, i);
+                piece_start = i + 1;
+            }
+            _ => (),
+        }
+        i += 1;
+    }
+    push(piece_start, bytes.len());
+    pieces
+}
+
+// "name: value" -> the name, and the value with its offset in the field.
+fn split_field(field: &str) -> Option<(&str, usize, &str)> {
+    let (name, value) = field.split_once(':')?;
+    let value_start = name.len() + 1;
+    Some((name.trim(), value_start, value))
+}
+
+// Skip whitespace and comments.
+fn skip_trivia(bytes: &[u8], mut i: usize) -> usize {
+    loop {
+        while bytes.get(i).is_some_and(|b| b.is_ascii_whitespace()) {
+            i += 1;
+        }
+        match bytes.get(i..i + 2) {
+            Some(b"//" | b"/*") => i = skip_literal(bytes, i).unwrap_or(bytes.len()),
+            _ => return i,
+        }
+    }
+}
+
+// If a string, character, or comment starts at `i`, where it ends.
+fn skip_literal(bytes: &[u8], i: usize) -> Option<usize> {
+    match bytes[i] {
+        b'"' => Some(skip_quoted(bytes, i + 1, b'"')),
+        b'\'' => Some(skip_quoted(bytes, i + 1, b'\'')),
+        b'/' if bytes.get(i + 1) == Some(&b'/') => Some(
+            bytes[i..]
+                .iter()
+                .position(|&b| b == b'\n')
+                .map_or(bytes.len(), |len| i + len + 1),
+        ),
+        b'/' if bytes.get(i + 1) == Some(&b'*') => {
+            // Block comments nest in RON.
+            let mut depth = 0;
+            let mut j = i;
+            while j < bytes.len() {
+                match bytes.get(j..j + 2) {
+                    Some(b"/*") => {
+                        depth += 1;
+                        j += 2;
+                    }
+                    Some(b"*/") => {
+                        depth -= 1;
+                        j += 2;
+                        if depth == 0 {
+                            return Some(j);
+                        }
+                    }
+                    _ => j += 1,
+                }
+            }
+            Some(bytes.len())
+        }
+        // Raw strings: r"...", r#"..."#, and so on. The r must not be the end of an identifier.
+        b'r' if i == 0 || !(bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'_') => {
+            let hashes = bytes[i + 1..].iter().take_while(|&&b| b == b'#').count();
+            if bytes.get(i + 1 + hashes) != Some(&b'"') {
+                return None;
+            }
+            let mut j = i + 2 + hashes;
+            while j < bytes.len() {
+                if bytes[j] == b'"'
+                    && bytes[j + 1..].iter().take_while(|&&b| b == b'#').count() >= hashes
+                {
+                    return Some(j + 1 + hashes);
+                }
+                j += 1;
+            }
+            Some(bytes.len())
+        }
+        _ => None,
+    }
+}
+
+// Skip to just past the closing quote, minding backslash escapes.
+fn skip_quoted(bytes: &[u8], mut i: usize, quote: u8) -> usize {
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b if b == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn enclosed_finds_the_matching_bracket() {
+        assert_eq!(
+            enclosed("  (a, [b, {c: (d)}], e) tail"),
+            Some((3, "a, [b, {c: (d)}], e"))
+        );
+        assert_eq!(enclosed("[]"), Some((1, "")));
+        assert_eq!(enclosed("// (\n(a)"), Some((6, "a")));
+        assert_eq!(enclosed("a (b)"), None);
+    }
+
+    #[test]
+    fn brackets_in_strings_and_comments_do_not_count() {
+        assert_eq!(
+            enclosed(r#"("(", ')', ")")"#),
+            Some((1, r#""(", ')', ")""#))
+        );
+        assert_eq!(enclosed(r#"("\")", a)"#), Some((1, r#""\")", a"#)));
+        assert_eq!(enclosed(r#"("\\", a)"#), Some((1, r#""\\", a"#)));
+        assert_eq!(enclosed(r##"(r#")"#, a)"##), Some((1, r##"r#")"#, a"##)));
+        assert_eq!(
+            enclosed("(a /* ) /* ) */ ) */, b)"),
+            Some((1, "a /* ) /* ) */ ) */, b"))
+        );
+        assert_eq!(enclosed("(a // )\n)"), Some((1, "a // )\n")));
+        // Not a raw string: the r ends an identifier, and the string after it is an ordinary one.
+        assert_eq!(enclosed(r#"(for"(")"#), Some((1, r#"for"(""#)));
+    }
+
+    #[test]
+    fn truncated_input_is_not_enclosed() {
+        assert_eq!(enclosed(""), None);
+        assert_eq!(enclosed("(a, [b, c)"), None);
+        assert_eq!(enclosed(r#"(a, "b)"#), None);
+        assert_eq!(enclosed("(a, /* b)"), None);
+        assert_eq!(enclosed(r#"("\"#), None);
+    }
+
+    #[test]
+    fn split_top_level_splits_only_outer_commas() {
+        assert_eq!(
+            split_top_level(
+                r#" a: (1, 2), b: "x, y", // c, d
+                c: [3, 4], "#
+            ),
+            vec![(1, "a: (1, 2)"), (12, r#"b: "x, y""#), (47, "c: [3, 4]")]
+        );
+        assert_eq!(split_top_level(" , ,"), vec![]);
+        // Whatever is left of truncated input is the last piece.
+        assert_eq!(split_top_level("a, (b, c"), vec![(0, "a"), (3, "(b, c")]);
+    }
+
+    #[test]
+    fn skip_literal_finds_the_end() {
+        assert_eq!(skip_literal(br#""a\"b" c"#, 0), Some(6));
+        assert_eq!(skip_literal(br"'\'' c", 0), Some(4));
+        assert_eq!(skip_literal(b"// a\nb", 0), Some(5));
+        assert_eq!(skip_literal(br###"r##"a"#"##b"###, 0), Some(10));
+        assert_eq!(skip_literal(b"/* a", 0), Some(4));
+        assert_eq!(skip_literal(br#""a"#, 0), Some(2));
+        assert_eq!(skip_literal(b"a", 0), None);
+        assert_eq!(skip_literal(b"r", 0), None);
+    }
+
+    #[test]
+    fn keeps_what_parses() {
+        let config_str = r#"(
+    version: "V1",
+    background: Gradient,
+    show_rare_keys: true,
+    profiles: [
+        (
+            name: "Broken",
+            enabled: maybe,
+        ),
+        (
+            name: "Fine, \"really\" (no brackets)",
+            enabled: true,
+            condition: Always,
+            base: (policy: {Key(A): NoRemap}),
+            layers: [],
+            clip_cursor: false,
+        ),
+    ],
+    something_new: [1, 2],
+)"#;
+        let salvaged = salvage(config_str).unwrap();
+        assert_eq!(
+            salvaged.config.background,
+            crate::config::Background::Gradient
+        );
+        assert!(salvaged.config.show_rare_keys);
+        assert_eq!(salvaged.config.profiles.len(), 1);
+        assert_eq!(
+            salvaged.config.profiles[0].name,
+            r#"Fine, "really" (no brackets)"#
+        );
+        // Migrated like any other V1 profile.
+        assert_eq!(
+            salvaged.config.profiles[0].base.policy
+                [crate::buttons::Button::Key(crate::buttons::key::KeyButton::A)],
+            crate::config::BaseRemapPolicy::Inherit
+        );
+        assert_eq!(salvaged.lost.len(), 1);
+        assert_eq!(salvaged.lost[0].name.as_deref(), Some("Broken"));
+        assert_eq!(salvaged.lost[0].line, 8);
+
+        assert_eq!(salvage("not a configuration"), None);
+    }
+}
@@ -0,0 +1,123 @@
+// Copyright 2025 Jordan Johnson
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Watches the configuration file for edits made outside Reemap.
+
+use crate::gui;
+use std::path::PathBuf;
+use thiserror::Error;
+use tracing::{debug, instrument, warn};
+use windows::Win32::Foundation as FN;
+use windows::Win32::Storage::FileSystem as FS;
+use windows::Win32::System::Threading as TH;
+
+// Spawn a thread that tells the UI thread whenever the configuration directory changes. Returns a
+// proxy used to stop the thread.
+//
+// Windows only tells us *something* in the directory changed, not what. That's fine; the UI thread
+// re-reads the file and compares it to what it expects anyways.
+pub fn spawn_scoped<'scope, 'env>(
+    s: &'scope std::thread::Scope<'scope, 'env>,
+    config_path: PathBuf,
+    ui_proxy: winit::event_loop::EventLoopProxy<gui::ReemapGuiEvent>,
+) -> Result<ConfigWatcherProxy> {
+    let (oneshot_sender, oneshot_receiver) = oneshot::channel();
+    s.spawn(|| {
+        run(oneshot_sender, config_path, ui_proxy);
+    });
+    oneshot_receiver.recv().unwrap()
+}
+
+#[instrument(skip_all, name = "config_watcher")]
+fn run(
+    sender: oneshot::Sender<Result<ConfigWatcherProxy>>,
+    config_path: PathBuf,
+    ui_proxy: winit::event_loop::EventLoopProxy<gui::ReemapGuiEvent>,
+) {
+    debug!("entering config watcher thread");
+
+    // Watch the whole directory rather than the file. Editors often save by writing a new file and
+    // renaming it over the old one.
+    let config_dir = config_path
+        .parent()
+        .expect("configuration file should be inside a directory");
+    let change_handle = match unsafe {
+        FS::FindFirstChangeNotificationW(
+            &windows::core::HSTRING::from(config_dir),
+            false,
+            FS::FILE_NOTIFY_CHANGE_LAST_WRITE | FS::FILE_NOTIFY_CHANGE_FILE_NAME,
+        )
+    } {
+        Ok(handle) => handle,
+        Err(e) => {
+            let _ = sender.send(Err(Error::CannotWatch(e)));
+            return;
+        }
+    };
+    let stop_event = match unsafe { TH::CreateEventW(None, true, false, None) } {
+        Ok(handle) => handle,
+        Err(e) => {
+            let _ = unsafe { FS::FindCloseChangeNotification(change_handle) };
+            let _ = sender.send(Err(Error::CannotCreateStopEvent(e)));
+            return;
+        }
+    };
+    sender
+        .send(Ok(ConfigWatcherProxy {
+            stop_event: stop_event.0 as usize,
+        }))
+        .unwrap();
+
+    loop {
+        let wait = unsafe {
+            TH::WaitForMultipleObjects(&[change_handle, stop_event], false, TH::INFINITE)
+        };
+        if wait != FN::WAIT_OBJECT_0 {
+            // Either we were told to stop, or the wait failed. Either way, we're done.
+            break;
+        }
+        debug!("configuration directory changed");
+        // Like the hook thread, the UI thread may have just barely stopped. That's OK.
+        if ui_proxy
+            .send_event(gui::ReemapGuiEvent::ConfigFileChanged)
+            .is_err()
+        {
+            warn!("failed to send message to UI thread");
+        }
+        if let Err(e) = unsafe { FS::FindNextChangeNotification(change_handle) } {
+            warn!(?e, "failed to keep watching the configuration directory");
+            break;
+        }
+    }
+
+    unsafe {
+        let _ = FS::FindCloseChangeNotification(change_handle);
+        let _ = FN::CloseHandle(stop_event);
+    }
+    debug!("exiting config watcher thread");
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigWatcherProxy {
+    stop_event: usize,
+}
+
+impl ConfigWatcherProxy {
+    pub fn quit(&self) {
+        debug!("telling config watcher to quit");
+        let stop_event = FN::HANDLE(self.stop_event as *mut std::ffi::c_void);
+        if let Err(e) = unsafe { TH::SetEvent(stop_event) } {
+            warn!(?e, "could not stop the config watcher");
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("could not watch the configuration directory")]
+    CannotWatch(#[source] windows::core::Error),
+    #[error("could not create an event to stop the config watcher")]
+    CannotCreateStopEvent(#[source] windows::core::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
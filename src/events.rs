@@ -0,0 +1,262 @@
+// Copyright 2025 Jordan Johnson
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! What a running Reemap can tell other programs about itself: a snapshot of its state, and the
+//! events that change it.
+//!
+//! Both are plain serde types. The control channel in ipc.rs sends them as JSON.
+
+use crate::buttons::{Button, HoldButton};
+use crate::config::Output;
+use enum_map::EnumMap;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, mpsc};
+use tracing::warn;
+
+// How many events a subscriber may fall behind by before it is disconnected.
+pub const SUBSCRIBER_BACKLOG: usize = 64;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Status {
+    // The active profiles, highest priority first.
+    pub profiles: Vec<ActiveProfile>,
+    // Every profile in the configuration, in order, active or not.
+    pub all_profiles: Vec<String>,
+    pub pinned: Option<String>,
+    pub paused: bool,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActiveProfile {
+    pub name: String,
+    // The layers turned on right now.
+    pub layers: Vec<String>,
+}
+
+// HeldButtons makes every event a few kilobytes. Boxing it instead would mean an allocation on the
+// hook thread for every input.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    // The active profiles, highest priority first.
+    ProfilesChanged { profiles: Vec<String> },
+    PinChanged { profile: Option<String> },
+    PausedChanged { paused: bool },
+    LayerActivated { profile: String, layer: String },
+    LayerDeactivated { profile: String, layer: String },
+
+    // Input events. These are only sent to subscribers that ask for them, since there are a lot.
+    // They describe the physical buttons, before any remap, and are sent while paused too.
+    ButtonPressed { button: Button },
+    ButtonReleased { button: Button },
+    // Every button held down after a press or release, and what Reemap did with it.
+    HeldButtons { buttons: HeldButtons },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeldButton {
+    pub button: Button,
+    // None if the button was let through as it is. Empty if it was suppressed.
+    pub remapped_to: Option<Vec<Button>>,
+}
+
+// -------------------- HeldButtons --------------------
+
+/// Every button held down, as a fixed-size copy, so the hook thread never allocates to send it.
+/// Whoever receives it turns it into a list with `list`, which is also how it is serialized.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "Vec<HeldButton>", into = "Vec<HeldButton>")]
+pub struct HeldButtons(pub EnumMap<HoldButton, Option<HeldAs>>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeldAs {
+    Unchanged,
+    Remapped(Output),
+    Suppressed,
+}
+
+impl HeldButtons {
+    pub fn list(&self) -> Vec<HeldButton> {
+        self.0
+            .iter()
+            .filter_map(|(button, held_as)| {
+                let remapped_to = match held_as.as_ref()? {
+                    HeldAs::Unchanged => None,
+                    HeldAs::Remapped(output) => Some(output.to_vec()),
+                    HeldAs::Suppressed => Some(Vec::new()),
+                };
+                Some(HeldButton {
+                    button: button.into(),
+                    remapped_to,
+                })
+            })
+            .collect()
+    }
+}
+
+impl From<HeldButtons> for Vec<HeldButton> {
+    fn from(value: HeldButtons) -> Self {
+        value.list()
+    }
+}
+
+// Buttons that cannot be held, like the wheel, are left out.
+impl From<Vec<HeldButton>> for HeldButtons {
+    fn from(value: Vec<HeldButton>) -> Self {
+        let mut held_buttons = Self::default();
+        for held in value {
+            let button = match held.button {
+                Button::Key(key) => HoldButton::Key(key),
+                Button::Mouse(mouse) => HoldButton::Mouse(mouse),
+                Button::Wheel(_) => continue,
+            };
+            held_buttons.0[button] = Some(match held.remapped_to {
+                None => HeldAs::Unchanged,
+                Some(output) if output.is_empty() => HeldAs::Suppressed,
+                Some(output) => HeldAs::Remapped(Output::from_vec(output)),
+            });
+        }
+        held_buttons
+    }
+}
+
+impl Event {
+    pub fn is_input(&self) -> bool {
+        matches!(
+            self,
+            Event::ButtonPressed { .. } | Event::ButtonReleased { .. } | Event::HeldButtons { .. }
+        )
+    }
+}
+
+// -------------------- EventBus --------------------
+
+/// Hands every published event to everyone subscribed at the time. Clones share subscribers.
+///
+/// Publishing never waits on a subscriber. Each one has room for SUBSCRIBER_BACKLOG events, and
+/// one that lets that many pile up is disconnected.
+#[derive(Debug, Default, Clone)]
+pub struct EventBus {
+    subscribers: Arc<Mutex<Vec<Subscriber>>>,
+    // How many subscribers there are, and how many of them want input events. The hook thread
+    // checks these on every input, so they are kept outside the mutex.
+    all_subscribers: Arc<AtomicUsize>,
+    input_subscribers: Arc<AtomicUsize>,
+}
+
+#[derive(Debug)]
+struct Subscriber {
+    sender: mpsc::SyncSender<Event>,
+    inputs: bool,
+}
+
+impl EventBus {
+    /// Subscribe to every event, including input events if `inputs` is set.
+    pub fn subscribe(&self, inputs: bool) -> mpsc::Receiver<Event> {
+        let (sender, receiver) = mpsc::sync_channel(SUBSCRIBER_BACKLOG);
+        let mut subscribers = self.subscribers.lock().expect("mutex poisoned");
+        subscribers.push(Subscriber { sender, inputs });
+        self.count_subscribers(&subscribers);
+        receiver
+    }
+
+    /// Whether anyone is subscribed at all. The hook thread checks this before building an event.
+    pub fn wants_events(&self) -> bool {
+        self.all_subscribers.load(Ordering::Relaxed) > 0
+    }
+
+    /// Whether anyone wants input events. Check this before building one.
+    pub fn wants_inputs(&self) -> bool {
+        self.input_subscribers.load(Ordering::Relaxed) > 0
+    }
+
+    // Subscribers that hung up or fell too far behind are dropped here. The mutex is only ever
+    // held briefly, so the hook thread does not wait on it for long.
+    pub fn publish(&self, event: Event) {
+        let is_input = event.is_input();
+        let mut subscribers = self.subscribers.lock().expect("mutex poisoned");
+        let before = subscribers.len();
+        subscribers.retain(|subscriber| {
+            if is_input && !subscriber.inputs {
+                return true;
+            }
+            match subscriber.sender.try_send(event.clone()) {
+                Ok(()) => true,
+                Err(mpsc::TrySendError::Full(_)) => {
+                    warn!("disconnected an event subscriber that fell behind");
+                    false
+                }
+                Err(mpsc::TrySendError::Disconnected(_)) => false,
+            }
+        });
+        if subscribers.len() != before {
+            self.count_subscribers(&subscribers);
+        }
+    }
+
+    fn count_subscribers(&self, subscribers: &[Subscriber]) {
+        let count = subscribers
+            .iter()
+            .filter(|subscriber| subscriber.inputs)
+            .count();
+        self.all_subscribers
+            .store(subscribers.len(), Ordering::Relaxed);
+        self.input_subscribers.store(count, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buttons::key::KeyButton;
+    use crate::buttons::mouse::MouseButton;
+    use smallvec::smallvec;
+
+    #[test]
+    fn held_buttons_are_sent_as_a_list() {
+        let mut held = HeldButtons::default();
+        held.0[HoldButton::Key(KeyButton::SPACE)] =
+            Some(HeldAs::Remapped(smallvec![Button::Mouse(
+                MouseButton::Left
+            )]));
+        held.0[HoldButton::Key(KeyButton::A)] = Some(HeldAs::Unchanged);
+        held.0[HoldButton::Mouse(MouseButton::X1)] = Some(HeldAs::Suppressed);
+        let event = Event::HeldButtons { buttons: held };
+
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["event"], "held_buttons");
+        assert_eq!(json["buttons"].as_array().unwrap().len(), 3);
+        let read_back: Event = serde_json::from_value(json).unwrap();
+        assert_eq!(read_back, event);
+    }
+
+    #[test]
+    fn subscribers_that_fall_behind_are_disconnected() {
+        let bus = EventBus::default();
+        let keeping_up = bus.subscribe(true);
+        let falling_behind = bus.subscribe(true);
+        let pressed = Event::ButtonPressed {
+            button: Button::Key(KeyButton::A),
+        };
+
+        for _ in 0..SUBSCRIBER_BACKLOG {
+            bus.publish(pressed.clone());
+            assert_eq!(keeping_up.try_recv(), Ok(pressed.clone()));
+        }
+        assert!(bus.wants_inputs());
+        // One too many, and the publisher carries on without waiting.
+        bus.publish(pressed.clone());
+        assert_eq!(keeping_up.try_recv(), Ok(pressed.clone()));
+        assert_eq!(falling_behind.try_iter().count(), SUBSCRIBER_BACKLOG);
+        assert_eq!(
+            falling_behind.try_recv(),
+            Err(mpsc::TryRecvError::Disconnected)
+        );
+
+        drop(keeping_up);
+        bus.publish(pressed);
+        assert!(!bus.wants_events());
+    }
+}
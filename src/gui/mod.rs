@@ -9,6 +9,7 @@ mod glutin_ctx;
 pub mod reemapp;
 
 use crate::buttons;
+use crate::hooks;
 
 use glutin_ctx::GlutinWindowContext;
 use tracing::{trace, warn};
@@ -44,8 +45,25 @@ pub enum ReemapGuiEvent {
     SetWindowVisibility(bool),
     TrayIconEvent(tray_icon::TrayIconEvent),
     TrayMenuEvent(tray_icon::menu::MenuEvent),
-    ChangedProfile(Option<String>),
+    ChangedProfile(Vec<String>), // Active profiles, highest priority first
+    ChangedPin(Option<String>),
+    ConfigFileChanged,
     ButtonPressed(buttons::Button),
+    ShowWindow,
+    HookHealth(hooks::HookHealth),
+}
+
+// The hook thread talks to the UI through this.
+impl hooks::UiSink for winit::event_loop::EventLoopProxy<ReemapGuiEvent> {
+    fn send(&self, event: hooks::UiEvent) -> bool {
+        let event = match event {
+            hooks::UiEvent::ChangedProfile(names) => ReemapGuiEvent::ChangedProfile(names),
+            hooks::UiEvent::ChangedPin(pinned) => ReemapGuiEvent::ChangedPin(pinned),
+            hooks::UiEvent::ButtonPressed(button) => ReemapGuiEvent::ButtonPressed(button),
+            hooks::UiEvent::HookHealth(health) => ReemapGuiEvent::HookHealth(health),
+        };
+        self.send_event(event).is_ok()
+    }
 }
 
 // Just something to pass along a little more info to the app.
@@ -57,6 +75,12 @@ pub struct TrayAppCtx {
 
 pub trait TrayApp {
     fn update(&mut self, egui_ctx: &egui::Context, app_ctx: &TrayAppCtx);
+    // The profiles listed in the tray's pin menu.
+    fn profile_names(&self) -> Vec<String>;
+    fn pin_profile(&mut self, profile: Option<String>);
+    fn config_file_changed(&mut self);
+    // The input hooks stopped working, or came back.
+    fn hook_health_changed(&mut self, health: hooks::HookHealth);
 }
 
 struct GlowApp<T: TrayApp> {
@@ -66,6 +90,9 @@ struct GlowApp<T: TrayApp> {
     egui_glow: Option<egui_glow::EguiGlow>,
     next_repaint_time: Option<Instant>,
     tray_icon: Option<TrayIcon>,
+    pin_menu: Option<tray_icon::menu::Submenu>,
+    pin_menu_names: Vec<String>, // The profile names behind each MENU_ID_PIN_<n>
+    pinned_profile: Option<String>,
     app_ctx: TrayAppCtx,
     start_visible: bool,
     app_data: T,
@@ -84,6 +111,9 @@ impl<T: TrayApp> GlowApp<T> {
             egui_glow: None,
             next_repaint_time: Some(Instant::now()),
             tray_icon: None,
+            pin_menu: None,
+            pin_menu_names: Vec::new(),
+            pinned_profile: None,
             app_ctx: TrayAppCtx::default(),
             start_visible,
             app_data,
@@ -102,6 +132,44 @@ impl<T: TrayApp> GlowApp<T> {
         }
     }
 
+    // Bring the window to the front, even if it was hidden or minimized.
+    fn show(&self, event_loop: &winit::event_loop::ActiveEventLoop) {
+        self.set_visible(true, event_loop);
+        if let Some(ref gl_window) = self.gl_window {
+            gl_window.window().focus_window();
+            gl_window.window().set_minimized(false);
+        }
+    }
+
+    // Rebuild the tray's pin menu from the current list of profiles.
+    fn refresh_pin_menu(&mut self) {
+        use tray_icon::menu::{CheckMenuItem, PredefinedMenuItem};
+
+        let Some(ref pin_menu) = self.pin_menu else {
+            return;
+        };
+        while pin_menu.remove_at(0).is_some() {}
+
+        self.pin_menu_names = self.app_data.profile_names();
+        let unpin_btn = CheckMenuItem::with_id(
+            "MENU_ID_UNPIN",
+            "Follow window in focus",
+            true,
+            self.pinned_profile.is_none(),
+            None,
+        );
+        let _ = pin_menu.append(&unpin_btn);
+        let _ = pin_menu.append(&PredefinedMenuItem::separator());
+        for (i, name) in self.pin_menu_names.iter().enumerate() {
+            let pinned = self.pinned_profile.as_ref() == Some(name);
+            // An ampersand would otherwise mark a mnemonic.
+            let text = name.replace('&', "&&");
+            let pin_btn =
+                CheckMenuItem::with_id(format!("MENU_ID_PIN_{i}"), text, true, pinned, None);
+            let _ = pin_menu.append(&pin_btn);
+        }
+    }
+
     fn check_repaint_time(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
         let now = Instant::now();
 
@@ -178,12 +246,15 @@ impl<T: TrayApp> winit::application::ApplicationHandler<ReemapGuiEvent> for Glow
             let menu = tray_icon::menu::Menu::new();
             let configure_btn =
                 tray_icon::menu::MenuItem::with_id("MENU_ID_CONFIGURE", "Configure", true, None);
+            let pin_menu = tray_icon::menu::Submenu::with_id("MENU_ID_PIN", "Pin profile", true);
             let exit_btn = tray_icon::menu::MenuItem::with_id("MENU_ID_EXIT", "Exit", true, None);
-            menu.append_items(&[&configure_btn, &exit_btn])
+            menu.append_items(&[&configure_btn, &pin_menu, &exit_btn])
                 .expect("could not initialize tray menu");
+            self.pin_menu = Some(pin_menu);
 
             menu
         };
+        self.refresh_pin_menu();
 
         // note: creating this has the side effect of creating the tray icon
         let tray_icon = {
@@ -305,42 +376,80 @@ impl<T: TrayApp> winit::application::ApplicationHandler<ReemapGuiEvent> for Glow
                 button: tray_icon::MouseButton::Left,
                 ..
             }) => {
-                self.set_visible(true, event_loop);
-                if let Some(ref gl_window) = self.gl_window {
-                    gl_window.window().focus_window();
-                    gl_window.window().set_minimized(false);
-                }
+                self.show(event_loop);
+            }
+            // The profiles may have changed since the pin menu was last built. Hovering over the
+            // icon comes before opening the menu, so this is a good time to catch up.
+            ReemapGuiEvent::TrayIconEvent(tray_icon::TrayIconEvent::Enter { .. }) => {
+                self.refresh_pin_menu();
             }
             ReemapGuiEvent::TrayIconEvent(_) => {}
             ReemapGuiEvent::TrayMenuEvent(tray_icon::menu::MenuEvent {
                 id: tray_icon::menu::MenuId(id),
             }) => match id.as_str() {
                 "MENU_ID_CONFIGURE" => {
-                    self.set_visible(true, event_loop);
-                    if let Some(ref gl_window) = self.gl_window {
-                        gl_window.window().focus_window();
-                        gl_window.window().set_minimized(false);
-                    }
+                    self.show(event_loop);
                 }
                 "MENU_ID_EXIT" => event_loop.exit(),
+                "MENU_ID_UNPIN" => {
+                    self.app_data.pin_profile(None);
+                    self.refresh_pin_menu();
+                }
+                id if id.starts_with("MENU_ID_PIN_") => {
+                    let name = id["MENU_ID_PIN_".len()..]
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|i| self.pin_menu_names.get(i));
+                    match name {
+                        Some(name) => self.app_data.pin_profile(Some(name.clone())),
+                        None => warn!("unrecognized pin menu ID"),
+                    }
+                    // Check marks toggle on click; put them back until the hook thread confirms.
+                    self.refresh_pin_menu();
+                }
                 _ => {
                     #[cfg(debug_assertions)]
                     panic!("unrecognized menu ID")
                 }
             },
-            ReemapGuiEvent::ChangedProfile(name) => {
-                let title = match name {
-                    Some(name) => format!("{name} - Reemap"),
-                    None => "Reemap".to_string(),
+            ReemapGuiEvent::ChangedProfile(names) => {
+                let title = if names.is_empty() {
+                    "Reemap".to_string()
+                } else {
+                    format!("{} - Reemap", names.join(" + "))
                 };
                 if let Some(ref gl_window) = self.gl_window {
                     gl_window.window().set_title(&title);
                 }
             }
+            ReemapGuiEvent::ChangedPin(pinned_profile) => {
+                let tooltip = match pinned_profile {
+                    Some(ref name) => format!("{TITLE} - {name} pinned"),
+                    None => TITLE.to_string(),
+                };
+                if let Some(ref tray_icon) = self.tray_icon
+                    && let Err(e) = tray_icon.set_tooltip(Some(tooltip))
+                {
+                    warn!(?e, "failed to set tray tooltip");
+                }
+                self.pinned_profile = pinned_profile;
+                self.refresh_pin_menu();
+            }
+            ReemapGuiEvent::ShowWindow => {
+                self.show(event_loop);
+            }
+            ReemapGuiEvent::ConfigFileChanged => {
+                self.app_data.config_file_changed();
+                self.next_repaint_time = Some(std::time::Instant::now());
+            }
             ReemapGuiEvent::ButtonPressed(button) => {
                 self.app_ctx.last_pressed_button = Some(button);
                 self.next_repaint_time = Some(std::time::Instant::now());
             }
+            ReemapGuiEvent::HookHealth(health) => {
+                self.app_data.hook_health_changed(health);
+                self.next_repaint_time = Some(std::time::Instant::now());
+            }
         }
         self.check_repaint_time(event_loop);
     }
@@ -3,6 +3,7 @@
 
 //! UI for the buttons across the top of the window.
 
+use super::ApplyStatus;
 use super::GuiMenu;
 use super::ReemApp;
 use crate::gui::reemapp::style;
@@ -28,6 +29,7 @@ pub fn breadcrumb(
                 args.apply_changes();
             }
         });
+        ui_apply_status(ui, args, unsaved_changes);
         ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
             if let Some(click) = breadcrumb_display(ctx, ui, args) {
                 args.gui_local.menu = click;
@@ -36,6 +38,26 @@ pub fn breadcrumb(
     });
 }
 
+// Whether the last Apply worked. "Applied" goes away once there are new changes; a failure stays
+// until an Apply works.
+fn ui_apply_status(ui: &mut egui::Ui, args: &ReemApp, unsaved_changes: bool) {
+    match &args.gui_local.apply_status {
+        None => (),
+        Some(ApplyStatus::Applying) => {
+            ui.weak("Applying...");
+        }
+        Some(ApplyStatus::Applied) => {
+            if !unsaved_changes {
+                ui.colored_label(egui::Color32::LIGHT_GREEN, "Applied");
+            }
+        }
+        Some(ApplyStatus::Failed(message)) => {
+            ui.colored_label(egui::Color32::LIGHT_RED, "Apply failed")
+                .on_hover_text(message);
+        }
+    }
+}
+
 fn breadcrumb_display(_ctx: &egui::Context, ui: &mut egui::Ui, args: &ReemApp) -> Option<GuiMenu> {
     let mut click = None;
 
@@ -0,0 +1,372 @@
+// Copyright 2025 Jordan Johnson
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Undo and redo for edits made in the UI.
+//!
+//! Rather than have every widget report what it changed, the configuration is compared to a
+//! snapshot at the end of each frame, the same way unsaved changes are detected. Any difference
+//! becomes one step in the history.
+
+use super::GuiMenu;
+use crate::config::{Config, Layer, Profile};
+
+// Older steps are forgotten past this point.
+const MAX_STEPS: usize = 100;
+
+#[derive(Debug)]
+pub struct History {
+    undo: Vec<Step>,
+    redo: Vec<Step>,
+    // The configuration as of the end of the last frame.
+    last_seen: Config,
+    // Describes the next change, for changes better described by what caused them.
+    next_description: Option<String>,
+}
+
+#[derive(Debug)]
+struct Step {
+    // The configuration on the other side of this step.
+    config: Config,
+    // Where the user was when they made the change.
+    menu: GuiMenu,
+    description: String,
+}
+
+impl History {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            last_seen: config.clone(),
+            next_description: None,
+        }
+    }
+
+    /// Use this description for the next change instead of working one out.
+    pub fn describe_next(&mut self, description: impl Into<String>) {
+        self.next_description = Some(description.into());
+    }
+
+    /// Call once per frame, after the UI has made its changes. `menu` is where the user was at the
+    /// start of the frame.
+    pub fn track(&mut self, config: &Config, menu: &GuiMenu) {
+        let next_description = self.next_description.take();
+        if *config == self.last_seen {
+            return;
+        }
+        let description = next_description.unwrap_or_else(|| describe(&self.last_seen, config));
+        let before = std::mem::replace(&mut self.last_seen, config.clone());
+        self.undo.push(Step {
+            config: before,
+            menu: menu.clone(),
+            description,
+        });
+        if self.undo.len() > MAX_STEPS {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn undo_description(&self) -> Option<&str> {
+        self.undo.last().map(|step| step.description.as_str())
+    }
+
+    pub fn redo_description(&self) -> Option<&str> {
+        self.redo.last().map(|step| step.description.as_str())
+    }
+
+    pub fn undo(&mut self, config: &mut Config, menu: &mut GuiMenu) {
+        if let Some(step) = self.undo.pop() {
+            let redo_step = self.step(step, config, menu);
+            self.redo.push(redo_step);
+        }
+    }
+
+    pub fn redo(&mut self, config: &mut Config, menu: &mut GuiMenu) {
+        if let Some(step) = self.redo.pop() {
+            let undo_step = self.step(step, config, menu);
+            self.undo.push(undo_step);
+        }
+    }
+
+    // Take a step, returning the step that leads back.
+    fn step(&mut self, step: Step, config: &mut Config, menu: &mut GuiMenu) -> Step {
+        let Step {
+            config: new_config,
+            menu: step_menu,
+            description,
+        } = step;
+        let old_config = std::mem::replace(config, new_config);
+        self.last_seen = config.clone();
+        // Take the user back to where the change was made, if it is still there.
+        *menu = if menu_exists(config, &step_menu) {
+            step_menu.clone()
+        } else {
+            GuiMenu::Main
+        };
+        Step {
+            config: old_config,
+            menu: step_menu,
+            description,
+        }
+    }
+
+    /// How many steps have been made from the start of the history.
+    pub fn position(&self) -> usize {
+        self.undo.len()
+    }
+
+    /// Every step, oldest first, including the ones that can be redone.
+    pub fn descriptions(&self) -> impl Iterator<Item = &str> {
+        self.undo
+            .iter()
+            .chain(self.redo.iter().rev())
+            .map(|step| step.description.as_str())
+    }
+
+    /// Undo or redo until `position` steps have been made.
+    pub fn go_to(&mut self, position: usize, config: &mut Config, menu: &mut GuiMenu) {
+        while self.undo.len() > position {
+            self.undo(config, menu);
+        }
+        while self.undo.len() < position && self.can_redo() {
+            self.redo(config, menu);
+        }
+    }
+}
+
+fn menu_exists(config: &Config, menu: &GuiMenu) -> bool {
+    match *menu {
+        GuiMenu::Main => true,
+        GuiMenu::Profile { profile_idx } => profile_idx < config.profiles.len(),
+        GuiMenu::ProfileLayer {
+            profile_idx,
+            layer_idx,
+        } => config
+            .profiles
+            .get(profile_idx)
+            .is_some_and(|profile| layer_idx < profile.layers.len()),
+    }
+}
+
+// -------------------- Descriptions --------------------
+// Worked out from the difference between two configurations. Most edits touch one thing, so these
+// only need to be specific about that case.
+
+fn describe(before: &Config, after: &Config) -> String {
+    let before_names: Vec<&str> = before.profiles.iter().map(|p| p.name.as_str()).collect();
+    let after_names: Vec<&str> = after.profiles.iter().map(|p| p.name.as_str()).collect();
+    if after.profiles.len() > before.profiles.len() {
+        let added = first_difference(&after_names, &before_names);
+        return format!("Add profile \"{}\"", after_names[added]);
+    }
+    if after.profiles.len() < before.profiles.len() {
+        let removed = first_difference(&before_names, &after_names);
+        return format!("Remove profile \"{}\"", before_names[removed]);
+    }
+
+    let changed: Vec<usize> = (0..after.profiles.len())
+        .filter(|&idx| before.profiles[idx] != after.profiles[idx])
+        .collect();
+    match changed.as_slice() {
+        [] => String::from("Change settings"),
+        [idx] => describe_profile(&before.profiles[*idx], &after.profiles[*idx]),
+        _ if same_names(&before_names, &after_names) => String::from("Rearrange profiles"),
+        _ => String::from("Edit profiles"),
+    }
+}
+
+fn describe_profile(before: &Profile, after: &Profile) -> String {
+    let name = &after.name;
+    if before.name != after.name {
+        return format!("Rename profile \"{}\" to \"{name}\"", before.name);
+    }
+    if before.enabled != after.enabled {
+        let verb = if after.enabled { "Enable" } else { "Disable" };
+        return format!("{verb} profile \"{name}\"");
+    }
+
+    let before_names: Vec<&str> = before.layers.iter().map(|l| l.name.as_str()).collect();
+    let after_names: Vec<&str> = after.layers.iter().map(|l| l.name.as_str()).collect();
+    if after.layers.len() > before.layers.len() {
+        let added = first_difference(&after_names, &before_names);
+        return format!("Add layer \"{}\" to \"{name}\"", after_names[added]);
+    }
+    if after.layers.len() < before.layers.len() {
+        let removed = first_difference(&before_names, &after_names);
+        return format!("Remove layer \"{}\" from \"{name}\"", before_names[removed]);
+    }
+
+    let changed: Vec<usize> = (0..after.layers.len())
+        .filter(|&idx| before.layers[idx] != after.layers[idx])
+        .collect();
+    match changed.as_slice() {
+        [] if before.base != after.base => format!("Edit remaps in \"{name}\""),
+        [] => format!("Edit profile \"{name}\""),
+        [idx] => describe_layer(name, &before.layers[*idx], &after.layers[*idx]),
+        _ if same_names(&before_names, &after_names) => {
+            format!("Rearrange layers in \"{name}\"")
+        }
+        _ => format!("Edit layers in \"{name}\""),
+    }
+}
+
+fn describe_layer(profile_name: &str, before: &Layer, after: &Layer) -> String {
+    let name = &after.name;
+    if before.name != after.name {
+        format!(
+            "Rename layer \"{}\" to \"{name}\" in \"{profile_name}\"",
+            before.name
+        )
+    } else if before.enabled != after.enabled {
+        let verb = if after.enabled { "Enable" } else { "Disable" };
+        format!("{verb} layer \"{name}\" in \"{profile_name}\"")
+    } else if before.policy != after.policy {
+        format!("Edit remaps in layer \"{name}\" of \"{profile_name}\"")
+    } else {
+        format!("Edit layer \"{name}\" in \"{profile_name}\"")
+    }
+}
+
+// The index of the first name in `longer` that is not lined up with the same name in `shorter`.
+fn first_difference(longer: &[&str], shorter: &[&str]) -> usize {
+    longer
+        .iter()
+        .zip(shorter.iter())
+        .position(|(a, b)| a != b)
+        .unwrap_or(shorter.len())
+}
+
+// Whether the same names appear, in any order.
+fn same_names(before: &[&str], after: &[&str]) -> bool {
+    let mut before = before.to_vec();
+    let mut after = after.to_vec();
+    before.sort_unstable();
+    after.sort_unstable();
+    before == after
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A configuration with one profile per name.
+    fn config(names: &[&str]) -> Config {
+        Config {
+            profiles: names
+                .iter()
+                .map(|name| Profile {
+                    name: name.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn names(config: &Config) -> Vec<&str> {
+        config.profiles.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    #[test]
+    fn undo_and_redo_retrace_edits_in_order() {
+        let mut config = config(&[]);
+        let mut menu = GuiMenu::Main;
+        let mut history = History::new(&config);
+        for name in ["A", "B", "C"] {
+            config.profiles.push(Profile {
+                name: name.to_string(),
+                ..Default::default()
+            });
+            history.track(&config, &GuiMenu::Main);
+        }
+        // Nothing changed this frame.
+        history.track(&config, &GuiMenu::Main);
+        assert_eq!(history.position(), 3);
+        assert_eq!(history.undo_description(), Some("Add profile \"C\""));
+
+        history.undo(&mut config, &mut menu);
+        history.undo(&mut config, &mut menu);
+        assert_eq!(names(&config), ["A"]);
+        assert_eq!(history.redo_description(), Some("Add profile \"B\""));
+        history.redo(&mut config, &mut menu);
+        assert_eq!(names(&config), ["A", "B"]);
+        assert_eq!(
+            history.descriptions().collect::<Vec<_>>(),
+            [
+                "Add profile \"A\"",
+                "Add profile \"B\"",
+                "Add profile \"C\""
+            ]
+        );
+
+        history.go_to(0, &mut config, &mut menu);
+        assert!(config.profiles.is_empty());
+        assert!(!history.can_undo());
+        history.go_to(3, &mut config, &mut menu);
+        assert_eq!(names(&config), ["A", "B", "C"]);
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn a_new_edit_clears_redo() {
+        let mut config = config(&["A"]);
+        let mut menu = GuiMenu::Main;
+        let mut history = History::new(&config);
+        config.profiles[0].name = String::from("B");
+        history.track(&config, &GuiMenu::Main);
+        history.undo(&mut config, &mut menu);
+        assert!(history.can_redo());
+
+        config.profiles[0].enabled = false;
+        history.track(&config, &GuiMenu::Main);
+        assert!(!history.can_redo());
+        assert_eq!(history.undo_description(), Some("Disable profile \"A\""));
+        history.undo(&mut config, &mut menu);
+        assert_eq!(names(&config), ["A"]);
+        assert!(config.profiles[0].enabled);
+    }
+
+    #[test]
+    fn old_steps_are_forgotten() {
+        let mut config = config(&["0"]);
+        let mut menu = GuiMenu::Main;
+        let mut history = History::new(&config);
+        for n in 1..=MAX_STEPS + 5 {
+            config.profiles[0].name = n.to_string();
+            history.track(&config, &GuiMenu::Main);
+        }
+        assert_eq!(history.position(), MAX_STEPS);
+        history.go_to(0, &mut config, &mut menu);
+        assert_eq!(names(&config), ["5"]);
+    }
+
+    #[test]
+    fn undo_returns_to_where_the_edit_was_made() {
+        let mut config = config(&["A", "B"]);
+        let mut history = History::new(&config);
+        // Remove B from its own page.
+        let profile_menu = GuiMenu::Profile { profile_idx: 1 };
+        config.profiles.pop();
+        history.track(&config, &profile_menu);
+        assert_eq!(history.undo_description(), Some("Remove profile \"B\""));
+
+        let mut menu = GuiMenu::Main;
+        history.undo(&mut config, &mut menu);
+        assert_eq!(names(&config), ["A", "B"]);
+        assert_eq!(menu, profile_menu);
+
+        // B's page is gone again once the removal is redone.
+        history.redo(&mut config, &mut menu);
+        assert_eq!(names(&config), ["A"]);
+        assert_eq!(menu, GuiMenu::Main);
+    }
+}
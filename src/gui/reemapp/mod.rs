@@ -4,15 +4,18 @@
 //! The entry for the main UI code.
 
 mod breadcrumb;
+mod history;
 mod style;
 mod ui_base_layer;
 mod ui_copy_modal;
 mod ui_edit_layer_modal;
 mod ui_edit_profile_modal;
+mod ui_history;
 mod ui_layer;
 mod ui_main;
 mod ui_ok_cancel_modal;
 mod ui_profile;
+mod ui_stats;
 mod ui_tables;
 
 use breadcrumb::breadcrumb;
@@ -20,32 +23,51 @@ use std::path::PathBuf;
 use tracing::warn;
 use tracing::{error, info, instrument};
 use ui_base_layer::ui_base_layer;
+use ui_history::ui_history;
 use ui_layer::ui_layer;
 use ui_main::ui_main;
 use ui_profile::ui_profile;
+use ui_stats::ui_stats_modal;
 
 use crate::buttons;
+use crate::cli;
 use crate::config;
 use crate::config::Output;
+use crate::config_file;
+use crate::config_formats;
+use crate::config_formats::Format;
+use crate::events;
 use crate::gui::TrayAppCtx;
 use crate::gui::reemapp::ui_profile::UiProfileModals;
 use crate::hooks;
-use crate::query_windows;
-
-use windows::Win32::UI::Input::KeyboardAndMouse;
+use crate::lint;
+use crate::overlay;
+use crate::platform::{self, Autostart, InputCapture, WindowQuery};
 
 const WEB_LINK: &str = "https://reemap.mightyburger.net";
 const TUTORIAL_LINK: &str = "https://reemap.mightyburger.net/tutorial";
 const REPO_LINK: &str = "https://github.com/MightyBurger/reemap";
 
+// How often to check whether the hook thread has installed an applied configuration.
+const APPLY_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(20);
+
+const UNDO_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+const REDO_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Y);
+
 // Thought the name was clever. Don't get too mad, please.
 #[derive(Debug)]
 pub struct ReemApp {
     hookthread_proxy: hooks::HookthreadProxy,
     config: config::Config,
     current_config: config::Config,
+    disk_config: config::Config, // What we last knew to be in the configuration file
     schedule_discard: bool,
     config_path: PathBuf,
+    history: history::History,
+    events: events::EventBus,
+    overlay: Option<overlay::OverlayServer>,
     gui_local: GuiLocal,
 }
 
@@ -54,62 +76,285 @@ impl ReemApp {
         hookthread_proxy: hooks::HookthreadProxy,
         config: config::Config,
         config_path: PathBuf,
+        events: events::EventBus,
     ) -> Self {
-        Self {
+        let mut app = Self {
             hookthread_proxy,
             current_config: config.clone(),
+            disk_config: config.clone(),
+            history: history::History::new(&config),
             config,
             schedule_discard: false,
             config_path,
+            events,
+            overlay: None,
             gui_local: GuiLocal::default(),
+        };
+        if let Err(e) = app.update_overlay() {
+            warn!("{e}");
         }
+        app
     }
     fn apply_changes(&mut self) {
         // Three things happen when setting the configuration.
-        // 1. UI therad saves configuration to %APPDATA%
-        // 2. UI thread sends config over to hookthread to update the remaps
-        // 3. UI thread updates its own current_config value
-
-        let config_str = ron::ser::to_string_pretty(
-            &config::VersionedConfig::from(self.config.clone()),
-            ron::ser::PrettyConfig::new(),
-        )
-        .unwrap();
-        match std::fs::write(&self.config_path, config_str) {
-            Ok(()) => (),
+        // 1. UI thread sends config over to hookthread to update the remaps. It does not wait for
+        //    the hookthread to install them; the answer is picked up in a later frame
+        // 2. UI thread updates its own current_config value, if the remaps were handed over
+        // 3. UI thread saves configuration to %APPDATA%, so the file never holds remaps that were
+        //    not sent to the hookthread
+        // Inheritance is resolved first. If it can't be, nothing is applied.
+
+        let resolved_config = match self.config.resolve_inheritance() {
+            Ok(resolved_config) => resolved_config,
+            Err(e) => {
+                error!("could not resolve profile inheritance: {e}");
+                native_dialog::DialogBuilder::message()
+                    .set_level(native_dialog::MessageLevel::Error)
+                    .set_title("Error applying changes")
+                    .set_text(format!(
+                        "Reemap could not resolve profile inheritance.\n\n{e}"
+                    ))
+                    .alert()
+                    .show()
+                    .unwrap();
+                return;
+            }
+        };
+
+        let installed = self.update_hookthread(resolved_config);
+        if !installed {
+            return;
+        }
+        self.current_config = self.config.clone();
+
+        // The file being replaced is backed up first.
+        match config_file::save_config(&self.config_path, &self.config) {
+            Ok(()) => {
+                self.disk_config = self.config.clone();
+            }
             Err(e) => {
                 error!("could not write to config file: {e}");
                 native_dialog::DialogBuilder::message()
                     .set_level(native_dialog::MessageLevel::Error)
                     .set_title("Error writing file")
-                    .set_text("Reemap could not write to the configuration file.")
+                    .set_text(format!(
+                        "Reemap could not write to the configuration file.\n\n{e}"
+                    ))
                     .alert()
                     .show()
                     .unwrap();
             }
         }
 
-        self.hookthread_proxy.update(self.config.clone());
-
-        self.current_config = self.config.clone();
+        if let Err(e) = self.update_overlay() {
+            warn!("{e}");
+            native_dialog::DialogBuilder::message()
+                .set_level(native_dialog::MessageLevel::Warning)
+                .set_title("Input overlay")
+                .set_text(format!(
+                    "Reemap could not start the input overlay. Try a different port.\n\n{e}"
+                ))
+                .alert()
+                .show()
+                .unwrap();
+        }
+    }
+    // Send a resolved configuration to the hook thread. How installing it went is shown next to
+    // the Apply button once the hook thread answers. Returns whether the remaps will be installed.
+    fn update_hookthread(&mut self, resolved_config: config::Config) -> bool {
+        match self.hookthread_proxy.update(resolved_config) {
+            Ok(answer) => {
+                self.gui_local.apply_status = Some(ApplyStatus::Applying);
+                self.gui_local.apply_answer = Some(answer);
+                true
+            }
+            Err(e) => {
+                error!("could not apply configuration: {e}");
+                self.gui_local.apply_status = Some(ApplyStatus::Failed(format!(
+                    "The remaps were not applied: {e}."
+                )));
+                self.gui_local.apply_answer = None;
+                false
+            }
+        }
+    }
+    // Check whether the hook thread has said how installing the last configuration went, without
+    // waiting for it. A late answer is as good as a quick one.
+    fn poll_apply_answer(&mut self, ctx: &egui::Context) {
+        let Some(answer) = &self.gui_local.apply_answer else {
+            return;
+        };
+        let status = match answer.try_recv() {
+            Ok(Ok(())) => {
+                info!("configuration applied");
+                ApplyStatus::Applied
+            }
+            Ok(Err(e)) => {
+                warn!("configuration applied with a problem: {e}");
+                ApplyStatus::Failed(format!("The remaps were applied, but {e}."))
+            }
+            Err(oneshot::TryRecvError::Empty) => {
+                ctx.request_repaint_after(APPLY_POLL_INTERVAL);
+                return;
+            }
+            Err(oneshot::TryRecvError::Disconnected) => {
+                error!("the hook thread did not install the configuration");
+                ApplyStatus::Failed(String::from(
+                    "The remaps were not applied: the hook thread stopped.",
+                ))
+            }
+        };
+        self.gui_local.apply_status = Some(status);
+        self.gui_local.apply_answer = None;
+    }
+    // Start, stop or move the input overlay to match the applied configuration.
+    fn update_overlay(&mut self) -> overlay::Result<()> {
+        let running_port = self.overlay.as_ref().map(overlay::OverlayServer::port);
+        if running_port == self.current_config.overlay_port {
+            return Ok(());
+        }
+        if let Some(overlay) = self.overlay.take() {
+            overlay.quit();
+        }
+        if let Some(port) = self.current_config.overlay_port {
+            self.overlay = Some(overlay::spawn(port, self.events.clone())?);
+        }
+        Ok(())
     }
     fn discard_changes(&mut self) {
         self.schedule_discard = true;
     }
+    fn undo(&mut self) {
+        self.history
+            .undo(&mut self.config, &mut self.gui_local.menu);
+    }
+    fn redo(&mut self) {
+        self.history
+            .redo(&mut self.config, &mut self.gui_local.menu);
+    }
+    // Load the configuration file after something changed it.
+    // Reemap's own writes end up here too, which is why we compare against what we last knew was
+    // on disk.
+    fn reload_config(&mut self) {
+        let config_str = match std::fs::read_to_string(&self.config_path) {
+            Ok(config_str) => config_str,
+            Err(e) => {
+                warn!("could not read config file: {e}");
+                self.gui_local.reload_error =
+                    Some(format!("Could not read the configuration file: {e}"));
+                return;
+            }
+        };
+        let (parsed, skipped) =
+            config::count_skipped_remaps(|| ron::from_str::<config::VersionedConfig>(&config_str));
+        let config = match parsed {
+            Ok(versioned_config) => config::Config::from(versioned_config),
+            Err(e) => {
+                warn!("failed to parse config file: {e}");
+                self.gui_local.reload_error =
+                    Some(format!("The configuration file was not loaded: {e}"));
+                return;
+            }
+        };
+        let resolved_config = match config.resolve_inheritance() {
+            Ok(resolved_config) => resolved_config,
+            Err(e) => {
+                warn!("could not resolve profile inheritance: {e}");
+                self.gui_local.reload_error =
+                    Some(format!("The configuration file was not loaded: {e}"));
+                return;
+            }
+        };
+        self.gui_local.reload_error = None;
+
+        if config == self.disk_config {
+            return;
+        }
+        info!("configuration file changed outside Reemap");
+        self.disk_config = config.clone();
+
+        // Applying would save over the remaps that were left out, so keep the file as it was.
+        if skipped > 0 {
+            warn!("skipped {skipped} remaps this version does not understand");
+            let copy_note = match config_file::copy_aside(&self.config_path, "newer") {
+                Ok(copy_path) => format!("The file was copied to {}.", copy_path.display()),
+                Err(e) => {
+                    error!("could not copy configuration: {e}");
+                    format!(
+                        "Reemap could not make a copy of the file ({e}). Back up {} yourself \
+                        before pressing Yes.",
+                        self.config_path.display()
+                    )
+                }
+            };
+            let load = native_dialog::DialogBuilder::message()
+                .set_level(native_dialog::MessageLevel::Warning)
+                .set_title("Configuration from a newer version")
+                .set_text(format!(
+                    "The configuration file was changed outside Reemap, and has {skipped} remaps \
+                    that this version of Reemap does not understand. They will be left out.\n\n\
+                    {copy_note}\n\n\
+                    Press Yes to load it without them. Reemap will overwrite the file the next \
+                    time you click \"Apply\".\n\n\
+                    Press No to keep the configuration you have open."
+                ))
+                .confirm()
+                .show()
+                .unwrap();
+            if !load {
+                info!("not loading a configuration with skipped remaps");
+                return;
+            }
+        }
+
+        // Don't silently throw away the user's work.
+        let unsaved_changes = self.current_config != self.config;
+        if unsaved_changes {
+            let reload = native_dialog::DialogBuilder::message()
+                .set_level(native_dialog::MessageLevel::Warning)
+                .set_title("Configuration file changed")
+                .set_text(
+                    "The configuration file was changed outside Reemap, but you have unsaved \
+                    changes.\n\n\
+                    Press Yes to load the new configuration and discard your changes.\n\n\
+                    Press No to keep your changes. Reemap will overwrite the file the next time you \
+                    click \"Apply\".",
+                )
+                .confirm()
+                .show()
+                .unwrap();
+            if !reload {
+                info!("keeping unsaved changes");
+                return;
+            }
+        }
+
+        if !self.update_hookthread(resolved_config) {
+            return;
+        }
+        self.history
+            .describe_next("Load changes made outside Reemap");
+        self.config = config.clone();
+        self.current_config = config;
+        if let Err(e) = self.update_overlay() {
+            warn!("{e}");
+        }
+        // The profile or layer on screen may not exist anymore.
+        self.gui_local.menu = GuiMenu::Main;
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 enum ProfileConditionUI {
     // custom
     Always,
-    TitleAndProcess,
-    Title,
-    Process,
+    MatchAll,
+    MatchAny,
 }
 
 impl Default for ProfileConditionUI {
     fn default() -> Self {
-        Self::TitleAndProcess
+        Self::MatchAll
     }
 }
 
@@ -118,29 +363,25 @@ impl std::fmt::Display for ProfileConditionUI {
         match self {
             // custom
             Self::Always => write!(f, "Always active"),
-            Self::TitleAndProcess => write!(f, "Window title and process"),
-            Self::Title => write!(f, "Window title"),
-            Self::Process => write!(f, "Process"),
+            Self::MatchAll => write!(f, "Window matches all"),
+            Self::MatchAny => write!(f, "Window matches any"),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 enum BaseRemapPolicyUI {
+    #[default]
+    Inherit,
     NoRemap,
     Remap,
     Suppress,
 }
 
-impl Default for BaseRemapPolicyUI {
-    fn default() -> Self {
-        Self::NoRemap
-    }
-}
-
 impl std::fmt::Display for BaseRemapPolicyUI {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            Self::Inherit => write!(f, "Not set"),
             Self::NoRemap => write!(f, "No Remap"),
             Self::Remap => write!(f, "Remap"),
             Self::Suppress => write!(f, "Suppress"),
@@ -173,7 +414,7 @@ impl std::fmt::Display for RemapPolicyUI {
     }
 }
 
-#[derive(Debug, Default, Clone, PartialEq)]
+#[derive(Debug, Default)]
 pub struct GuiLocal {
     menu: GuiMenu,
     remaps_search_base: RemapsSearchOpts,
@@ -189,8 +430,23 @@ pub struct GuiLocal {
     new_remap_modal: NewRemapModalOpts,
     new_base_remap_modal: NewBaseRemapModalOpts,
     see_buttons_modal: bool,
+    stats_modal: ui_stats::StatsModalOpts,
     about_modal: bool,
     settings_modal: SettingsModalOpts,
+    restore_modal: RestoreModalOpts,
+    history_panel: bool,
+    reload_error: Option<String>,
+    apply_status: Option<ApplyStatus>, // How the last apply went. None until something is applied.
+    // How installing the last applied configuration went, until the hook thread answers.
+    apply_answer: Option<oneshot::Receiver<hooks::UpdateResult>>,
+    hook_notice: Option<String>, // The last thing the hook thread's supervisor had to say.
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApplyStatus {
+    Applying, // Waiting for the hook thread to install the remaps
+    Applied,
+    Failed(String),
 }
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -211,40 +467,175 @@ pub struct RearrangeLayersModalOpts {
     new_order: Vec<config::Layer>,
 }
 
-#[derive(Debug, Default, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct EditProfileModalOpts {
     modal_open: bool,
     name: String,
     condition: ProfileConditionUI,
+    use_title: bool,
     title: String,
+    use_process: bool,
     process: String,
-    open_windows: Vec<query_windows::WindowInfo>,
+    use_class: bool,
+    class: String,
+    use_path: bool,
+    path: String,
+    use_ancestor: bool,
+    ancestor: String,
+    open_windows: Vec<platform::WindowInfo>,
+    parent: Option<String>,
+    parent_choices: Vec<String>,
+    // A condition the fields above cannot show exactly, like All and Any nested in each other. It
+    // is shown as text and kept as it is, unless the user starts over.
+    kept_condition: Option<config::ProfileCondition>,
+}
+
+impl Default for EditProfileModalOpts {
+    fn default() -> Self {
+        Self {
+            modal_open: false,
+            name: String::new(),
+            condition: ProfileConditionUI::default(),
+            use_title: true,
+            title: String::new(),
+            use_process: true,
+            process: String::new(),
+            use_class: false,
+            class: String::new(),
+            use_path: false,
+            path: String::new(),
+            use_ancestor: false,
+            ancestor: String::new(),
+            open_windows: Vec::new(),
+            parent: None,
+            parent_choices: Vec::new(),
+            kept_condition: None,
+        }
+    }
 }
 
 impl EditProfileModalOpts {
+    // Open the modal to edit an existing profile.
+    // The fields only hold one level of All/Any, with each kind of criterion once. Anything else
+    // is kept as it is rather than flattened.
+    fn from_profile(profile: &config::Profile, parent_choices: Vec<String>) -> Self {
+        let condition = &profile.condition;
+        let mut result = Self {
+            modal_open: true,
+            name: profile.name.clone(),
+            condition: match condition {
+                config::ProfileCondition::Always => ProfileConditionUI::Always,
+                config::ProfileCondition::Any(_) => ProfileConditionUI::MatchAny,
+                _ => ProfileConditionUI::MatchAll,
+            },
+            use_title: false,
+            use_process: false,
+            open_windows: platform::Native.open_windows(),
+            parent: profile.parent.clone(),
+            parent_choices,
+            ..Default::default()
+        };
+        result.set_criteria(condition);
+        if result.clone().extract_condition() != *condition {
+            result.kept_condition = Some(condition.clone());
+        }
+        result
+    }
+    fn set_criteria(&mut self, condition: &config::ProfileCondition) {
+        use config::ProfileCondition as PC;
+        match condition {
+            PC::Always => (),
+            PC::TitleAndProcess { title, process } => {
+                (self.use_title, self.title) = (true, title.clone());
+                (self.use_process, self.process) = (true, process.clone());
+            }
+            PC::Title { title } => (self.use_title, self.title) = (true, title.clone()),
+            PC::Process { process } => (self.use_process, self.process) = (true, process.clone()),
+            PC::WindowClass { class } => (self.use_class, self.class) = (true, class.clone()),
+            PC::ExecutablePath { path } => (self.use_path, self.path) = (true, path.clone()),
+            PC::AncestorProcess { process } => {
+                (self.use_ancestor, self.ancestor) = (true, process.clone())
+            }
+            PC::All(conditions) | PC::Any(conditions) => {
+                for condition in conditions {
+                    self.set_criteria(condition);
+                }
+            }
+        }
+    }
+    // Fill in every field from a window the user picked from the list.
+    fn set_window(&mut self, window: platform::WindowInfo) {
+        self.title = window.title;
+        self.process = window.process;
+        self.class = window.class;
+        self.path = window.path;
+        self.ancestor = window.ancestors.into_iter().next().unwrap_or_default();
+    }
     fn extract_condition(self) -> config::ProfileCondition {
-        match self.condition {
-            ProfileConditionUI::Always => config::ProfileCondition::Always,
-            ProfileConditionUI::TitleAndProcess => config::ProfileCondition::TitleAndProcess {
+        use config::ProfileCondition as PC;
+        if let Some(condition) = self.kept_condition {
+            return condition;
+        }
+        let mut criteria = Vec::new();
+        // Title and process together is the classic condition; keep using it where it fits.
+        if self.use_title && self.use_process && self.condition == ProfileConditionUI::MatchAll {
+            criteria.push(PC::TitleAndProcess {
                 title: self.title,
                 process: self.process,
-            },
-            ProfileConditionUI::Title => config::ProfileCondition::Title { title: self.title },
-            ProfileConditionUI::Process => config::ProfileCondition::Process {
-                process: self.process,
-            },
+            });
+        } else {
+            if self.use_title {
+                criteria.push(PC::Title { title: self.title });
+            }
+            if self.use_process {
+                criteria.push(PC::Process {
+                    process: self.process,
+                });
+            }
+        }
+        if self.use_class {
+            criteria.push(PC::WindowClass { class: self.class });
+        }
+        if self.use_path {
+            criteria.push(PC::ExecutablePath { path: self.path });
+        }
+        if self.use_ancestor {
+            criteria.push(PC::AncestorProcess {
+                process: self.ancestor,
+            });
+        }
+        match self.condition {
+            ProfileConditionUI::Always => PC::Always,
+            ProfileConditionUI::MatchAll | ProfileConditionUI::MatchAny if criteria.len() == 1 => {
+                criteria.remove(0)
+            }
+            ProfileConditionUI::MatchAll => PC::All(criteria),
+            ProfileConditionUI::MatchAny => PC::Any(criteria),
         }
     }
     fn valid(&self) -> bool {
-        !self.name.is_empty()
-            && match self.condition {
-                ProfileConditionUI::TitleAndProcess => {
-                    !self.title.is_empty() && !self.process.is_empty()
-                }
-                ProfileConditionUI::Title => !self.title.is_empty(),
-                ProfileConditionUI::Process => !self.process.is_empty(),
-                ProfileConditionUI::Always => true,
+        let criteria = [
+            (self.use_title, &self.title),
+            (self.use_process, &self.process),
+            (self.use_class, &self.class),
+            (self.use_path, &self.path),
+            (self.use_ancestor, &self.ancestor),
+        ];
+        if self.name.is_empty() {
+            return false;
+        }
+        if self.kept_condition.is_some() {
+            return true;
+        }
+        match self.condition {
+            ProfileConditionUI::MatchAll | ProfileConditionUI::MatchAny => {
+                criteria.iter().any(|(used, _)| *used)
+                    && criteria
+                        .iter()
+                        .all(|(used, value)| !*used || !value.is_empty())
             }
+            ProfileConditionUI::Always => true,
+        }
     }
 }
 
@@ -252,6 +643,7 @@ impl From<EditProfileModalOpts> for config::Profile {
     fn from(value: EditProfileModalOpts) -> Self {
         Self {
             name: value.name.clone(),
+            parent: value.parent.clone(),
             condition: value.extract_condition(),
             ..Default::default()
         }
@@ -302,6 +694,16 @@ pub struct SettingsModalOpts {
     background: config::Background,
     current_run_on_login: Option<bool>,
     show_rare_keys: bool,
+    pin_hotkey: bool,
+    overlay: bool,
+    overlay_port: u16,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RestoreModalOpts {
+    modal_open: bool,
+    backups: Vec<config_file::Backup>,
+    selected: Option<usize>,
 }
 
 // All the possible menus the GUI can be in at any point in time.
@@ -333,6 +735,19 @@ impl crate::gui::TrayApp for ReemApp {
         } = app_ctx;
 
         ctx.set_visuals(egui::Visuals::dark());
+        self.poll_apply_answer(ctx);
+
+        // Undo and redo, unless a text field wants the keys for itself or a modal is open.
+        if !ctx.wants_keyboard_input() && ctx.memory(|mem| mem.top_modal_layer().is_none()) {
+            if ctx.input_mut(|i| i.consume_shortcut(&UNDO_SHORTCUT)) {
+                self.undo();
+            }
+            if ctx.input_mut(|i| i.consume_shortcut(&REDO_SHORTCUT)) {
+                self.redo();
+            }
+        }
+        // Edits made this frame are recorded against the menu they were made from.
+        let menu_before = self.gui_local.menu.clone();
 
         // This is a lot less falliable than having a "dirty" flag set every time a setting
         // changes. Yet, it is probably a lot less performant. This could be optimized.
@@ -365,6 +780,24 @@ impl crate::gui::TrayApp for ReemApp {
                             });
                         });
                         ui.separator();
+                        if ui.button("Import Configuration").clicked() {
+                            if let Some(config) = import_config_dialog() {
+                                self.history.describe_next("Import configuration");
+                                self.config = config;
+                                self.gui_local.menu = GuiMenu::Main;
+                            }
+                        }
+                        if ui.button("Export Configuration").clicked() {
+                            export_config_dialog(self.config.clone());
+                        }
+                        if ui.button("Restore Previous Configuration").clicked() {
+                            self.gui_local.restore_modal = RestoreModalOpts {
+                                modal_open: true,
+                                backups: config_file::list_backups(&self.config_path),
+                                selected: None,
+                            };
+                        }
+                        ui.separator();
                         if ui.button("Settings").clicked() {
                             if unsaved_changes {
                                 native_dialog::DialogBuilder::message()
@@ -375,7 +808,7 @@ impl crate::gui::TrayApp for ReemApp {
                                     .show()
                                     .unwrap();
                             } else {
-                                let run_on_login = match crate::registry::is_registered_run_on_login()
+                                let run_on_login = match platform::Native.runs_on_login()
                                  {
                                     Ok(val) => Some(val),
                                     Err(e) => {
@@ -395,16 +828,51 @@ impl crate::gui::TrayApp for ReemApp {
                                     run_on_login,
                                     background: self.config.background,
                                     current_run_on_login: run_on_login,
-                                    show_rare_keys: self.config.show_rare_keys
+                                    show_rare_keys: self.config.show_rare_keys,
+                                    pin_hotkey: self.config.pin_hotkey.is_some(),
+                                    overlay: self.config.overlay_port.is_some(),
+                                    overlay_port: self
+                                        .config
+                                        .overlay_port
+                                        .unwrap_or(overlay::DEFAULT_PORT),
                                 };
                             }
                         }
                     });
+                    ui.menu_button("Edit", |ui| {
+                        let undo_button = egui::Button::new("Undo")
+                            .shortcut_text(ctx.format_shortcut(&UNDO_SHORTCUT));
+                        let undo_response = ui.add_enabled(self.history.can_undo(), undo_button);
+                        let undo_response = match self.history.undo_description() {
+                            Some(description) => undo_response.on_hover_text(description),
+                            None => undo_response,
+                        };
+                        if undo_response.clicked() {
+                            self.undo();
+                        }
+                        let redo_button = egui::Button::new("Redo")
+                            .shortcut_text(ctx.format_shortcut(&REDO_SHORTCUT));
+                        let redo_response = ui.add_enabled(self.history.can_redo(), redo_button);
+                        let redo_response = match self.history.redo_description() {
+                            Some(description) => redo_response.on_hover_text(description),
+                            None => redo_response,
+                        };
+                        if redo_response.clicked() {
+                            self.redo();
+                        }
+                        ui.separator();
+                        if ui.checkbox(&mut self.gui_local.history_panel, "History").clicked() {
+                            ui.close_menu();
+                        }
+                    });
                     ui.menu_button("Tools", |ui| {
                         if ui.button("Button Viewer").clicked() {
                             self.gui_local.see_buttons_modal = true;
                             self.hookthread_proxy.register_observe_inputs();
                         }
+                        if ui.button("Button Statistics").clicked() {
+                            self.gui_local.stats_modal.modal_open = true;
+                        }
                     });
                     ui.menu_button("Help", |ui| {
                         ui.hyperlink_to("Website", WEB_LINK);
@@ -417,7 +885,7 @@ impl crate::gui::TrayApp for ReemApp {
             });
 
         // Display a message to inform the user if remaps are disabled
-        if unsafe { KeyboardAndMouse::GetKeyState(KeyboardAndMouse::VK_SCROLL.0.into()) & 1 > 0 } {
+        if platform::Native.escape_hatch() {
             let warning_frame = egui::Frame::new().fill(egui::Color32::DARK_RED);
             egui::TopBottomPanel::bottom("ui_warn_panel")
                 .frame(warning_frame)
@@ -431,6 +899,59 @@ impl crate::gui::TrayApp for ReemApp {
                 });
         }
 
+        // Display a message if an outside edit to the configuration file could not be loaded
+        if let Some(ref reload_error) = self.gui_local.reload_error {
+            let warning_frame = egui::Frame::new().fill(egui::Color32::DARK_RED);
+            egui::TopBottomPanel::bottom("ui_reload_error_panel")
+                .frame(warning_frame)
+                .show(ctx, |ui| {
+                    ui.with_layout(
+                        egui::Layout::centered_and_justified(egui::Direction::BottomUp),
+                        |ui| {
+                            ui.strong(reload_error);
+                        },
+                    );
+                });
+        }
+
+        // Display a message if the input hooks stopped working, until it is dismissed
+        if let Some(ref hook_notice) = self.gui_local.hook_notice {
+            let mut dismissed = false;
+            let warning_frame = egui::Frame::new().fill(egui::Color32::DARK_RED);
+            egui::TopBottomPanel::bottom("ui_hook_notice_panel")
+                .frame(warning_frame)
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        dismissed = ui.button("Dismiss").clicked();
+                        ui.strong(hook_notice);
+                    });
+                });
+            if dismissed {
+                self.gui_local.hook_notice = None;
+            }
+        }
+
+        // Display a message if the last apply did not fully work
+        if let Some(ApplyStatus::Failed(ref message)) = self.gui_local.apply_status {
+            let warning_frame = egui::Frame::new().fill(egui::Color32::DARK_RED);
+            egui::TopBottomPanel::bottom("ui_apply_error_panel")
+                .frame(warning_frame)
+                .show(ctx, |ui| {
+                    ui.with_layout(
+                        egui::Layout::centered_and_justified(egui::Direction::BottomUp),
+                        |ui| {
+                            ui.strong(message);
+                        },
+                    );
+                });
+        }
+
+        if self.gui_local.history_panel {
+            egui::SidePanel::right("history panel")
+                .default_width(200.0)
+                .show(ctx, |ui| ui_history(ui, self));
+        }
+
         egui::CentralPanel::default()
             .frame(
                 egui::Frame::new()
@@ -457,32 +978,76 @@ impl crate::gui::TrayApp for ReemApp {
                     ui.separator();
                     ui.add_space(style::SPACING);
 
+                    let warnings = lint::check(&self.config);
                     let menu = self.gui_local.menu.clone();
                     match menu {
-                        GuiMenu::Main => ui_main(ui, self),
-                        GuiMenu::Profile { profile_idx } => ui_profile(
-                            ui,
-                            &mut self.config.profiles[profile_idx],
-                            profile_idx,
-                            &mut self.gui_local.menu,
-                            &mut self.gui_local.remaps_search_base,
-                            self.config.show_rare_keys,
-                            UiProfileModals {
-                                copy_layers_modal: &mut self.gui_local.copy_layer_modal,
-                                rearrange_layers_modal: &mut self.gui_local.rearrange_layers_modal,
-                                edit_profile_modal: &mut self.gui_local.edit_profile_modal,
-                                new_layer_modal: &mut self.gui_local.edit_layer_modal,
-                                new_base_remap_modal: &mut self.gui_local.new_base_remap_modal,
-                            },
-                        ),
+                        GuiMenu::Main => ui_main(ui, self, &warnings),
+                        GuiMenu::Profile { profile_idx } => {
+                            // Everything this profile inherits, for display alongside its own remaps.
+                            let inherited = self.config.resolve_parent(profile_idx);
+                            let parent_choices = self
+                                .config
+                                .profiles
+                                .iter()
+                                .enumerate()
+                                .filter(|(i, _)| *i != profile_idx)
+                                .map(|(_, profile)| profile.name.clone())
+                                .collect();
+                            let old_name = self.config.profiles[profile_idx].name.clone();
+                            ui_profile(
+                                ui,
+                                &mut self.config.profiles[profile_idx],
+                                profile_idx,
+                                &inherited,
+                                parent_choices,
+                                &warnings,
+                                &mut self.gui_local.menu,
+                                &mut self.gui_local.remaps_search_base,
+                                self.config.show_rare_keys,
+                                UiProfileModals {
+                                    copy_layers_modal: &mut self.gui_local.copy_layer_modal,
+                                    rearrange_layers_modal: &mut self
+                                        .gui_local
+                                        .rearrange_layers_modal,
+                                    edit_profile_modal: &mut self.gui_local.edit_profile_modal,
+                                    new_layer_modal: &mut self.gui_local.edit_layer_modal,
+                                    new_base_remap_modal: &mut self.gui_local.new_base_remap_modal,
+                                },
+                            );
+                            // Keep profiles inheriting from this one pointing at it after a rename.
+                            let new_name = self.config.profiles[profile_idx].name.clone();
+                            if new_name != old_name {
+                                for profile in self.config.profiles.iter_mut() {
+                                    if profile.parent.as_ref() == Some(&old_name) {
+                                        profile.parent = Some(new_name.clone());
+                                    }
+                                }
+                            }
+                        }
                         GuiMenu::ProfileLayer {
                             profile_idx,
                             layer_idx,
                         } => {
+                            let inherited_layer = self
+                                .config
+                                .resolve_parent(profile_idx)
+                                .ok()
+                                .flatten()
+                                .and_then(|parent| {
+                                    let name =
+                                        &self.config.profiles[profile_idx].layers[layer_idx].name;
+                                    parent.layers.into_iter().find(|layer| layer.name == *name)
+                                });
+                            let layer_warnings: Vec<lint::Warning> = warnings
+                                .into_iter()
+                                .filter(|w| w.is_for_layer(profile_idx, layer_idx))
+                                .collect();
                             let layer = &mut self.config.profiles[profile_idx].layers[layer_idx];
                             ui_layer(
                                 ui,
                                 layer,
+                                inherited_layer.as_ref(),
+                                &layer_warnings,
                                 &mut self.gui_local.new_remap_modal,
                                 &mut self.gui_local.edit_layer_modal,
                                 &mut self.gui_local.remaps_search_layer,
@@ -503,15 +1068,65 @@ impl crate::gui::TrayApp for ReemApp {
                         *last_pressed_button,
                     );
                 }
+                if self.gui_local.stats_modal.modal_open {
+                    ui_stats_modal(ui, self);
+                }
                 if self.gui_local.about_modal {
                     about_modal(ui, &mut self.gui_local.about_modal);
                 }
+                if self.gui_local.restore_modal.modal_open {
+                    restore_modal(ui, self);
+                }
             });
         if self.schedule_discard {
             self.schedule_discard = false;
+            self.history.describe_next("Discard changes");
             self.config = self.current_config.clone();
             self.gui_local.menu = GuiMenu::Main;
         }
+        self.history.track(&self.config, &menu_before);
+    }
+    fn profile_names(&self) -> Vec<String> {
+        // Only applied profiles exist as far as the hook thread is concerned, and a disabled one
+        // would not take effect.
+        self.current_config
+            .profiles
+            .iter()
+            .filter(|profile| profile.enabled)
+            .map(|profile| profile.name.clone())
+            .collect()
+    }
+    fn pin_profile(&mut self, profile: Option<String>) {
+        if let Err(e) = self.hookthread_proxy.pin(profile) {
+            warn!("{e}");
+        }
+    }
+    fn config_file_changed(&mut self) {
+        self.reload_config();
+    }
+    fn hook_health_changed(&mut self, health: hooks::HookHealth) {
+        let notice = match health {
+            hooks::HookHealth::Recovered => {
+                "Remapping stopped working for a moment and was started again. Buttons held at the \
+                time were released."
+                    .to_string()
+            }
+            hooks::HookHealth::Failed(e) => {
+                let notice = format!(
+                    "Remapping is not working, because Reemap could not hook into the keyboard or \
+                    mouse. It will keep trying.\n\n{e}"
+                );
+                native_dialog::DialogBuilder::message()
+                    .set_level(native_dialog::MessageLevel::Error)
+                    .set_title("Remapping stopped")
+                    .set_text(notice.clone())
+                    .alert()
+                    .show()
+                    .unwrap();
+                notice
+            }
+        };
+        self.gui_local.hook_notice = Some(notice);
     }
 }
 
@@ -519,6 +1134,8 @@ fn settings_modal(ui: &mut egui::Ui, args: &mut ReemApp) {
     use ui_ok_cancel_modal::ui_ok_cancel_modal;
 
     let modal_opts = &mut args.gui_local.settings_modal;
+    // Keep a hotkey customized in the configuration file, if there is one.
+    let pin_hotkey = args.config.pin_hotkey.clone().unwrap_or_default();
 
     let ok_cancel = ui_ok_cancel_modal(ui, "", true, |ui| {
         ui.heading("Reemap Settings");
@@ -584,6 +1201,42 @@ Note: even with this setting enabled, some keys are unavailable. This includes e
 Windows defines as reserved, undefined, or unassigned. This also includes the Scroll \
 Lock key, which Reemap uses as an escape-hatch to disable all remaps.",
         );
+            ui.add_space(style::SPACING);
+            ui.separator();
+            ui.add_space(style::SPACING);
+
+            // pin hotkey
+            ui.checkbox(
+                &mut modal_opts.pin_hotkey,
+                format!("Pin hotkey ({pin_hotkey})"),
+            );
+            ui.add_space(style::SPACING);
+            ui.label(
+                "When checked, pressing the hotkey pins the active profile so it stays active \
+no matter which window is in focus. Press it again to unpin. This helps when Reemap does not \
+detect a game's window correctly, which can happen with borderless or overlay windows. You can \
+also pin profiles from the tray icon's menu.",
+            );
+            ui.add_space(style::SPACING);
+            ui.separator();
+            ui.add_space(style::SPACING);
+
+            // input overlay
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut modal_opts.overlay, "Input overlay on port");
+                ui.add_enabled(
+                    modal_opts.overlay,
+                    egui::DragValue::new(&mut modal_opts.overlay_port).range(1024..=65535),
+                );
+            });
+            ui.add_space(style::SPACING);
+            ui.label(format!(
+                "When checked, Reemap shows the buttons you hold, and what they are remapped to, \
+on a web page for streaming. Add http://localhost:{}/ to OBS as a browser source. Only programs on \
+this computer can see it. To customize the page, save your own overlay.html next to Reemap's \
+configuration file.",
+                modal_opts.overlay_port
+            ));
         });
     });
     match ok_cancel {
@@ -591,7 +1244,7 @@ Lock key, which Reemap uses as an escape-hatch to disable all remaps.",
             if modal_opts.current_run_on_login != modal_opts.run_on_login
                 && let Some(run) = modal_opts.run_on_login
             {
-                let result = crate::registry::run_on_login(run);
+                let result = platform::Native.set_run_on_login(run);
                 if let Err(e) = result {
                     native_dialog::DialogBuilder::message()
                         .set_level(native_dialog::MessageLevel::Warning)
@@ -606,6 +1259,8 @@ Lock key, which Reemap uses as an escape-hatch to disable all remaps.",
 
             args.config.background = modal_opts.background;
             args.config.show_rare_keys = modal_opts.show_rare_keys;
+            args.config.pin_hotkey = modal_opts.pin_hotkey.then_some(pin_hotkey);
+            args.config.overlay_port = modal_opts.overlay.then_some(modal_opts.overlay_port);
             modal_opts.modal_open = false;
             args.apply_changes();
         }
@@ -661,6 +1316,73 @@ fn see_buttons_modal(
     }
 }
 
+fn restore_modal(ui: &mut egui::Ui, args: &mut ReemApp) {
+    use ui_ok_cancel_modal::ui_ok_cancel_modal;
+
+    let modal_opts = &mut args.gui_local.restore_modal;
+    let can_restore = modal_opts
+        .selected
+        .and_then(|i| modal_opts.backups.get(i))
+        .is_some_and(|backup| backup.config.is_some());
+
+    let ok_cancel = ui_ok_cancel_modal(
+        ui,
+        "Restored configurations take effect once you click Apply.",
+        can_restore,
+        |ui| {
+            ui.heading("Restore Previous Configuration");
+            ui.separator();
+            ui.add_space(style::SPACING);
+            if modal_opts.backups.is_empty() {
+                ui.label(format!(
+                    "There are no backups yet. Each time you click Apply, Reemap keeps a copy of \
+                    the configuration it replaces, up to {}.",
+                    config_file::MAX_BACKUPS
+                ));
+                return;
+            }
+            style::UI_FRAME.show(ui, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (i, backup) in modal_opts.backups.iter().enumerate() {
+                        let selected = modal_opts.selected == Some(i);
+                        let text = format!("{}\n{}", backup.taken, backup.summary());
+                        if ui
+                            .add_enabled(
+                                backup.config.is_some(),
+                                egui::SelectableLabel::new(selected, text),
+                            )
+                            .on_hover_text(backup.path.display().to_string())
+                            .clicked()
+                        {
+                            modal_opts.selected = Some(i);
+                        }
+                    }
+                });
+            });
+        },
+    );
+
+    match ok_cancel {
+        Some(true) => {
+            let backup = modal_opts
+                .selected
+                .and_then(|i| modal_opts.backups.get(i))
+                .and_then(|backup| backup.config.clone());
+            modal_opts.modal_open = false;
+            if let Some(config) = backup {
+                info!("restoring configuration from backup");
+                args.history.describe_next("Restore previous configuration");
+                args.config = config;
+                args.gui_local.menu = GuiMenu::Main;
+            }
+        }
+        Some(false) => {
+            modal_opts.modal_open = false;
+        }
+        None => (),
+    }
+}
+
 fn about_modal(ui: &mut egui::Ui, modal_opts: &mut bool) {
     use egui::special_emojis::GITHUB;
 
@@ -700,7 +1422,10 @@ fn import_profile_dialog() -> Option<config::Profile> {
     }
 
     let selection = native_dialog::DialogBuilder::file()
+        .add_filter("Profile", Format::EXTENSIONS)
         .add_filter("RON", ["ron"])
+        .add_filter("JSON", ["json"])
+        .add_filter("TOML", ["toml"])
         .open_single_file()
         .show();
     let selection = match selection {
@@ -712,7 +1437,8 @@ fn import_profile_dialog() -> Option<config::Profile> {
         }
     };
 
-    let profile_str = match std::fs::read_to_string(selection) {
+    let format = Format::from_path(&selection).unwrap_or(Format::Ron);
+    let profile_str = match std::fs::read_to_string(&selection) {
         Ok(profile_str) => profile_str,
         Err(e) => {
             display_warning("Error opening file.", e);
@@ -720,7 +1446,17 @@ fn import_profile_dialog() -> Option<config::Profile> {
         }
     };
 
-    let versioned_profile: config::VersionedProfile = match ron::from_str(&profile_str) {
+    // Remaps this version does not understand would be gone once the import is saved.
+    let (versioned_profile, skipped) =
+        config::count_skipped_remaps(|| config_formats::profile_from_str(&profile_str, format));
+    if skipped > 0 {
+        display_warning(
+            "This profile was made in a newer version of Reemap.",
+            cli::Error::WouldLoseRemaps(selection, skipped),
+        );
+        return None;
+    }
+    let versioned_profile = match versioned_profile {
         Ok(prf) => prf,
         Err(e) => {
             display_warning(
@@ -748,6 +1484,8 @@ fn export_profile_dialog(profile: config::Profile) {
     let name = profile.name.clone();
     let selection = native_dialog::DialogBuilder::file()
         .add_filter("RON", ["ron"])
+        .add_filter("JSON", ["json"])
+        .add_filter("TOML", ["toml"])
         .set_filename(&name)
         .save_single_file()
         .show();
@@ -755,10 +1493,14 @@ fn export_profile_dialog(profile: config::Profile) {
     match selection {
         Ok(None) => (),
         Ok(Some(path)) => {
-            let profile_str =
-                ron::ser::to_string_pretty(&versioned_profile, ron::ser::PrettyConfig::new())
-                    .unwrap();
-            match std::fs::write(&path, profile_str) {
+            // Anything without a known extension is saved as RON.
+            let format = Format::from_path(&path).unwrap_or(Format::Ron);
+            let written = config_formats::profile_to_string(&versioned_profile, format)
+                .map_err(|e| e.to_string())
+                .and_then(|profile_str| {
+                    std::fs::write(&path, profile_str).map_err(|e| e.to_string())
+                });
+            match written {
                 Ok(()) => {
                     native_dialog::DialogBuilder::message()
                         .set_level(native_dialog::MessageLevel::Info)
@@ -788,3 +1530,128 @@ fn export_profile_dialog(profile: config::Profile) {
         Err(e) => warn!(?e, "error opening export dialog"),
     }
 }
+
+fn import_config_dialog() -> Option<config::Config> {
+    fn display_warning(text: &str, ctx: impl std::fmt::Display) {
+        let body_text = format!("{text}\n\n{ctx}");
+        native_dialog::DialogBuilder::message()
+            .set_level(native_dialog::MessageLevel::Error)
+            .set_title("Error importing configuration")
+            .set_text(&body_text)
+            .alert()
+            .show()
+            .unwrap();
+        warn!("error opening configuration: {}", &body_text);
+    }
+
+    let selection = native_dialog::DialogBuilder::file()
+        .add_filter("Configuration", Format::EXTENSIONS)
+        .add_filter("RON", ["ron"])
+        .add_filter("JSON", ["json"])
+        .add_filter("TOML", ["toml"])
+        .open_single_file()
+        .show();
+    let selection = match selection {
+        Ok(Some(path)) => path,
+        Ok(None) => return None,
+        Err(e) => {
+            display_warning("Error with file selection dialog.", e);
+            return None;
+        }
+    };
+
+    let format = Format::from_path(&selection).unwrap_or(Format::Ron);
+    let config_str = match std::fs::read_to_string(&selection) {
+        Ok(config_str) => config_str,
+        Err(e) => {
+            display_warning("Error opening file.", e);
+            return None;
+        }
+    };
+
+    // Remaps this version does not understand would be gone once the import is saved.
+    let (versioned_config, skipped) =
+        config::count_skipped_remaps(|| config_formats::config_from_str(&config_str, format));
+    if skipped > 0 {
+        display_warning(
+            "This configuration was made in a newer version of Reemap.",
+            cli::Error::WouldLoseRemaps(selection, skipped),
+        );
+        return None;
+    }
+    let versioned_config = match versioned_config {
+        Ok(cfg) => cfg,
+        Err(e) => {
+            display_warning(
+                "Error parsing configuration. Was this configuration made in a newer version of \
+                Reemap?",
+                e,
+            );
+            return None;
+        }
+    };
+
+    let config = config::Config::from(versioned_config);
+    native_dialog::DialogBuilder::message()
+        .set_level(native_dialog::MessageLevel::Info)
+        .set_title("Imported configuration")
+        .set_text(format!(
+            "Imported a configuration with {} profiles. Click Apply to start using it.",
+            config.profiles.len()
+        ))
+        .alert()
+        .show()
+        .unwrap();
+    info!("imported configuration");
+
+    Some(config)
+}
+
+fn export_config_dialog(config: config::Config) {
+    let selection = native_dialog::DialogBuilder::file()
+        .add_filter("RON", ["ron"])
+        .add_filter("JSON", ["json"])
+        .add_filter("TOML", ["toml"])
+        .set_filename("config")
+        .save_single_file()
+        .show();
+    let versioned_config = config::VersionedConfig::from(config);
+    match selection {
+        Ok(None) => (),
+        Ok(Some(path)) => {
+            // Anything without a known extension is saved as RON.
+            let format = Format::from_path(&path).unwrap_or(Format::Ron);
+            let written = config_formats::config_to_string(&versioned_config, format)
+                .map_err(|e| e.to_string())
+                .and_then(|config_str| {
+                    std::fs::write(&path, config_str).map_err(|e| e.to_string())
+                });
+            match written {
+                Ok(()) => {
+                    native_dialog::DialogBuilder::message()
+                        .set_level(native_dialog::MessageLevel::Info)
+                        .set_title("Exported configuration")
+                        .set_text(format!(
+                            "Exported configuration to {}",
+                            path.to_str().unwrap_or("(path not UTF-8)")
+                        ))
+                        .alert()
+                        .show()
+                        .unwrap();
+                    info!("exported configuration");
+                }
+                Err(e) => {
+                    native_dialog::DialogBuilder::message()
+                        .set_level(native_dialog::MessageLevel::Warning)
+                        .set_title("Error exporting configuration")
+                        .set_text(format!("Reemap could not export the configuration.\n\n{e}"))
+                        .alert()
+                        .show()
+                        .unwrap();
+                    warn!(?e, "failed to export configuration");
+                }
+            }
+        }
+        Err(e) => warn!(?e, "error opening export dialog"),
+    }
+}
@@ -17,6 +17,7 @@ use strum::IntoEnumIterator;
 pub fn ui_base_layer(
     ui: &mut egui::Ui,
     layer: &mut config::BaseLayer,
+    inherited: Option<&config::BaseLayer>,
     new_base_remap_modal: &mut NewBaseRemapModalOpts,
     remaps_search: &mut RemapsSearchOpts,
     show_rare_keys: bool,
@@ -32,6 +33,7 @@ pub fn ui_base_layer(
                     ui_base_remaps_table(
                         ui,
                         layer,
+                        inherited,
                         new_base_remap_modal,
                         remaps_search,
                         show_rare_keys,
@@ -70,6 +72,7 @@ pub fn ui_base_layer(
 pub fn ui_base_remaps_table(
     ui: &mut egui::Ui,
     layer: &mut config::BaseLayer,
+    inherited: Option<&config::BaseLayer>,
     new_base_remap_modal: &mut NewBaseRemapModalOpts,
     remaps_search: &RemapsSearchOpts,
     show_rare_keys: bool,
@@ -78,6 +81,12 @@ pub fn ui_base_remaps_table(
     use buttons::key::KeyType;
     use egui_extras::{Column, TableBuilder};
 
+    // What the button actually does once the parent profile, if any, is merged in.
+    let effective_policy = |button: Button| match (&layer.policy[button], inherited) {
+        (config::BaseRemapPolicy::Inherit, Some(parent)) => &parent.policy[button],
+        (policy, _) => policy,
+    };
+
     let mut pointing_hand = false;
     let mut button_select = None;
     TableBuilder::new(ui)
@@ -104,7 +113,7 @@ pub fn ui_base_remaps_table(
             let key_iter = buttons::key::KeyButton::iter()
                 .filter(|key| {
                     match (
-                        &layer.policy[buttons::Button::from(*key)],
+                        effective_policy(buttons::Button::from(*key)),
                         show_rare_keys,
                         key.key_type(),
                     ) {
@@ -128,8 +137,8 @@ pub fn ui_base_remaps_table(
                     let mod_search = remaps_search.search_string.trim().to_lowercase();
                     mod_search.is_empty()
                         || button.to_string().to_lowercase().contains(&mod_search)
-                        || if let config::BaseRemapPolicy::Remap(ref outputs) =
-                            layer.policy[*button]
+                        || if let config::BaseRemapPolicy::Remap(outputs) =
+                            effective_policy(*button)
                             && outputs.iter().any(|output| {
                                 output.to_string().to_lowercase().contains(&mod_search)
                             })
@@ -141,7 +150,10 @@ pub fn ui_base_remaps_table(
                 })
                 .filter(|button| {
                     !remaps_search.hide_unmapped
-                        || !matches!(layer.policy[*button], config::BaseRemapPolicy::NoRemap)
+                        || !matches!(
+                            effective_policy(*button),
+                            config::BaseRemapPolicy::Inherit | config::BaseRemapPolicy::NoRemap
+                        )
                 })
             {
                 body.row(style::ROW_HEIGHT, |mut row| {
@@ -158,9 +170,18 @@ pub fn ui_base_remaps_table(
                         ui.add(egui::Label::new(button.to_string()).truncate());
                     });
                     row.col(|ui| {
-                        let policy = layer.policy[button].clone();
+                        let parent_policy = inherited.map(|parent| &parent.policy[button]);
+                        let text = match (&layer.policy[button], parent_policy) {
+                            (policy, None | Some(config::BaseRemapPolicy::Inherit)) => {
+                                policy.to_string()
+                            }
+                            (config::BaseRemapPolicy::Inherit, Some(parent_policy)) => {
+                                format!("{parent_policy} (inherited)")
+                            }
+                            (policy, Some(_)) => format!("{policy} (overrides parent)"),
+                        };
                         ui.style_mut().interaction.selectable_labels = false;
-                        ui.add(egui::Label::new(policy.to_string()).truncate());
+                        ui.add(egui::Label::new(text).truncate());
                     });
                     if row.response().hovered() {
                         pointing_hand = true;
@@ -175,14 +196,15 @@ pub fn ui_base_remaps_table(
         *new_base_remap_modal = NewBaseRemapModalOpts {
             modal_open: Some(button),
             policy: match layer.policy[button] {
+                config::BaseRemapPolicy::Inherit => BaseRemapPolicyUI::Inherit,
                 config::BaseRemapPolicy::NoRemap => BaseRemapPolicyUI::NoRemap,
                 config::BaseRemapPolicy::Remap(_) => BaseRemapPolicyUI::Remap,
                 config::BaseRemapPolicy::Suppress => BaseRemapPolicyUI::Suppress,
             },
             outputs: match layer.policy[button] {
-                config::BaseRemapPolicy::NoRemap | config::BaseRemapPolicy::Suppress => {
-                    SmallVec::new()
-                }
+                config::BaseRemapPolicy::Inherit
+                | config::BaseRemapPolicy::NoRemap
+                | config::BaseRemapPolicy::Suppress => SmallVec::new(),
                 config::BaseRemapPolicy::Remap(ref output) => output.clone(),
             },
             search: String::new(),
@@ -207,7 +229,9 @@ fn ui_new_base_remap_modal(
         get_new_remap_helper_text_base(&button, &modal_opts.outputs, &modal_opts.policy);
     let valid = match modal_opts.policy {
         BaseRemapPolicyUI::Remap => !modal_opts.outputs.is_empty(),
-        BaseRemapPolicyUI::NoRemap | BaseRemapPolicyUI::Suppress => true,
+        BaseRemapPolicyUI::Inherit | BaseRemapPolicyUI::NoRemap | BaseRemapPolicyUI::Suppress => {
+            true
+        }
     };
     let ok_cancel = ui_ok_cancel_modal(ui, &helper_text, valid, |ui| {
         ui.heading(format!("Remaps for {button}"));
@@ -220,6 +244,11 @@ fn ui_new_base_remap_modal(
             egui::ComboBox::from_id_salt("base_remap_policy")
                 .selected_text(format!("{}", &modal_opts.policy))
                 .show_ui(ui, |ui| {
+                    ui.selectable_value(
+                        &mut modal_opts.policy,
+                        BaseRemapPolicyUI::Inherit,
+                        "Not set",
+                    );
                     ui.selectable_value(
                         &mut modal_opts.policy,
                         BaseRemapPolicyUI::NoRemap,
@@ -236,6 +265,7 @@ fn ui_new_base_remap_modal(
         ui.add_space(style::SPACING);
 
         let enable_tables = match modal_opts.policy {
+            BaseRemapPolicyUI::Inherit => false,
             BaseRemapPolicyUI::NoRemap => false,
             BaseRemapPolicyUI::Remap => true,
             BaseRemapPolicyUI::Suppress => false,
@@ -274,6 +304,7 @@ fn ui_new_base_remap_modal(
     match ok_cancel {
         Some(true) => {
             *policy = match modal_opts.policy {
+                BaseRemapPolicyUI::Inherit => config::BaseRemapPolicy::Inherit,
                 BaseRemapPolicyUI::NoRemap => config::BaseRemapPolicy::NoRemap,
                 BaseRemapPolicyUI::Remap => {
                     config::BaseRemapPolicy::Remap(modal_opts.outputs.clone())
@@ -295,8 +326,14 @@ fn get_new_remap_helper_text_base(
     policy: &BaseRemapPolicyUI,
 ) -> String {
     match policy {
+        BaseRemapPolicyUI::Inherit => {
+            format!(
+                "{button} is left to the parent profile, or to the profiles active underneath \
+                this one."
+            )
+        }
         BaseRemapPolicyUI::NoRemap => {
-            format!("{button} will not be remapped.")
+            format!("{button} will not be remapped, whatever other profiles do.")
         }
         BaseRemapPolicyUI::Remap => {
             if outputs.is_empty() {
@@ -6,7 +6,7 @@
 use crate::gui::reemapp::style;
 use crate::gui::reemapp::ui_ok_cancel_modal::ui_ok_cancel_modal;
 use crate::gui::reemapp::{EditProfileModalOpts, ProfileConditionUI};
-use crate::query_windows;
+use crate::platform::{self, WindowQuery};
 
 pub fn ui_edit_profile_modal(
     ui: &mut egui::Ui,
@@ -21,23 +21,14 @@ pub fn ui_edit_profile_modal(
     } else if modal_opts.name.is_empty() {
         String::from("Choose a profile name")
     } else {
-        String::from("Choose a window title or process")
+        String::from("Choose which window details to match")
     };
     ui_ok_cancel_modal(ui, &helper_text, valid, |ui| {
-        let enable_title = matches!(
-            modal_opts.condition,
-            ProfileConditionUI::TitleAndProcess | ProfileConditionUI::Title
-        );
-        let enable_process = matches!(
-            modal_opts.condition,
-            ProfileConditionUI::TitleAndProcess | ProfileConditionUI::Process
-        );
-        let enable_table = match modal_opts.condition {
-            ProfileConditionUI::TitleAndProcess
-            | ProfileConditionUI::Title
-            | ProfileConditionUI::Process => true,
-            ProfileConditionUI::Always => false,
-        };
+        let enable_criteria = modal_opts.kept_condition.is_none()
+            && match modal_opts.condition {
+                ProfileConditionUI::MatchAll | ProfileConditionUI::MatchAny => true,
+                ProfileConditionUI::Always => false,
+            };
         ui.heading(heading);
         ui.separator();
 
@@ -57,69 +48,89 @@ pub fn ui_edit_profile_modal(
                 ui.end_row();
 
                 ui.label("Condition");
-                egui::ComboBox::from_id_salt("condition")
-                    .selected_text(modal_opts.condition.to_string())
+                if let Some(kept_condition) = &modal_opts.kept_condition {
+                    // Too involved for the fields below. Edit it in the configuration file, or
+                    // replace it.
+                    let mut start_over = false;
+                    ui.horizontal(|ui| {
+                        ui.add(egui::Label::new(kept_condition.helper_text()).truncate())
+                            .on_hover_text(
+                                "This condition can only be changed in the configuration file",
+                            );
+                        start_over = ui.button("Start over").clicked();
+                    });
+                    if start_over {
+                        modal_opts.kept_condition = None;
+                    }
+                } else {
+                    egui::ComboBox::from_id_salt("condition")
+                        .selected_text(modal_opts.condition.to_string())
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(
+                                &mut modal_opts.condition,
+                                ProfileConditionUI::Always,
+                                "Always active",
+                            );
+                            ui.selectable_value(
+                                &mut modal_opts.condition,
+                                ProfileConditionUI::MatchAll,
+                                "Window matches all",
+                            );
+                            ui.selectable_value(
+                                &mut modal_opts.condition,
+                                ProfileConditionUI::MatchAny,
+                                "Window matches any",
+                            );
+                        });
+                }
+                ui.end_row();
+
+                ui.label("Inherit from");
+                egui::ComboBox::from_id_salt("parent")
+                    .selected_text(modal_opts.parent.as_deref().unwrap_or("(none)"))
                     .show_ui(ui, |ui| {
-                        ui.selectable_value(
-                            &mut modal_opts.condition,
-                            ProfileConditionUI::Always,
-                            "Always active",
-                        );
-                        ui.selectable_value(
-                            &mut modal_opts.condition,
-                            ProfileConditionUI::TitleAndProcess,
-                            "Window title and process",
-                        );
-                        ui.selectable_value(
-                            &mut modal_opts.condition,
-                            ProfileConditionUI::Title,
-                            "Window title",
-                        );
-                        ui.selectable_value(
-                            &mut modal_opts.condition,
-                            ProfileConditionUI::Process,
-                            "Process",
-                        );
+                        ui.selectable_value(&mut modal_opts.parent, None, "(none)");
+                        for name in modal_opts.parent_choices.iter() {
+                            ui.selectable_value(&mut modal_opts.parent, Some(name.clone()), name);
+                        }
                     });
                 ui.end_row();
             });
         ui.add_space(style::SPACING * 2.0);
 
         ui.add_enabled(
-            enable_table,
+            enable_criteria,
             egui::Label::new("Select from a list of running applications:"),
         );
 
         StripBuilder::new(ui)
-            .size(Size::exact(200.0))
+            .size(Size::exact(120.0))
             .size(Size::initial(style::BUTTON_HEIGHT))
             .size(Size::exact(style::SPACING * 2.0))
-            .size(Size::initial(style::BUTTON_HEIGHT * 2.0))
+            .size(Size::initial(
+                style::BUTTON_HEIGHT * 5.0 + style::SPACING * 4.0,
+            ))
             .size(Size::exact(style::SPACING))
             .size(Size::initial(1.0))
             .vertical(|mut strip| {
                 strip.cell(|ui| {
-                    ui.add_enabled_ui(enable_table, |ui| {
+                    ui.add_enabled_ui(enable_criteria, |ui| {
                         style::UI_FRAME.show(ui, |ui| {
-                            if let Some(query_windows::WindowInfo {
-                                title,
-                                process,
-                                rect: _,
-                            }) = ui_open_windows_table(ui, &modal_opts.open_windows)
+                            if let Some(window) =
+                                ui_open_windows_table(ui, &modal_opts.open_windows)
                             {
-                                modal_opts.title = title;
-                                modal_opts.process = process;
+                                modal_opts.set_window(window);
                             }
                         });
                     });
                 });
                 strip.cell(|ui| {
-                    ui.add_enabled_ui(enable_table, |ui| {
+                    ui.add_enabled_ui(enable_criteria, |ui| {
                         if ui
                             .add_sized(style::BUTTON_SIZE, egui::Button::new("Refresh"))
                             .clicked()
                         {
-                            modal_opts.open_windows = query_windows::enumerate_open_windows();
+                            modal_opts.open_windows = platform::Native.open_windows();
                         }
                     });
                 });
@@ -130,20 +141,46 @@ pub fn ui_edit_profile_modal(
                         .num_columns(2)
                         .spacing([style::SPACING, style::SPACING])
                         .show(ui, |ui| {
-                            ui.add_enabled(enable_title, egui::Label::new("Window Title"));
-                            ui.add_enabled(
-                                enable_title,
-                                egui::TextEdit::singleline(&mut modal_opts.title)
-                                    .hint_text("Insert title"),
-                            );
-                            ui.end_row();
-                            ui.add_enabled(enable_process, egui::Label::new("Process"));
-                            ui.add_enabled(
-                                enable_process,
-                                egui::TextEdit::singleline(&mut modal_opts.process)
-                                    .hint_text("Insert process"),
-                            );
-                            ui.end_row();
+                            let criteria = [
+                                (
+                                    &mut modal_opts.use_title,
+                                    &mut modal_opts.title,
+                                    "Window title",
+                                    "Insert title",
+                                ),
+                                (
+                                    &mut modal_opts.use_process,
+                                    &mut modal_opts.process,
+                                    "Process",
+                                    "Insert process",
+                                ),
+                                (
+                                    &mut modal_opts.use_class,
+                                    &mut modal_opts.class,
+                                    "Window class",
+                                    "Insert window class",
+                                ),
+                                (
+                                    &mut modal_opts.use_path,
+                                    &mut modal_opts.path,
+                                    "Program path",
+                                    "Insert full path to the program",
+                                ),
+                                (
+                                    &mut modal_opts.use_ancestor,
+                                    &mut modal_opts.ancestor,
+                                    "Launched by",
+                                    "Insert parent or ancestor process",
+                                ),
+                            ];
+                            for (used, value, label, hint) in criteria {
+                                ui.add_enabled(enable_criteria, egui::Checkbox::new(used, label));
+                                ui.add_enabled(
+                                    enable_criteria && *used,
+                                    egui::TextEdit::singleline(value).hint_text(hint),
+                                );
+                                ui.end_row();
+                            }
                         });
                 });
                 strip.empty();
@@ -156,8 +193,8 @@ pub fn ui_edit_profile_modal(
 
 fn ui_open_windows_table(
     ui: &mut egui::Ui,
-    windows: &[query_windows::WindowInfo],
-) -> Option<query_windows::WindowInfo> {
+    windows: &[platform::WindowInfo],
+) -> Option<platform::WindowInfo> {
     use egui_extras::{Column, TableBuilder};
 
     let process_exclude_list = ["reemap.exe"];
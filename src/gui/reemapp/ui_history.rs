@@ -0,0 +1,47 @@
+// Copyright 2025 Jordan Johnson
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! UI for the history panel, listing every change that can be undone or redone.
+
+use super::ReemApp;
+use crate::gui::reemapp::style;
+
+pub fn ui_history(ui: &mut egui::Ui, args: &mut ReemApp) {
+    ui.add_space(style::SPACING);
+    ui.horizontal(|ui| {
+        ui.heading("History");
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            if ui.button("✖").on_hover_text("Close").clicked() {
+                args.gui_local.history_panel = false;
+            }
+        });
+    });
+    ui.separator();
+    ui.label("Click a change to go back to just after it.");
+    ui.add_space(style::SPACING);
+
+    let position = args.history.position();
+    let mut go_to = None;
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        let steps = std::iter::once("Start").chain(args.history.descriptions());
+        for (idx, description) in steps.enumerate() {
+            // Changes that were undone are shown faded until something else replaces them.
+            let text = if idx > position {
+                egui::RichText::new(description).weak()
+            } else {
+                egui::RichText::new(description)
+            };
+            if ui
+                .add(egui::SelectableLabel::new(idx == position, text))
+                .clicked()
+            {
+                go_to = Some(idx);
+            }
+        }
+    });
+
+    if let Some(position) = go_to {
+        args.history
+            .go_to(position, &mut args.config, &mut args.gui_local.menu);
+    }
+}
@@ -12,12 +12,15 @@ use crate::gui::reemapp::ui_ok_cancel_modal::ui_ok_cancel_modal;
 use crate::gui::reemapp::ui_tables::ui_available_buttons_table;
 use crate::gui::reemapp::ui_tables::ui_rearrange_table;
 use crate::gui::reemapp::{EditLayerModalOpts, NewRemapModalOpts, RemapPolicyUI};
+use crate::lint;
 use smallvec::SmallVec;
 use strum::IntoEnumIterator;
 
 pub fn ui_layer(
     ui: &mut egui::Ui,
     layer: &mut config::Layer,
+    inherited: Option<&config::Layer>,
+    warnings: &[lint::Warning],
     new_remap_modal: &mut NewRemapModalOpts,
     edit_layer_modal: &mut EditLayerModalOpts,
     remaps_search: &mut RemapsSearchOpts,
@@ -29,6 +32,12 @@ pub fn ui_layer(
     egui::Frame::new().shadow(REEMAP_SHADOW).show(ui, |ui| {
         ui.with_layout(egui::Layout::top_down(egui::Align::LEFT), |ui| {
             ui.label(layer.condition_helper_text());
+            if inherited.is_some() {
+                ui.label("Deferred buttons use the parent profile's layer of the same name.");
+            }
+            for warning in warnings {
+                ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}", warning.problem));
+            }
             let edit_response = ui.add_sized(style::BUTTON_SIZE, egui::Button::new("Edit"));
             if edit_response.clicked() {
                 *edit_layer_modal = EditLayerModalOpts {
@@ -40,6 +49,11 @@ pub fn ui_layer(
                 };
             }
             ui.add_space(style::SPACING);
+            ui.checkbox(
+                &mut layer.clip_cursor,
+                "Confine cursor while this layer is active",
+            );
+            ui.add_space(style::SPACING);
             StripBuilder::new(ui)
                 .size(Size::remainder())
                 .size(Size::initial(style::BUTTON_HEIGHT))
@@ -49,6 +63,7 @@ pub fn ui_layer(
                             ui_remaps_table(
                                 ui,
                                 layer,
+                                inherited,
                                 new_remap_modal,
                                 remaps_search,
                                 show_rare_keys,
@@ -114,6 +129,7 @@ pub fn ui_layer(
 fn ui_remaps_table(
     ui: &mut egui::Ui,
     layer: &mut config::Layer,
+    inherited: Option<&config::Layer>,
     new_remap_modal: &mut NewRemapModalOpts,
     remaps_search: &RemapsSearchOpts,
     show_rare_keys: bool,
@@ -122,6 +138,12 @@ fn ui_remaps_table(
     use buttons::key::KeyType;
     use egui_extras::{Column, TableBuilder};
 
+    // What the button actually does once the parent profile's layer, if any, is merged in.
+    let effective_policy = |button: Button| match (&layer.policy[button], inherited) {
+        (config::RemapPolicy::Defer, Some(parent)) => &parent.policy[button],
+        (policy, _) => policy,
+    };
+
     let mut pointing_hand = false;
     let mut button_select = None;
     TableBuilder::new(ui)
@@ -148,7 +170,7 @@ fn ui_remaps_table(
             let key_iter = buttons::key::KeyButton::iter()
                 .filter(|key| {
                     match (
-                        &layer.policy[buttons::Button::from(*key)],
+                        effective_policy(buttons::Button::from(*key)),
                         show_rare_keys,
                         key.key_type(),
                     ) {
@@ -174,7 +196,7 @@ fn ui_remaps_table(
                     let mod_search = remaps_search.search_string.trim().to_lowercase();
                     mod_search.is_empty()
                         || button.to_string().to_lowercase().contains(&mod_search)
-                        || if let config::RemapPolicy::Remap(ref outputs) = layer.policy[*button]
+                        || if let config::RemapPolicy::Remap(outputs) = effective_policy(*button)
                             && outputs.iter().any(|output| {
                                 output.to_string().to_lowercase().contains(&mod_search)
                             })
@@ -186,7 +208,7 @@ fn ui_remaps_table(
                 })
                 .filter(|button| {
                     !remaps_search.hide_unmapped
-                        || !matches!(layer.policy[*button], config::RemapPolicy::Defer)
+                        || !matches!(effective_policy(*button), config::RemapPolicy::Defer)
                 })
             {
                 body.row(style::ROW_HEIGHT, |mut row| {
@@ -203,9 +225,16 @@ fn ui_remaps_table(
                         ui.add(egui::Label::new(button.to_string()).truncate());
                     });
                     row.col(|ui| {
-                        let policy = layer.policy[button].clone();
+                        let parent_policy = inherited.map(|parent| &parent.policy[button]);
+                        let text = match (&layer.policy[button], parent_policy) {
+                            (policy, None | Some(config::RemapPolicy::Defer)) => policy.to_string(),
+                            (config::RemapPolicy::Defer, Some(parent_policy)) => {
+                                format!("{parent_policy} (inherited)")
+                            }
+                            (policy, Some(_)) => format!("{policy} (overrides parent)"),
+                        };
                         ui.style_mut().interaction.selectable_labels = false;
-                        ui.add(egui::Label::new(policy.to_string()).truncate());
+                        ui.add(egui::Label::new(text).truncate());
                     });
                     if row.response().hovered() {
                         pointing_hand = true;
@@ -10,12 +10,13 @@ use crate::gui::reemapp::ui_edit_profile_modal::ui_edit_profile_modal;
 use crate::gui::reemapp::ui_ok_cancel_modal::ui_ok_cancel_modal;
 use crate::gui::reemapp::ui_tables::ui_enable_clickable_table;
 use crate::gui::reemapp::ui_tables::ui_rearrange_table;
-use crate::query_windows;
+use crate::lint;
+use crate::platform::{self, WindowQuery};
 
 use super::GuiMenu;
 use super::ReemApp;
 
-pub fn ui_main(ui: &mut egui::Ui, args: &mut ReemApp) {
+pub fn ui_main(ui: &mut egui::Ui, args: &mut ReemApp, warnings: &[lint::Warning]) {
     use crate::gui::reemapp::style::REEMAP_SHADOW;
     use egui_extras::{Size, StripBuilder};
 
@@ -45,9 +46,21 @@ pub fn ui_main(ui: &mut egui::Ui, args: &mut ReemApp) {
                                                 ui.label("Add a profile to get started.");
                                             });
                                         } else {
+                                            // Flag a profile if anything in it needs attention.
+                                            let profile_warnings: Vec<Vec<String>> =
+                                                (0..args.config.profiles.len())
+                                                    .map(|idx| {
+                                                        warnings
+                                                            .iter()
+                                                            .filter(|w| w.profile_idx == idx)
+                                                            .map(|w| w.to_string())
+                                                            .collect()
+                                                    })
+                                                    .collect();
                                             let profile_select = ui_enable_clickable_table(
                                                 ui,
                                                 &mut args.config.profiles,
+                                                &profile_warnings,
                                                 "Profile",
                                             );
                                             if let Some(profile_idx) = profile_select {
@@ -77,8 +90,13 @@ pub fn ui_main(ui: &mut egui::Ui, args: &mut ReemApp) {
                                             args.gui_local.new_profile_modal =
                                                 EditProfileModalOpts {
                                                     modal_open: true,
-                                                    open_windows:
-                                                        query_windows::enumerate_open_windows(),
+                                                    open_windows: platform::Native.open_windows(),
+                                                    parent_choices: args
+                                                        .config
+                                                        .profiles
+                                                        .iter()
+                                                        .map(|profile| profile.name.clone())
+                                                        .collect(),
                                                     ..Default::default()
                                                 };
                                         }
@@ -8,7 +8,6 @@ use crate::config;
 use crate::gui::reemapp::EditLayerModalOpts;
 use crate::gui::reemapp::EditProfileModalOpts;
 use crate::gui::reemapp::NewBaseRemapModalOpts;
-use crate::gui::reemapp::ProfileConditionUI;
 use crate::gui::reemapp::RearrangeLayersModalOpts;
 use crate::gui::reemapp::RemapsSearchOpts;
 use crate::gui::reemapp::style;
@@ -19,7 +18,7 @@ use crate::gui::reemapp::ui_edit_profile_modal::ui_edit_profile_modal;
 use crate::gui::reemapp::ui_ok_cancel_modal::ui_ok_cancel_modal;
 use crate::gui::reemapp::ui_tables::ui_enable_clickable_table;
 use crate::gui::reemapp::ui_tables::ui_rearrange_table;
-use crate::query_windows;
+use crate::lint;
 
 pub struct UiProfileModals<'a> {
     pub copy_layers_modal: &'a mut bool,
@@ -33,6 +32,9 @@ pub fn ui_profile(
     ui: &mut egui::Ui,
     profile: &mut config::Profile,
     profile_idx: usize,
+    inherited: &Result<Option<config::Profile>, config::InheritanceError>,
+    parent_choices: Vec<String>,
+    warnings: &[lint::Warning],
     menu: &mut GuiMenu,
     remaps_search: &mut RemapsSearchOpts,
     show_rare_keys: bool,
@@ -53,51 +55,54 @@ pub fn ui_profile(
                         .vertical(|mut strip| {
                             strip.cell(|ui| {
                                 ui.label(profile.condition.helper_text());
+                                match inherited {
+                                    Ok(None) => (),
+                                    Ok(Some(parent)) => {
+                                        ui.label(format!("Inherits from {}", parent.name));
+                                        let inherited_layers: Vec<&str> = parent
+                                            .layers
+                                            .iter()
+                                            .filter(|pl| {
+                                                !profile.layers.iter().any(|l| l.name == pl.name)
+                                            })
+                                            .map(|pl| pl.name.as_str())
+                                            .collect();
+                                        if !inherited_layers.is_empty() {
+                                            ui.label(format!(
+                                                "Also uses the parent's layers: {}",
+                                                inherited_layers.join(", ")
+                                            ));
+                                        }
+                                    }
+                                    Err(e) => {
+                                        ui.colored_label(egui::Color32::LIGHT_RED, e.to_string());
+                                    }
+                                }
+                                for warning in
+                                    warnings.iter().filter(|w| w.is_for_profile(profile_idx))
+                                {
+                                    ui.colored_label(
+                                        ui.visuals().warn_fg_color,
+                                        format!("⚠ {}", warning.problem),
+                                    );
+                                }
                                 let edit_response =
                                     ui.add_sized(style::BUTTON_SIZE, egui::Button::new("Edit"));
                                 if edit_response.clicked() {
-                                    *modals.edit_profile_modal = EditProfileModalOpts {
-                                        modal_open: true,
-                                        name: profile.name.clone(),
-                                        condition: match &profile.condition {
-                                            config::ProfileCondition::Always => {
-                                                ProfileConditionUI::Always
-                                            }
-                                            config::ProfileCondition::TitleAndProcess {
-                                                ..
-                                            } => ProfileConditionUI::TitleAndProcess,
-                                            config::ProfileCondition::Title { .. } => {
-                                                ProfileConditionUI::Title
-                                            }
-                                            config::ProfileCondition::Process { .. } => {
-                                                ProfileConditionUI::Process
-                                            }
-                                        },
-                                        title: match &profile.condition {
-                                            config::ProfileCondition::TitleAndProcess {
-                                                title,
-                                                process: _,
-                                            } => title.clone(),
-                                            config::ProfileCondition::Title { title } => {
-                                                title.clone()
-                                            }
-                                            _ => String::new(),
-                                        },
-                                        process: match &profile.condition {
-                                            config::ProfileCondition::TitleAndProcess {
-                                                title: _,
-                                                process,
-                                            } => process.clone(),
-                                            config::ProfileCondition::Process { process } => {
-                                                process.clone()
-                                            }
-                                            _ => String::new(),
-                                        },
-                                        open_windows: query_windows::enumerate_open_windows(),
-                                    };
+                                    *modals.edit_profile_modal = EditProfileModalOpts::from_profile(
+                                        profile,
+                                        parent_choices.clone(),
+                                    );
                                 }
                                 ui.add_space(style::SPACING);
-                                ui.checkbox(&mut profile.clip_cursor, "Confine cursor to window");
+                                ui.horizontal(|ui| {
+                                    ui.checkbox(&mut profile.clip_cursor, "Confine cursor to");
+                                    ui_clip_area(
+                                        ui,
+                                        &mut profile.clip_area,
+                                        &mut profile.clip_inset,
+                                    );
+                                });
                                 ui.add_space(style::SPACING);
 
                                 style::UI_FRAME.show(ui, |ui| {
@@ -107,9 +112,22 @@ pub fn ui_profile(
                                             ui.label("This profile has no layers.");
                                         });
                                     } else {
+                                        let layer_warnings: Vec<Vec<String>> =
+                                            (0..profile.layers.len())
+                                                .map(|layer_idx| {
+                                                    warnings
+                                                        .iter()
+                                                        .filter(|w| {
+                                                            w.is_for_layer(profile_idx, layer_idx)
+                                                        })
+                                                        .map(|w| w.problem.to_string())
+                                                        .collect()
+                                                })
+                                                .collect();
                                         let layer_select = ui_enable_clickable_table(
                                             ui,
                                             &mut profile.layers,
+                                            &layer_warnings,
                                             "Layer",
                                         );
                                         if let Some(i) = layer_select {
@@ -179,6 +197,11 @@ pub fn ui_profile(
                     ui_base_layer(
                         ui,
                         &mut profile.base,
+                        inherited
+                            .as_ref()
+                            .ok()
+                            .and_then(|parent| parent.as_ref())
+                            .map(|parent| &parent.base),
                         modals.new_base_remap_modal,
                         remaps_search,
                         show_rare_keys,
@@ -211,6 +234,7 @@ pub fn ui_profile(
             Some(true) => {
                 profile.name = modals.edit_profile_modal.clone().name;
                 profile.condition = modals.edit_profile_modal.clone().extract_condition();
+                profile.parent = modals.edit_profile_modal.parent.clone();
                 modals.edit_profile_modal.modal_open = false;
             }
             Some(false) => {
@@ -238,6 +262,38 @@ pub fn ui_profile(
     }
 }
 
+fn ui_clip_area(ui: &mut egui::Ui, clip_area: &mut config::ClipArea, clip_inset: &mut i32) {
+    use config::ClipArea;
+
+    egui::ComboBox::from_id_salt("clip_area")
+        .selected_text(clip_area.to_string())
+        .show_ui(ui, |ui| {
+            let options = [
+                ClipArea::Window,
+                ClipArea::ClientArea,
+                ClipArea::Monitor,
+                ClipArea::CenterLock {
+                    size: ClipArea::DEFAULT_CENTER_LOCK_SIZE,
+                },
+            ];
+            for option in options {
+                let selected = std::mem::discriminant(clip_area) == std::mem::discriminant(&option);
+                if ui.selectable_label(selected, option.to_string()).clicked() && !selected {
+                    *clip_area = option;
+                }
+            }
+        });
+    if let ClipArea::CenterLock { size } = clip_area {
+        ui.add(egui::DragValue::new(size).range(2..=4000).suffix(" px"));
+    }
+    ui.add(
+        egui::DragValue::new(clip_inset)
+            .range(0..=1000)
+            .prefix("margin ")
+            .suffix(" px"),
+    );
+}
+
 fn ui_rearrange_layers_modal(
     ui: &mut egui::Ui,
     modal_opts: &mut RearrangeLayersModalOpts,
@@ -0,0 +1,305 @@
+// Copyright 2025 Jordan Johnson
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! UI for button statistics: how often each button was pressed, as a table and a heatmap.
+
+use super::ReemApp;
+use crate::buttons::Button;
+use crate::buttons::key::KeyButton as K;
+use crate::buttons::mouse::MouseButton as M;
+use crate::buttons::wheel::MouseWheelButton as W;
+use crate::gui::reemapp::style;
+use crate::stats;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+// The hook thread is asked for fresh counts this often while the window is open.
+const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+
+// How often to check whether the hook thread has answered.
+const ANSWER_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+// The width of a single key in the heatmap.
+const KEY_SIZE: f32 = 30.0;
+
+#[derive(Debug, Default)]
+pub struct StatsModalOpts {
+    pub modal_open: bool,
+    stats: stats::ButtonStats,
+    last_refresh: Option<Instant>,
+    // Fresh counts the hook thread has yet to send. The window never waits for them.
+    request: Option<oneshot::Receiver<stats::ButtonStats>>,
+    // None shows the whole session.
+    profile: Option<String>,
+}
+
+// -------------------- Heatmap layout --------------------
+// A US keyboard, plus the mouse. Each key is a button, a short label, and a width in keys. None is
+// a gap. Buttons not shown here still appear in the table.
+
+type HeatmapKey = (Option<Button>, &'static str, f32);
+
+const fn key(key: K, label: &'static str, width: f32) -> HeatmapKey {
+    (Some(Button::Key(key)), label, width)
+}
+
+const fn gap(width: f32) -> HeatmapKey {
+    (None, "", width)
+}
+
+#[rustfmt::skip]
+const KEYBOARD: &[&[HeatmapKey]] = &[
+    &[
+        key(K::ESCAPE, "Esc", 1.0), gap(1.0),
+        key(K::F1, "F1", 1.0), key(K::F2, "F2", 1.0), key(K::F3, "F3", 1.0), key(K::F4, "F4", 1.0),
+        gap(0.5),
+        key(K::F5, "F5", 1.0), key(K::F6, "F6", 1.0), key(K::F7, "F7", 1.0), key(K::F8, "F8", 1.0),
+        gap(0.5),
+        key(K::F9, "F9", 1.0), key(K::F10, "F10", 1.0), key(K::F11, "F11", 1.0),
+        key(K::F12, "F12", 1.0),
+    ],
+    &[
+        key(K::OEM_3, "`", 1.0), key(K::KEY1, "1", 1.0), key(K::KEY2, "2", 1.0),
+        key(K::KEY3, "3", 1.0), key(K::KEY4, "4", 1.0), key(K::KEY5, "5", 1.0),
+        key(K::KEY6, "6", 1.0), key(K::KEY7, "7", 1.0), key(K::KEY8, "8", 1.0),
+        key(K::KEY9, "9", 1.0), key(K::KEY0, "0", 1.0), key(K::OEM_MINUS, "-", 1.0),
+        key(K::OEM_PLUS, "=", 1.0), key(K::BACK, "Bksp", 2.0),
+    ],
+    &[
+        key(K::TAB, "Tab", 1.5), key(K::Q, "Q", 1.0), key(K::W, "W", 1.0), key(K::E, "E", 1.0),
+        key(K::R, "R", 1.0), key(K::T, "T", 1.0), key(K::Y, "Y", 1.0), key(K::U, "U", 1.0),
+        key(K::I, "I", 1.0), key(K::O, "O", 1.0), key(K::P, "P", 1.0), key(K::OEM_4, "[", 1.0),
+        key(K::OEM_6, "]", 1.0), key(K::OEM_5, "\\", 1.5),
+    ],
+    &[
+        key(K::CAPITAL, "Caps", 1.75), key(K::A, "A", 1.0), key(K::S, "S", 1.0),
+        key(K::D, "D", 1.0), key(K::F, "F", 1.0), key(K::G, "G", 1.0), key(K::H, "H", 1.0),
+        key(K::J, "J", 1.0), key(K::K, "K", 1.0), key(K::L, "L", 1.0), key(K::OEM_1, ";", 1.0),
+        key(K::OEM_7, "'", 1.0), key(K::RETURN, "Enter", 2.25),
+    ],
+    &[
+        key(K::LSHIFT, "Shift", 2.25), key(K::Z, "Z", 1.0), key(K::X, "X", 1.0),
+        key(K::C, "C", 1.0), key(K::V, "V", 1.0), key(K::B, "B", 1.0), key(K::N, "N", 1.0),
+        key(K::M, "M", 1.0), key(K::OEM_COMMA, ",", 1.0), key(K::OEM_PERIOD, ".", 1.0),
+        key(K::OEM_2, "/", 1.0), key(K::RSHIFT, "Shift", 2.75),
+    ],
+    &[
+        key(K::LCONTROL, "Ctrl", 1.25), key(K::LWIN, "Win", 1.25), key(K::LMENU, "Alt", 1.25),
+        key(K::SPACE, "Space", 6.25), key(K::RMENU, "Alt", 1.25), key(K::RWIN, "Win", 1.25),
+        key(K::APPS, "Menu", 1.25), key(K::RCONTROL, "Ctrl", 1.25),
+    ],
+];
+
+#[rustfmt::skip]
+const MOUSE: &[HeatmapKey] = &[
+    (Some(Button::Mouse(M::Left)), "Left", 2.0),
+    (Some(Button::Mouse(M::Middle)), "Middle", 2.0),
+    (Some(Button::Mouse(M::Right)), "Right", 2.0),
+    (Some(Button::Mouse(M::X1)), "X1", 1.0),
+    (Some(Button::Mouse(M::X2)), "X2", 1.0),
+    gap(0.5),
+    (Some(Button::Wheel(W::Up)), "Wheel ↑", 2.0),
+    (Some(Button::Wheel(W::Down)), "Wheel ↓", 2.0),
+    (Some(Button::Wheel(W::HorzLeft)), "Wheel ←", 2.0),
+    (Some(Button::Wheel(W::HorzRight)), "Wheel →", 2.0),
+];
+
+// -------------------- Modal --------------------
+
+pub fn ui_stats_modal(ui: &mut egui::Ui, args: &mut ReemApp) {
+    let modal_opts = &mut args.gui_local.stats_modal;
+
+    // Keep the counts fresh while the window is open. A busy hook thread answers late, or not at
+    // all, and the window carries on showing the last counts in the meantime.
+    if let Some(request) = &modal_opts.request {
+        match request.try_recv() {
+            Ok(stats) => {
+                modal_opts.stats = stats;
+                modal_opts.request = None;
+            }
+            Err(oneshot::TryRecvError::Empty) => (),
+            Err(oneshot::TryRecvError::Disconnected) => modal_opts.request = None,
+        }
+    }
+    let stale = modal_opts
+        .last_refresh
+        .is_none_or(|last_refresh| last_refresh.elapsed() >= REFRESH_INTERVAL);
+    if stale {
+        // Any request still unanswered is given up on.
+        modal_opts.request = args.hookthread_proxy.request_stats();
+        modal_opts.last_refresh = Some(Instant::now());
+    }
+    if modal_opts.request.is_some() {
+        ui.ctx().request_repaint_after(ANSWER_POLL_INTERVAL);
+    } else {
+        ui.ctx().request_repaint_after(REFRESH_INTERVAL);
+    }
+
+    // The profile shown may not be counted anymore.
+    if let Some(profile) = &modal_opts.profile
+        && !modal_opts.stats.profiles.contains_key(profile)
+    {
+        modal_opts.profile = None;
+    }
+
+    let modal = egui::Modal::new(egui::Id::new("stats modal"))
+        .backdrop_color(style::MODAL_BACKDROP_COLOR)
+        .frame(style::MODAL_FRAME)
+        .show(ui.ctx(), |ui| {
+            style::set_reemap_style(ui);
+
+            ui.heading("Button Statistics");
+            ui.add_space(style::SPACING);
+            ui.label(
+                "How often each button was pressed since Reemap started. Buttons you never press \
+are good candidates for new remaps.",
+            );
+            ui.add_space(style::SPACING);
+
+            ui.horizontal(|ui| {
+                let selected_text = match &modal_opts.profile {
+                    None => "Every profile",
+                    Some(name) => name.as_str(),
+                };
+                egui::ComboBox::from_id_salt("stats_profile_combo")
+                    .selected_text(selected_text)
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut modal_opts.profile, None, "Every profile");
+                        for name in modal_opts.stats.profiles.keys() {
+                            ui.selectable_value(
+                                &mut modal_opts.profile,
+                                Some(name.clone()),
+                                name.as_str(),
+                            );
+                        }
+                    });
+                if ui.button("Export CSV").clicked() {
+                    export_csv_dialog(&modal_opts.stats);
+                }
+                if ui.button("Reset").clicked() {
+                    args.hookthread_proxy.reset_stats();
+                    modal_opts.stats = Default::default();
+                    // It would bring back the counts from before the reset.
+                    modal_opts.request = None;
+                    modal_opts.profile = None;
+                }
+            });
+            ui.add_space(style::SPACING);
+
+            let empty = stats::Counts::default();
+            let counts = modal_opts
+                .stats
+                .counts(modal_opts.profile.as_deref())
+                .unwrap_or(&empty);
+
+            style::UI_FRAME.show(ui, |ui| {
+                ui_heatmap(ui, counts);
+            });
+            ui.add_space(style::SPACING);
+            style::UI_FRAME.show(ui, |ui| {
+                ui_table(ui, counts);
+            });
+        });
+    if modal.should_close() {
+        args.gui_local.stats_modal.modal_open = false;
+    }
+}
+
+fn ui_heatmap(ui: &mut egui::Ui, counts: &stats::Counts) {
+    let max = counts.values().copied().max().unwrap_or(0);
+    ui.spacing_mut().item_spacing = egui::vec2(2.0, 2.0);
+    for row in KEYBOARD {
+        ui_heatmap_row(ui, row, counts, max);
+    }
+    ui.add_space(style::SPACING);
+    ui_heatmap_row(ui, MOUSE, counts, max);
+}
+
+fn ui_heatmap_row(ui: &mut egui::Ui, row: &[HeatmapKey], counts: &stats::Counts, max: u64) {
+    ui.horizontal(|ui| {
+        for &(button, label, width) in row {
+            let spacing = ui.spacing().item_spacing.x;
+            let size = egui::vec2(width * KEY_SIZE + (width - 1.0) * spacing, KEY_SIZE);
+            let (rect, response) = ui.allocate_exact_size(size, egui::Sense::hover());
+            let Some(button) = button else {
+                continue;
+            };
+            let count = counts[button];
+            let fill = heat_color(count, max);
+            ui.painter().rect_filled(rect, 3.0, fill);
+            ui.painter().text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                label,
+                egui::FontId::proportional(11.0),
+                egui::Color32::WHITE,
+            );
+            response.on_hover_text(format!("{button}: {count}"));
+        }
+    });
+}
+
+// Dark for buttons never pressed, through to bright orange for the most pressed.
+fn heat_color(count: u64, max: u64) -> egui::Color32 {
+    let cold = egui::Color32::from_gray(40);
+    let hot = egui::Color32::from_rgb(255, 120, 0);
+    if max == 0 || count == 0 {
+        return cold;
+    }
+    let t = count as f32 / max as f32;
+    egui::Color32::from_rgb(
+        egui::lerp(cold.r() as f32..=hot.r() as f32, t) as u8,
+        egui::lerp(cold.g() as f32..=hot.g() as f32, t) as u8,
+        egui::lerp(cold.b() as f32..=hot.b() as f32, t) as u8,
+    )
+}
+
+fn ui_table(ui: &mut egui::Ui, counts: &stats::Counts) {
+    let sorted = stats::sorted(counts);
+    if sorted.is_empty() {
+        ui.label("(no presses yet)");
+        return;
+    }
+    egui::ScrollArea::vertical()
+        .max_height(160.0)
+        .show(ui, |ui| {
+            egui::Grid::new("stats table")
+                .num_columns(2)
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.strong("Button");
+                    ui.strong("Presses");
+                    ui.end_row();
+                    for (button, count) in sorted {
+                        ui.label(button.to_string());
+                        ui.label(count.to_string());
+                        ui.end_row();
+                    }
+                });
+        });
+}
+
+fn export_csv_dialog(stats: &stats::ButtonStats) {
+    let selection = native_dialog::DialogBuilder::file()
+        .add_filter("CSV", ["csv"])
+        .set_filename("button statistics.csv")
+        .save_single_file()
+        .show();
+    match selection {
+        Ok(None) => (),
+        Ok(Some(path)) => match std::fs::write(&path, stats.to_csv()) {
+            Ok(()) => info!("exported button statistics"),
+            Err(e) => {
+                native_dialog::DialogBuilder::message()
+                    .set_level(native_dialog::MessageLevel::Warning)
+                    .set_title("Error exporting statistics")
+                    .set_text(format!("Reemap could not export the statistics.\n\n{e}"))
+                    .alert()
+                    .show()
+                    .unwrap();
+                warn!(?e, "failed to export button statistics");
+            }
+        },
+        Err(e) => warn!(?e, "error opening export dialog"),
+    }
+}
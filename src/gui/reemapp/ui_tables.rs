@@ -67,10 +67,16 @@ impl EnableListItem for config::Layer {
 }
 
 /// Display a table that allows the user to enable items in the list and to click them.
+/// `warnings[i]` holds any warnings to flag next to item `i`.
 /// Important: if called multiple times within the same `Ui`, each call must have a different
 /// `name`, or runtime errors will occur.
 /// Returns the index of the item the user clicked.
-pub fn ui_enable_clickable_table<T>(ui: &mut egui::Ui, list: &mut [T], name: &str) -> Option<usize>
+pub fn ui_enable_clickable_table<T>(
+    ui: &mut egui::Ui,
+    list: &mut [T],
+    warnings: &[Vec<String>],
+    name: &str,
+) -> Option<usize>
 where
     T: EnableListItem,
 {
@@ -107,6 +113,12 @@ where
                     });
                     row.col(|ui| {
                         ui.style_mut().interaction.selectable_labels = false;
+                        if let Some(item_warnings) = warnings.get(i)
+                            && !item_warnings.is_empty()
+                        {
+                            ui.colored_label(ui.visuals().warn_fg_color, "⚠")
+                                .on_hover_text(item_warnings.join("\n"));
+                        }
                         ui.add(egui::Label::new(item.to_string()).truncate());
                     });
                     if row.response().hovered() {
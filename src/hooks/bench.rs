@@ -0,0 +1,244 @@
+// Copyright 2025 Jordan Johnson
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Measures how long the hook procedures take to decide what to do with an input.
+//!
+//! Synthetic streams of presses and releases are run through the same code the hooks use, against
+//! a configuration built for the purpose, and the time for each input is recorded. Nothing is sent
+//! to the operating system, so it is safe to run while using the computer. Run it with `reemap bench`
+//! in a build with the "bench" feature.
+
+use super::engine::{self, InputBuffer};
+use super::hooklocal::HookLocalData;
+use crate::buttons::key::KeyButton as K;
+use crate::buttons::mouse::MouseButton as M;
+use crate::buttons::wheel::MouseWheelButton as W;
+use crate::buttons::{Button, InputEvent};
+use crate::config::{self, BaseRemapPolicy, LayerType, RemapPolicy};
+use smallvec::smallvec;
+use std::cell::RefCell;
+use std::time::{Duration, Instant};
+
+use InputEvent::{Down, Tap, Up};
+
+// A press and release of each button in turn.
+fn press_each(keys: &[K]) -> Vec<InputEvent> {
+    keys.iter()
+        .flat_map(|&key| [Down(key.into()), Up(key.into())])
+        .collect()
+}
+
+// Each stream is named for the work it makes the hooks do.
+fn streams() -> Vec<(&'static str, Vec<InputEvent>)> {
+    let mut key_repeat = vec![Down(K::S.into()); 30];
+    key_repeat.push(Up(K::S.into()));
+
+    let mut layer = vec![Down(K::CAPITAL.into())];
+    layer.extend(press_each(&[K::H, K::J, K::K, K::L]));
+    layer.push(Up(K::CAPITAL.into()));
+
+    vec![
+        ("not remapped", press_each(&[K::Q, K::W, K::E, K::R])),
+        ("remapped", press_each(&[K::A, K::S, K::D, K::F])),
+        ("key repeat", key_repeat),
+        ("layer", layer),
+        (
+            "wheel",
+            vec![
+                Tap(W::Up.into()),
+                Tap(W::Down.into()),
+                Tap(W::HorzLeft.into()),
+            ],
+        ),
+    ]
+}
+
+// A profile that is always active, with a bit of everything the streams touch.
+fn bench_config() -> config::Config {
+    let mut base = config::BaseLayer::default();
+    base.policy[Button::from(K::A)] = BaseRemapPolicy::Remap(smallvec![M::Left.into()]);
+    base.policy[Button::from(K::S)] =
+        BaseRemapPolicy::Remap(smallvec![K::LCONTROL.into(), K::C.into()]);
+    base.policy[Button::from(K::D)] = BaseRemapPolicy::Suppress;
+    base.policy[Button::from(K::F)] = BaseRemapPolicy::Remap(smallvec![
+        K::LSHIFT.into(),
+        K::LCONTROL.into(),
+        K::LMENU.into(),
+        K::LWIN.into(),
+        K::F.into(),
+        M::Right.into(),
+        W::Down.into(),
+        K::SPACE.into(),
+    ]);
+    base.policy[Button::from(W::Up)] = BaseRemapPolicy::Remap(smallvec![K::PRIOR.into()]);
+    base.policy[Button::from(W::Down)] = BaseRemapPolicy::Suppress;
+
+    let mut arrows = config::Layer {
+        name: String::from("Arrows"),
+        layer_type: LayerType::Modifier,
+        condition: vec![K::CAPITAL.into()],
+        ..Default::default()
+    };
+    for (from, to) in [
+        (K::H, K::LEFT),
+        (K::J, K::DOWN),
+        (K::K, K::UP),
+        (K::L, K::RIGHT),
+    ] {
+        arrows.policy[Button::from(from)] = RemapPolicy::Remap(smallvec![to.into()]);
+    }
+
+    config::Config {
+        profiles: vec![config::Profile {
+            name: String::from("Benchmark"),
+            condition: config::ProfileCondition::Always,
+            base,
+            layers: vec![arrows],
+            ..Default::default()
+        }],
+        ..Default::default()
+    }
+}
+
+// -------------------- Running --------------------
+
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub stream: &'static str,
+    pub inputs: usize,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub p999: Duration,
+    pub max: Duration,
+}
+
+/// Run every stream `rounds` times and report how long each input took.
+pub fn run(rounds: usize) -> Vec<Report> {
+    // The hooks borrow their data for every input, so the benchmark does too.
+    let hook_local = RefCell::new(HookLocalData::detached(bench_config()));
+    streams()
+        .into_iter()
+        .map(|(name, stream)| {
+            // Warm up first, so one-time costs like the first press counted towards a profile's
+            // statistics are left out.
+            run_stream(&hook_local, &stream, |_| ());
+            let mut times = Vec::with_capacity(stream.len() * rounds);
+            for _ in 0..rounds {
+                run_stream(&hook_local, &stream, |time| times.push(time));
+            }
+            report(name, times)
+        })
+        .collect()
+}
+
+// Time each input in the stream the way the hook procedures handle it.
+fn run_stream(
+    hook_local: &RefCell<HookLocalData>,
+    stream: &[InputEvent],
+    mut record: impl FnMut(Duration),
+) {
+    for &input in stream {
+        let start = Instant::now();
+        let mut inputs = InputBuffer::new();
+        let mut hook_local = hook_local.borrow_mut();
+        let intercepted = match input {
+            Down(button) => engine::hold_down(&mut hook_local, button, &mut inputs),
+            Up(button) => engine::hold_up(&mut hook_local, button, &mut inputs),
+            Tap(button) => engine::tap(&mut hook_local, button, &mut inputs),
+        };
+        std::mem::drop(hook_local);
+        let time = start.elapsed();
+        std::hint::black_box((intercepted, inputs));
+        record(time);
+    }
+}
+
+fn report(stream: &'static str, mut times: Vec<Duration>) -> Report {
+    times.sort_unstable();
+    let percentile = |p: f64| {
+        let idx = ((times.len() as f64 * p).ceil() as usize).clamp(1, times.len()) - 1;
+        times[idx]
+    };
+    Report {
+        stream,
+        inputs: times.len(),
+        p50: percentile(0.50),
+        p90: percentile(0.90),
+        p99: percentile(0.99),
+        p999: percentile(0.999),
+        max: times.last().copied().unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    // Counts allocations made on the current thread, so tests running alongside do not interfere.
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    fn count_allocation() {
+        let _ = ALLOCATIONS.try_with(|allocations| allocations.set(allocations.get() + 1));
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            count_allocation();
+            unsafe { System.alloc(layout) }
+        }
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe { System.dealloc(ptr, layout) }
+        }
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            count_allocation();
+            unsafe { System.realloc(ptr, layout, new_size) }
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    #[test]
+    fn hooks_do_not_allocate() {
+        let hook_local = RefCell::new(HookLocalData::detached(bench_config()));
+        for (name, stream) in streams() {
+            run_stream(&hook_local, &stream, |_| ());
+            let before = ALLOCATIONS.with(Cell::get);
+            run_stream(&hook_local, &stream, |_| ());
+            let allocations = ALLOCATIONS.with(Cell::get) - before;
+            assert_eq!(allocations, 0, "the {name} stream allocated");
+        }
+    }
+
+    #[test]
+    fn remaps_are_sent_in_order() {
+        let mut hook_local = HookLocalData::detached(bench_config());
+        let mut inputs = InputBuffer::new();
+        assert!(engine::hold_down(&mut hook_local, K::F.into(), &mut inputs));
+        assert_eq!(inputs.len(), 8);
+        assert!(!inputs.spilled());
+        assert_eq!(inputs[0], Down(K::LSHIFT.into()));
+        assert_eq!(inputs[6], Tap(W::Down.into()));
+
+        inputs.clear();
+        assert!(engine::hold_up(&mut hook_local, K::F.into(), &mut inputs));
+        // The wheel is only sent on the press.
+        assert_eq!(inputs.len(), 7);
+        assert_eq!(inputs[6], Up(K::SPACE.into()));
+
+        inputs.clear();
+        assert!(!engine::hold_down(
+            &mut hook_local,
+            K::Q.into(),
+            &mut inputs
+        ));
+        assert!(inputs.is_empty());
+    }
+}
@@ -0,0 +1,707 @@
+// Copyright 2025 Jordan Johnson
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! The remap engine: what to do with each press, release and tap.
+//!
+//! It knows nothing about where the inputs come from or where its answers go. The hooks on Windows
+//! feed it what they see and send what it collects; the benchmarks and tests feed it made-up
+//! inputs and look at what it collects.
+
+use crate::buttons::key::KeyButton;
+use crate::buttons::{Button, HoldButton, InputEvent, TapButton};
+use crate::config::{Layer, LayerType, Profile, REMAP_SMALLVEC_LEN};
+use crate::events::Event;
+use crate::hooks::UiEvent;
+use crate::hooks::hooklocal::{HoldButtonState, HookLocalData};
+use crate::hooks::runtime_config::RuntimeConfig;
+use crate::tables::Resolution;
+use smallvec::SmallVec;
+
+// Room for every input a remap of REMAP_SMALLVEC_LEN buttons can send: a down and an up for each
+// button when tapped. The engine builds its inputs here so it never allocates. Longer remaps still
+// work, but spill onto the heap (`reemap config check` points them out).
+pub const INPUT_BUFFER_LEN: usize = 2 * REMAP_SMALLVEC_LEN;
+pub type InputBuffer = SmallVec<[InputEvent; INPUT_BUFFER_LEN]>;
+
+/*
+
+A note on profiles: more than one profile can be active at once. The hook thread keeps a stack of
+active profiles (see HookLocalData::update_from_foreground). Everything below that talks about
+"layers" applies to the layers of every profile in the stack. When choosing a remap, the profiles
+are checked from the top of the stack down; each profile's layers are checked, then its base layer.
+A base layer whose policy for a button is "Inherit" lets the next profile in the stack decide (see
+resolve_policy). Any other base policy, "NoRemap" included, is final.
+
+On button down:
+
+    1.  Check button_state. Key repeat means it is likely we receive many DOWN inputs before an UP
+        input. We want to map subsequent DOWN inputs the same as we mapped the first DOWN input.
+        Additionally, if this DOWN input is due to key repeat, we do not want to toggle any toggle
+        layers.
+
+        i.  If the input state is "HeldNoRemap", forward the input unmodified and quit.
+
+        ii. If the input state is "HeldWithRemap", send the listed Hold inputs as DOWN and quit.
+            Do not send any Tap inputs because we've already sent the one for this button press.
+
+        iii.If the input state is "NotHeld", we have a new button press. Let's process it.
+
+    2.  Update which layers are enabled.
+
+        For each layer (exclduing the base layer, which is always enabled):
+
+        a.  If this input is in the layer's condition list, and all other buttons in this list
+            are already held:
+
+            i.  If this is a modifier layer, make it enabled.
+
+            ii. If this is a toggle layer, toggle whether it is enabled.
+
+    3.  Layers are now up-to-date with this latest button press. Now, dispatch inputs and mark
+        button_state.
+
+        For each enabled layer, starting from the highest priority, check the layer's policy
+        for this button.
+
+        a.  If this layer's policy is "Defer", check the next layer. If the base layer's policy is
+            "Inherit", check the next profile in the stack.
+
+        b.  If this layer's policy is "NoRemap", immediately forward the input unmodified. Mark the
+            input in button_state as "HeldNoRemap". Do the same if no profile handles the input,
+            that is, if every profile in the stack inherits it.
+
+        c.  If this layer's policy is "Remap", immediately send the specified inputs.
+            Send Hold inputs as a DOWN input.
+            Send Tap inputs.
+            Mark the input in button_state as "HeldWithRemap".
+
+On button up:
+
+    1.  Every button up is a fresh, new button up. Update which layers are enabled.
+
+        For each layer (excluding the base layer, which is always enabled):
+
+        a.  If this input is in the layer's condition list:
+
+            i.  If this is a modifier layer, make it disabled.
+
+            ii. If this is a toggle layer, do nothing. Toggle layers only change on a depress.
+
+
+    2.  Layers are now up-to-date with this latest button press. Now check and update button_state:
+
+        a.  If the input is "HeldNoRemap", immediately forward the input unmodified.
+
+        b.  If the input is "HeldWithRemap", immediately send the specified inputs as UP inputs.
+
+        c.  If the input is "NotHeld", well, this shouldn't have happened. We got a KEYUP or
+            MOUSEUP without remembering seeing a corresponding KEYDOWN or MOUSEDOWN. Ah well - let's
+            just forward the input unmodified.
+
+    (Notice we do not check the mappings in the layers on a button up.)
+
+    3.  Mark the button_state as NotHeld.
+
+On tap:
+
+    Layers cannot be conditional on tap inputs. Therefore, we do not need to adjust any "enabled"
+    values for the layers.
+
+    For each layer, check the layer's policy for this button.
+
+    1.  If this layer's policy is "Defer", check the next layer. If the base layer's policy is
+        "Inherit", check the next profile in the stack.
+
+    2.  If this layer's policy is "NoRemap", immediately forward the input unmodified.
+
+    3.  If this layer's policy is "Remap", immediately send the specified inputs.
+        Every Hold input in the policy should be sent together as a DOWN/UP pair.
+        Every Tap input should be sent.
+
+*/
+
+// Decide what to do with a button press, and collect the inputs to send in its place.
+// This is separate from sending them so the benchmarks can run it too.
+pub fn hold_down(
+    hook_local: &mut HookLocalData,
+    hold_button: HoldButton,
+    inputs: &mut InputBuffer,
+) -> bool {
+    // Inform the UI of this button press if it wants to know.
+    if hook_local.ui_observing_inputs {
+        hook_local.send_to_ui(UiEvent::ButtonPressed(hold_button.into()));
+    }
+
+    // Step 1
+    // An early return to handle key repeat - the case when you get multiple keydowns before a keyup
+    match &hook_local.button_state[hold_button] {
+        // Already held (key repeat), and there's no remap. So do not intercept it.
+        HoldButtonState::HeldNoRemap => {
+            return false;
+        }
+
+        // Already held (key repeat), and there is a remap.
+        // Let's intercept it and repeat any keyboard keys this remap targets.
+        HoldButtonState::HeldWithRemap(targets) => {
+            inputs.extend(targets.iter().filter_map(|btn| match btn {
+                Button::Key(key) => Some(InputEvent::Down((*key).into())),
+                _ => None,
+            }));
+            return true;
+        }
+
+        // ALready held (key repeat), and the input is suppressed.
+        // Exact same as HeldWithRemap, except just don't send any input. So, intercept it.
+        HoldButtonState::HeldSuppress => return true,
+
+        // Not held - this is a fresh input. Let's continue processing.
+        HoldButtonState::NotHeld => {}
+    }
+
+    // Key repeats were handled above, so statistics and subscribers only see the first press.
+    hook_local.record_press(hold_button.into());
+    if hook_local.events.wants_inputs() {
+        hook_local.events.publish(Event::ButtonPressed {
+            button: hold_button.into(),
+        });
+    }
+
+    // Check that a profile is actually active and remaps are not paused. Otherwise, do not
+    // intercept. Buttons pressed before a pause still come through here and are released as they
+    // were remapped, so nothing gets stuck.
+    // The button is still marked held, so it shows up for input subscribers.
+    if hook_local.active_profiles.is_empty() || hook_local.paused {
+        hook_local.button_state[hold_button] = HoldButtonState::HeldNoRemap;
+        hook_local.publish_held_buttons();
+        return false;
+    }
+
+    // Step 2
+    // Update layers
+    // Only buttons that are a condition of some layer can change layers.
+    let mut layers_changed = false;
+    if hook_local.config.layer_conditions[hold_button] {
+        for &profile_idx in hook_local.active_profiles.iter() {
+            let profile: &Profile = &hook_local.config.profiles[profile_idx];
+            let current_layer_actives: &mut [bool] =
+                &mut hook_local.active_layers_profile[profile_idx];
+            for (layer, active) in profile
+                .layers
+                .iter()
+                .zip(current_layer_actives.iter_mut())
+                .filter(|(layer, _)| layer.enabled)
+            {
+                // Only update layers for which this button is a condition.
+                if layer.condition.contains(&hold_button) {
+                    // All conditions met?
+                    if layer
+                        .condition
+                        .iter()
+                        .filter(|&condition| *condition != hold_button)
+                        .all(|condition| {
+                            hook_local.button_state[*condition] != HoldButtonState::NotHeld
+                        })
+                    {
+                        // All conditions met. Let's enable/toggle this layer.
+                        let was_active = *active;
+                        match &layer.layer_type {
+                            LayerType::Modifier => *active = true,
+                            LayerType::Toggle => *active = !*active,
+                        }
+                        if *active != was_active {
+                            layers_changed = true;
+                            if hook_local.events.wants_events() {
+                                hook_local
+                                    .events
+                                    .publish(layer_event(profile, layer, *active));
+                            }
+                        }
+                    }
+                }
+            }
+            hook_local.active_layer_sets[profile_idx] =
+                hook_local.config.tables[profile_idx].layer_set(current_layer_actives);
+        }
+    }
+    // A layer may want the cursor confined while it is active. Scroll Lock may have just turned
+    // the escape hatch off, and the clip needs to come back.
+    if layers_changed || hold_button == HoldButton::Key(KeyButton::SCROLL) {
+        hook_local.update_clip();
+    }
+
+    // Step 3
+    // Identify the appropriate remap and apply it. At the same time, set button_state.
+    let intercepted = match resolve_policy(
+        &hook_local.config,
+        &hook_local.active_profiles,
+        &hook_local.active_layers_profile,
+        &hook_local.active_layer_sets,
+        Button::from(hold_button),
+    ) {
+        Resolution::Remap(output) => {
+            inputs.extend(output.iter().copied().map(InputEvent::press));
+            hook_local.button_state[hold_button] = HoldButtonState::HeldWithRemap(output.clone());
+            true
+        }
+        Resolution::Suppress => {
+            hook_local.button_state[hold_button] = HoldButtonState::HeldSuppress;
+            true
+        }
+        Resolution::NoRemap => {
+            hook_local.button_state[hold_button] = HoldButtonState::HeldNoRemap;
+            false
+        }
+    };
+    hook_local.publish_held_buttons();
+
+    intercepted
+}
+
+// Decide what to do with a button release, and collect the inputs to send in its place.
+pub fn hold_up(
+    hook_local: &mut HookLocalData,
+    hold_button: HoldButton,
+    inputs: &mut InputBuffer,
+) -> bool {
+    if hook_local.events.wants_inputs() {
+        hook_local.events.publish(Event::ButtonReleased {
+            button: hold_button.into(),
+        });
+    }
+
+    // Step 1
+    // Update layers
+    let mut layers_changed = false;
+    if hook_local.config.layer_conditions[hold_button] {
+        for &profile_idx in hook_local.active_profiles.iter() {
+            let profile: &Profile = &hook_local.config.profiles[profile_idx];
+            let current_layer_actives: &mut [bool] =
+                &mut hook_local.active_layers_profile[profile_idx];
+            for (layer, active) in profile
+                .layers
+                .iter()
+                .zip(current_layer_actives.iter_mut())
+                .filter(|(layer, _)| layer.enabled)
+            {
+                // Only update layers for which this button is a condition.
+                // These layers are no longer active.
+                if layer.condition.contains(&hold_button) {
+                    match &layer.layer_type {
+                        LayerType::Modifier if *active => {
+                            *active = false;
+                            layers_changed = true;
+                            if hook_local.events.wants_events() {
+                                hook_local
+                                    .events
+                                    .publish(layer_event(profile, layer, false));
+                            }
+                        }
+                        LayerType::Modifier => (),
+                        LayerType::Toggle => (), // Toggle buttons not affected by keyup
+                    }
+                }
+            }
+            hook_local.active_layer_sets[profile_idx] =
+                hook_local.config.tables[profile_idx].layer_set(current_layer_actives);
+        }
+    }
+    if layers_changed || hold_button == HoldButton::Key(KeyButton::SCROLL) {
+        hook_local.update_clip();
+    }
+
+    // Step 2
+    // See what this button was mapped to.
+    // Note we never consult the profile. The original decision of what a button maps to is only
+    // made when the button is first pressed.
+    let intercepted = match &hook_local.button_state[hold_button] {
+        // This button down was not intercepted, so let's not intercept the button up.
+        HoldButtonState::HeldNoRemap | HoldButtonState::NotHeld => false,
+
+        // This button down was intercepted, so let's intercept the button up the same way.
+        HoldButtonState::HeldWithRemap(targets) => {
+            // Wheel input is only sent on the press.
+            inputs.extend(targets.iter().copied().filter_map(InputEvent::release));
+            true
+        }
+
+        // This button down was intercepted and suppressed, so let's suppress the button up, too.
+        HoldButtonState::HeldSuppress => true,
+    };
+    // Step 3
+    hook_local.button_state[hold_button] = HoldButtonState::NotHeld;
+    hook_local.publish_held_buttons();
+
+    intercepted
+}
+
+// Release every button that is held, as if it had been let go, and collect the inputs to send.
+// Used when releases may have been missed, so nothing Reemap pressed stays down.
+pub fn release_all(hook_local: &mut HookLocalData, inputs: &mut InputBuffer) {
+    let held: SmallVec<[HoldButton; REMAP_SMALLVEC_LEN]> = hook_local
+        .button_state
+        .iter()
+        .filter(|(_, state)| **state != HoldButtonState::NotHeld)
+        .map(|(button, _)| button)
+        .collect();
+    for hold_button in held {
+        hold_up(hook_local, hold_button, inputs);
+    }
+}
+
+// Decide what to do with a tap, and collect the inputs to send in its place.
+pub fn tap(
+    hook_local: &mut HookLocalData,
+    tap_button: TapButton,
+    inputs: &mut InputBuffer,
+) -> bool {
+    // Layers are not allowed to depend on tap inputs.
+    // Additionally, we do not try to remember which tap inputs are "held", because it is
+    // meaningless to "hold" a scroll wheel button.
+    // This makes the job much easier.
+
+    // Inform the UI of this button press if it wants to know.
+    if hook_local.ui_observing_inputs {
+        hook_local.send_to_ui(UiEvent::ButtonPressed(tap_button.into()));
+    }
+    hook_local.record_press(tap_button.into());
+    if hook_local.events.wants_inputs() {
+        hook_local.events.publish(Event::ButtonPressed {
+            button: tap_button.into(),
+        });
+    }
+
+    if hook_local.paused {
+        return false;
+    }
+
+    match resolve_policy(
+        &hook_local.config,
+        &hook_local.active_profiles,
+        &hook_local.active_layers_profile,
+        &hook_local.active_layer_sets,
+        Button::from(tap_button),
+    ) {
+        Resolution::Remap(output) => {
+            for &button in output.iter() {
+                inputs.push(InputEvent::press(button));
+                inputs.extend(InputEvent::release(button));
+            }
+            true
+        }
+        Resolution::Suppress => true,
+        Resolution::NoRemap => false,
+    }
+}
+
+// For subscribers outside Reemap.
+fn layer_event(profile: &Profile, layer: &Layer, active: bool) -> Event {
+    let profile = profile.name.clone();
+    let layer = layer.name.clone();
+    if active {
+        Event::LayerActivated { profile, layer }
+    } else {
+        Event::LayerDeactivated { profile, layer }
+    }
+}
+
+/*
+
+    Resolve Policy
+
+*/
+
+// Walk the profile stack from the top. Each profile looks the button up in its tables (see
+// tables.rs), which give what its active layers or its base layer say. A base layer that inherits
+// the button passes the decision down to the next profile.
+fn resolve_policy<'a>(
+    config: &'a RuntimeConfig,
+    active_profiles: &[usize],
+    active_layers_profile: &[SmallVec<[bool; REMAP_SMALLVEC_LEN]>],
+    active_layer_sets: &[usize],
+    button: Button,
+) -> Resolution<'a> {
+    for &profile_idx in active_profiles {
+        let resolution = config.tables[profile_idx].resolve(
+            &config.profiles[profile_idx],
+            &active_layers_profile[profile_idx],
+            active_layer_sets[profile_idx],
+            button,
+        );
+        if let Some(resolution) = resolution {
+            return resolution;
+        }
+    }
+    Resolution::NoRemap
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buttons::key::KeyButton as K;
+    use crate::buttons::mouse::MouseButton as M;
+    use crate::buttons::wheel::MouseWheelButton as W;
+    use crate::config::{self, BaseRemapPolicy, ProfileCondition};
+    use crate::platform::WindowInfo;
+    use smallvec::smallvec;
+
+    use InputEvent::{Down, Up};
+
+    fn profile(name: &str, condition: ProfileCondition) -> config::Profile {
+        config::Profile {
+            name: name.to_string(),
+            condition,
+            ..Default::default()
+        }
+    }
+
+    fn remap(to: K) -> BaseRemapPolicy {
+        BaseRemapPolicy::Remap(smallvec![to.into()])
+    }
+
+    // Press and release a key. Whether each was intercepted, and what was sent instead.
+    fn press(hook_local: &mut HookLocalData, key: K) -> (bool, bool, Vec<InputEvent>) {
+        let mut inputs = InputBuffer::new();
+        let down = hold_down(hook_local, key.into(), &mut inputs);
+        let up = hold_up(hook_local, key.into(), &mut inputs);
+        (down, up, inputs.into_vec())
+    }
+
+    #[test]
+    fn profiles_stack_over_always_profiles() {
+        let mut game = profile(
+            "Game",
+            ProfileCondition::Process {
+                process: String::from("game.exe"),
+            },
+        );
+        game.base.policy[Button::from(K::B)] = BaseRemapPolicy::NoRemap;
+        game.base.policy[Button::from(K::C)] = remap(K::Y);
+        game.base.policy[Button::from(K::D)] = BaseRemapPolicy::Suppress;
+        let mut everywhere = profile("Everywhere", ProfileCondition::Always);
+        everywhere.base.policy[Button::from(K::A)] = remap(K::X);
+        everywhere.base.policy[Button::from(K::B)] = remap(K::X);
+        everywhere.base.policy[Button::from(K::C)] = remap(K::X);
+        let mut hook_local = HookLocalData::detached(config::Config {
+            profiles: vec![game, everywhere],
+            ..Default::default()
+        });
+
+        // Only the Always profile, until the game is in focus.
+        assert_eq!(hook_local.active_profiles.as_slice(), &[1]);
+        assert_eq!(
+            press(&mut hook_local, K::C),
+            (true, true, vec![Down(K::X.into()), Up(K::X.into())])
+        );
+
+        hook_local.update_from_foreground(WindowInfo {
+            process: String::from("game.exe"),
+            ..Default::default()
+        });
+        assert_eq!(hook_local.active_profiles.as_slice(), &[0, 1]);
+        // Left to the profile below.
+        assert_eq!(
+            press(&mut hook_local, K::A),
+            (true, true, vec![Down(K::X.into()), Up(K::X.into())])
+        );
+        // Passed through, even though the profile below remaps it.
+        assert_eq!(press(&mut hook_local, K::B), (false, false, vec![]));
+        // The top profile wins.
+        assert_eq!(
+            press(&mut hook_local, K::C),
+            (true, true, vec![Down(K::Y.into()), Up(K::Y.into())])
+        );
+        assert_eq!(press(&mut hook_local, K::D), (true, true, vec![]));
+        // No profile decides.
+        assert_eq!(press(&mut hook_local, K::E), (false, false, vec![]));
+    }
+
+    #[test]
+    fn deferring_layers_reach_profiles_below() {
+        let mut game = profile("Game", ProfileCondition::Always);
+        let mut layer = config::Layer {
+            name: String::from("Layer"),
+            condition: vec![K::CAPITAL.into()],
+            ..Default::default()
+        };
+        layer.policy[Button::from(K::B)] = config::RemapPolicy::NoRemap;
+        game.layers.push(layer);
+        let mut everywhere = profile("Everywhere", ProfileCondition::Always);
+        everywhere.base.policy[Button::from(K::A)] = remap(K::X);
+        everywhere.base.policy[Button::from(K::B)] = remap(K::X);
+        let mut hook_local = HookLocalData::detached(config::Config {
+            profiles: vec![game, everywhere],
+            ..Default::default()
+        });
+        assert_eq!(hook_local.active_profiles.as_slice(), &[0, 1]);
+
+        let mut inputs = InputBuffer::new();
+        assert!(!hold_down(&mut hook_local, K::CAPITAL.into(), &mut inputs));
+        assert_eq!(
+            press(&mut hook_local, K::A),
+            (true, true, vec![Down(K::X.into()), Up(K::X.into())])
+        );
+        assert_eq!(press(&mut hook_local, K::B), (false, false, vec![]));
+        assert!(!hold_up(&mut hook_local, K::CAPITAL.into(), &mut inputs));
+        assert_eq!(
+            press(&mut hook_local, K::B),
+            (true, true, vec![Down(K::X.into()), Up(K::X.into())])
+        );
+        assert!(inputs.is_empty());
+    }
+
+    fn down(hook_local: &mut HookLocalData, key: K) -> (bool, Vec<InputEvent>) {
+        let mut inputs = InputBuffer::new();
+        let intercepted = hold_down(hook_local, key.into(), &mut inputs);
+        (intercepted, inputs.into_vec())
+    }
+
+    fn up(hook_local: &mut HookLocalData, key: K) -> (bool, Vec<InputEvent>) {
+        let mut inputs = InputBuffer::new();
+        let intercepted = hold_up(hook_local, key.into(), &mut inputs);
+        (intercepted, inputs.into_vec())
+    }
+
+    fn layer(name: &str, layer_type: LayerType, condition: K, from: K, to: K) -> Layer {
+        let mut layer = Layer {
+            name: name.to_string(),
+            layer_type,
+            condition: vec![condition.into()],
+            ..Default::default()
+        };
+        layer.policy[Button::from(from)] = config::RemapPolicy::Remap(smallvec![to.into()]);
+        layer
+    }
+
+    fn detached(profile: config::Profile) -> HookLocalData {
+        HookLocalData::detached(config::Config {
+            profiles: vec![profile],
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn modifier_layers_are_on_while_held() {
+        let mut game = profile("Game", ProfileCondition::Always);
+        game.layers
+            .push(layer("Shift", LayerType::Modifier, K::LSHIFT, K::A, K::X));
+        let mut hook_local = detached(game);
+
+        assert_eq!(press(&mut hook_local, K::A), (false, false, vec![]));
+        // The condition button itself is not remapped.
+        assert_eq!(down(&mut hook_local, K::LSHIFT), (false, vec![]));
+        assert_eq!(
+            press(&mut hook_local, K::A),
+            (true, true, vec![Down(K::X.into()), Up(K::X.into())])
+        );
+        assert_eq!(up(&mut hook_local, K::LSHIFT), (false, vec![]));
+        assert_eq!(press(&mut hook_local, K::A), (false, false, vec![]));
+    }
+
+    #[test]
+    fn toggle_layers_flip_on_fresh_presses_only() {
+        let mut game = profile("Game", ProfileCondition::Always);
+        game.layers
+            .push(layer("Caps", LayerType::Toggle, K::CAPITAL, K::A, K::X));
+        let mut hook_local = detached(game);
+
+        down(&mut hook_local, K::CAPITAL);
+        // Key repeat does not toggle it back off.
+        down(&mut hook_local, K::CAPITAL);
+        down(&mut hook_local, K::CAPITAL);
+        up(&mut hook_local, K::CAPITAL);
+        assert_eq!(
+            press(&mut hook_local, K::A),
+            (true, true, vec![Down(K::X.into()), Up(K::X.into())])
+        );
+
+        press(&mut hook_local, K::CAPITAL);
+        assert_eq!(press(&mut hook_local, K::A), (false, false, vec![]));
+    }
+
+    #[test]
+    fn held_buttons_repeat_and_release_as_first_remapped() {
+        let mut game = profile("Game", ProfileCondition::Always);
+        game.base.policy[Button::from(K::A)] =
+            BaseRemapPolicy::Remap(smallvec![K::LCONTROL.into(), M::Left.into()]);
+        game.base.policy[Button::from(K::B)] = BaseRemapPolicy::Suppress;
+        game.layers
+            .push(layer("Caps", LayerType::Toggle, K::CAPITAL, K::A, K::X));
+        let mut hook_local = detached(game);
+
+        assert_eq!(
+            down(&mut hook_local, K::A),
+            (true, vec![Down(K::LCONTROL.into()), Down(M::Left.into())])
+        );
+        // Key repeat only repeats the keys.
+        assert_eq!(
+            down(&mut hook_local, K::A),
+            (true, vec![Down(K::LCONTROL.into())])
+        );
+        assert_eq!(
+            hook_local.button_state[HoldButton::from(K::A)],
+            HoldButtonState::HeldWithRemap(smallvec![K::LCONTROL.into(), M::Left.into()])
+        );
+        // A layer turning on while A is held does not change what A releases.
+        press(&mut hook_local, K::CAPITAL);
+        assert_eq!(
+            up(&mut hook_local, K::A),
+            (true, vec![Up(K::LCONTROL.into()), Up(M::Left.into())])
+        );
+        assert_eq!(
+            hook_local.button_state[HoldButton::from(K::A)],
+            HoldButtonState::NotHeld
+        );
+
+        assert_eq!(down(&mut hook_local, K::B), (true, vec![]));
+        assert_eq!(down(&mut hook_local, K::B), (true, vec![]));
+        assert_eq!(up(&mut hook_local, K::B), (true, vec![]));
+
+        // A release with no press seen is let through.
+        assert_eq!(up(&mut hook_local, K::C), (false, vec![]));
+    }
+
+    #[test]
+    fn pausing_leaves_held_buttons_to_be_released_as_remapped() {
+        let mut game = profile("Game", ProfileCondition::Always);
+        game.base.policy[Button::from(K::A)] = remap(K::X);
+        let mut hook_local = detached(game);
+
+        assert_eq!(down(&mut hook_local, K::A), (true, vec![Down(K::X.into())]));
+        hook_local.set_paused(true);
+        assert_eq!(up(&mut hook_local, K::A), (true, vec![Up(K::X.into())]));
+        assert_eq!(press(&mut hook_local, K::A), (false, false, vec![]));
+
+        // And the other way around: pressed while paused, released after.
+        assert_eq!(down(&mut hook_local, K::A), (false, vec![]));
+        hook_local.set_paused(false);
+        assert_eq!(up(&mut hook_local, K::A), (false, vec![]));
+        assert_eq!(
+            press(&mut hook_local, K::A),
+            (true, true, vec![Down(K::X.into()), Up(K::X.into())])
+        );
+    }
+
+    #[test]
+    fn taps_send_whole_presses() {
+        let mut game = profile("Game", ProfileCondition::Always);
+        game.base.policy[Button::from(W::Up)] =
+            BaseRemapPolicy::Remap(smallvec![K::X.into(), W::Down.into()]);
+        let mut hook_local = detached(game);
+
+        let mut inputs = InputBuffer::new();
+        assert!(tap(&mut hook_local, W::Up.into(), &mut inputs));
+        assert_eq!(
+            inputs.into_vec(),
+            [
+                Down(K::X.into()),
+                Up(K::X.into()),
+                InputEvent::Tap(W::Down.into())
+            ]
+        );
+        let mut inputs = InputBuffer::new();
+        assert!(!tap(&mut hook_local, W::Down.into(), &mut inputs));
+        hook_local.set_paused(true);
+        assert!(!tap(&mut hook_local, W::Up.into(), &mut inputs));
+        assert!(inputs.is_empty());
+    }
+}
@@ -0,0 +1,511 @@
+// Copyright 2025 Jordan Johnson
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Input capture on Linux: grabs keyboards and mice through evdev, and runs what they send through
+//! the remap engine, all on one thread.
+//!
+//! A grabbed device sends only to Reemap. What Reemap leaves alone is passed on through the
+//! LinuxDesktop's virtual device, and what it remaps is sent from there in its place (see
+//! platform/linux.rs).
+
+use super::engine::{self, InputBuffer};
+use super::hooklocal::{self, HoldButtonState, HookLocalData, with_hook_local};
+use super::runtime_config::RuntimeConfig;
+use crate::buttons::HoldButton;
+use crate::buttons::key::KeyButton;
+use crate::buttons::mouse::MouseButton;
+use crate::buttons::wheel::MouseWheelButton;
+use crate::config;
+use crate::events;
+use crate::platform::linux::{self, LinuxDesktop, VIRTUAL_DEVICE_NAME};
+use crate::platform::{InputCapture, InputInjector};
+use evdev::{Device, EventSummary, KeyCode, RelativeAxisCode, SynchronizationCode};
+use smallvec::SmallVec;
+use std::io;
+use std::os::fd::AsRawFd;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, instrument, trace, warn};
+
+// What a key event's value means.
+const RELEASED: i32 = 0;
+const PRESSED: i32 = 1;
+
+/// Grab every keyboard and mouse and remap them with this configuration, pinning a profile if
+/// given one. Returns only if reading the devices fails.
+#[instrument(skip_all, name = "capture")]
+pub fn run(
+    config: config::Config,
+    pin: Option<String>,
+    events: events::EventBus,
+) -> io::Result<()> {
+    let devices = find_devices();
+    if devices.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "no keyboard or mouse could be opened; Reemap needs read access to /dev/input",
+        ));
+    }
+    let desktop = Arc::new(LinuxDesktop::new(&devices)?);
+    let mut capture = Capture::new(devices, desktop, config, events)?;
+    with_hook_local(|hook_local| hook_local.set_pinned_profile(pin));
+    loop {
+        capture.wait(None)?;
+    }
+}
+
+/// Every keyboard and mouse that can be opened. Touchpads, tablets, game controllers and Reemap's
+/// own virtual device are left alone.
+pub fn find_devices() -> Vec<Device> {
+    evdev::enumerate()
+        .map(|(_, device)| device)
+        .filter(|device| device.name() != Some(VIRTUAL_DEVICE_NAME))
+        .filter(|device| is_keyboard(device) || is_mouse(device))
+        .inspect(|device| debug!(name = device.name(), "found input device"))
+        .collect()
+}
+
+fn is_keyboard(device: &Device) -> bool {
+    device
+        .supported_keys()
+        .is_some_and(|keys| keys.contains(KeyCode::KEY_A) && keys.contains(KeyCode::KEY_Z))
+}
+
+fn is_mouse(device: &Device) -> bool {
+    device
+        .supported_keys()
+        .is_some_and(|keys| keys.contains(KeyCode::BTN_LEFT))
+        && device
+            .supported_relative_axes()
+            .is_some_and(|axes| axes.contains(RelativeAxisCode::REL_X))
+}
+
+// -------------------- Capture --------------------
+
+/// The grabbed devices. The thread that creates this holds the remap engine's data (see
+/// hooklocal.rs), so it has to stay on that thread.
+#[derive(Debug)]
+pub struct Capture {
+    devices: Vec<Device>,
+    desktop: Arc<LinuxDesktop>,
+    // The key that was pressed as the pin hotkey, so its release is swallowed too.
+    pin_key: Option<KeyButton>,
+}
+
+impl Capture {
+    /// Grab the devices and set up the remap engine on this thread.
+    pub fn new(
+        mut devices: Vec<Device>,
+        desktop: Arc<LinuxDesktop>,
+        config: config::Config,
+        events: events::EventBus,
+    ) -> io::Result<Self> {
+        for device in devices.iter_mut() {
+            device.set_nonblocking(true)?;
+            device.grab()?;
+            info!(name = device.name(), "grabbed input device");
+        }
+        hooklocal::init_hook_local(HookLocalData::init_settings(
+            Arc::new(RuntimeConfig::new(config)),
+            desktop.clone(),
+            None,
+            events,
+        ));
+        Ok(Self {
+            devices,
+            desktop,
+            pin_key: None,
+        })
+    }
+
+    /// Wait up to the timeout for any device to send something, then handle everything sent so far.
+    /// None waits as long as it takes.
+    pub fn wait(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        let mut fds: Vec<libc::pollfd> = self
+            .devices
+            .iter()
+            .map(|device| libc::pollfd {
+                fd: device.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            })
+            .collect();
+        let timeout = timeout.map_or(-1, |timeout| {
+            timeout.as_millis().try_into().unwrap_or(libc::c_int::MAX)
+        });
+        let ready = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) };
+        if ready < 0 {
+            let e = io::Error::last_os_error();
+            return match e.kind() {
+                io::ErrorKind::Interrupted => Ok(()),
+                _ => Err(e),
+            };
+        }
+
+        // Go from the back, so removing an unplugged device does not move the ones still to read.
+        for (idx, fd) in fds.iter().enumerate().rev() {
+            if fd.revents == 0 {
+                continue;
+            }
+            if let Err(e) = self.read(idx) {
+                let device = self.devices.remove(idx);
+                warn!(?e, name = device.name(), "stopped reading input device");
+            }
+        }
+        Ok(())
+    }
+
+    // Handle everything one device has sent.
+    fn read(&mut self, idx: usize) -> io::Result<()> {
+        let Self {
+            devices,
+            desktop,
+            pin_key,
+        } = self;
+        let events = match devices[idx].fetch_events() {
+            Ok(events) => events,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        // Inputs passed on as they are wait here until the end of their report, and are sent as
+        // one. Anything Reemap sends in their place goes after the ones already waiting.
+        let mut forward: SmallVec<[evdev::InputEvent; 16]> = SmallVec::new();
+        for event in events {
+            match event.destructure() {
+                EventSummary::Synchronization(_, SynchronizationCode::SYN_REPORT, _) => {
+                    desktop.forward(&forward);
+                    forward.clear();
+                }
+                // The desktop repeats held keys itself, and ignores these.
+                EventSummary::Key(_, _, value) if value != RELEASED && value != PRESSED => {}
+                EventSummary::Key(_, code, value) => {
+                    if code == KeyCode::KEY_SCROLLLOCK && value == PRESSED {
+                        desktop.toggle_scroll_lock();
+                    }
+                    let button = match MouseButton::from_evdev(code) {
+                        Some(mouse) => Some(HoldButton::from(mouse)),
+                        None => KeyButton::from_evdev(code).map(HoldButton::from),
+                    };
+                    // Let's leave everything alone while scroll lock is enabled.
+                    let Some(button) = button.filter(|_| !desktop.escape_hatch()) else {
+                        forward.push(event);
+                        continue;
+                    };
+                    if let HoldButton::Key(key) = button
+                        && take_pin_hotkey(desktop, pin_key, key, value)
+                    {
+                        continue;
+                    }
+                    let mut inputs = InputBuffer::new();
+                    let intercepted = with_hook_local(|hook_local| match value {
+                        PRESSED => engine::hold_down(hook_local, button, &mut inputs),
+                        _ => engine::hold_up(hook_local, button, &mut inputs),
+                    });
+                    if intercepted {
+                        send(desktop, &mut forward, &inputs);
+                    } else {
+                        forward.push(event);
+                    }
+                }
+                EventSummary::RelativeAxis(_, axis, _) if linux::is_high_resolution(axis) => {}
+                EventSummary::RelativeAxis(_, axis, value)
+                    if !desktop.escape_hatch()
+                        && let Some(wheel) = MouseWheelButton::from_evdev(axis, value) =>
+                {
+                    // Each notch is its own tap.
+                    let notch =
+                        evdev::InputEvent::new(event.event_type().0, axis.0, value.signum());
+                    for _ in 0..value.unsigned_abs() {
+                        let mut inputs = InputBuffer::new();
+                        let intercepted = with_hook_local(|hook_local| {
+                            engine::tap(hook_local, wheel.into(), &mut inputs)
+                        });
+                        if intercepted {
+                            send(desktop, &mut forward, &inputs);
+                        } else {
+                            forward.push(notch);
+                        }
+                    }
+                }
+                _ => forward.push(event),
+            }
+        }
+        desktop.forward(&forward);
+        Ok(())
+    }
+}
+
+// Send what the engine collected, after whatever is already waiting to be passed on.
+fn send(
+    desktop: &LinuxDesktop,
+    forward: &mut SmallVec<[evdev::InputEvent; 16]>,
+    inputs: &InputBuffer,
+) {
+    desktop.forward(forward);
+    forward.clear();
+    desktop.inject(inputs);
+}
+
+// Swallow the pin hotkey and toggle the pin, the way it works when Windows registers it: only
+// with exactly its modifiers held. Returns true if the key event was the hotkey's.
+fn take_pin_hotkey(
+    desktop: &LinuxDesktop,
+    pin_key: &mut Option<KeyButton>,
+    key: KeyButton,
+    value: i32,
+) -> bool {
+    if value == RELEASED {
+        return pin_key.take_if(|pinned| *pinned == key).is_some();
+    }
+    let Some(hotkey) = desktop.pin_hotkey() else {
+        return false;
+    };
+    if hotkey.key != key {
+        return false;
+    }
+    with_hook_local(|hook_local| {
+        let held = |keys: [KeyButton; 2]| {
+            keys.into_iter().any(|key| {
+                hook_local.button_state[HoldButton::from(key)] != HoldButtonState::NotHeld
+            })
+        };
+        if held([KeyButton::LCONTROL, KeyButton::RCONTROL]) != hotkey.ctrl
+            || held([KeyButton::LMENU, KeyButton::RMENU]) != hotkey.alt
+            || held([KeyButton::LSHIFT, KeyButton::RSHIFT]) != hotkey.shift
+            || held([KeyButton::LWIN, KeyButton::RWIN]) != hotkey.win
+        {
+            return false;
+        }
+        trace!("pin hotkey pressed");
+        hook_local.toggle_pinned_profile();
+        *pin_key = Some(key);
+        true
+    })
+}
+
+impl Drop for Capture {
+    // Let go of anything Reemap is holding down, so nothing stays pressed once the devices are
+    // ungrabbed.
+    fn drop(&mut self) {
+        let mut inputs = InputBuffer::new();
+        with_hook_local(|hook_local| engine::release_all(hook_local, &mut inputs));
+        self.desktop.inject(&inputs);
+        debug!("releasing input devices");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buttons::Button;
+    use crate::buttons::key::KeyButton as K;
+    use crate::buttons::wheel::MouseWheelButton as W;
+    use crate::config::BaseRemapPolicy;
+    use evdev::uinput::VirtualDevice;
+    use evdev::{AttributeSet, EventType};
+    use smallvec::smallvec;
+    use std::time::Instant;
+
+    /*
+        These run Reemap between two virtual devices: a keyboard and mouse made with uinput, which
+        the test presses buttons on, and Reemap's own virtual device, which the test reads. They
+        need write access to /dev/uinput and read access to /dev/input, so they are ignored unless
+        asked for with `cargo test -- --ignored`. Without that access they fail rather than pass
+        having checked nothing.
+    */
+
+    const TIMEOUT: Duration = Duration::from_secs(2);
+
+    struct Rig {
+        input: VirtualDevice,
+        output: Device,
+        capture: Capture,
+    }
+
+    fn rig(config: config::Config) -> Rig {
+        if let Err(e) = std::fs::OpenOptions::new().write(true).open("/dev/uinput") {
+            panic!("/dev/uinput is not available: {e}");
+        }
+
+        let mut keys = AttributeSet::<KeyCode>::new();
+        for key in [
+            KeyCode::KEY_A,
+            KeyCode::KEY_D,
+            KeyCode::KEY_Q,
+            KeyCode::KEY_Z,
+            KeyCode::KEY_SCROLLLOCK,
+            KeyCode::BTN_LEFT,
+        ] {
+            keys.insert(key);
+        }
+        let mut axes = AttributeSet::<RelativeAxisCode>::new();
+        for axis in [
+            RelativeAxisCode::REL_X,
+            RelativeAxisCode::REL_Y,
+            RelativeAxisCode::REL_WHEEL,
+        ] {
+            axes.insert(axis);
+        }
+        let mut input = VirtualDevice::builder()
+            .unwrap()
+            .name("Reemap test keyboard and mouse")
+            .with_keys(&keys)
+            .unwrap()
+            .with_relative_axes(&axes)
+            .unwrap()
+            .build()
+            .unwrap();
+        let device = open(
+            input
+                .enumerate_dev_nodes_blocking()
+                .unwrap()
+                .next()
+                .unwrap()
+                .unwrap(),
+        );
+
+        let desktop = Arc::new(LinuxDesktop::new(std::slice::from_ref(&device)).unwrap());
+        let output = open(desktop.dev_node().unwrap());
+        output.set_nonblocking(true).unwrap();
+        let capture = Capture::new(vec![device], desktop, config, Default::default()).unwrap();
+        Rig {
+            input,
+            output,
+            capture,
+        }
+    }
+
+    // udev may take a moment to give a new device node its permissions.
+    fn open(path: std::path::PathBuf) -> Device {
+        let start = Instant::now();
+        loop {
+            match Device::open(&path) {
+                Ok(device) => return device,
+                Err(e) if start.elapsed() > TIMEOUT => panic!("could not open {path:?}: {e}"),
+                Err(_) => std::thread::sleep(Duration::from_millis(10)),
+            }
+        }
+    }
+
+    impl Rig {
+        // Send inputs on the test device, let Reemap handle them, and collect what comes out until
+        // `expected` inputs have, or it times out.
+        fn send(&mut self, events: &[(EventType, u16, i32)], expected: usize) -> Vec<(u16, i32)> {
+            let events: Vec<evdev::InputEvent> = events
+                .iter()
+                .map(|&(kind, code, value)| evdev::InputEvent::new(kind.0, code, value))
+                .collect();
+            self.input.emit(&events).unwrap();
+
+            let mut received = Vec::new();
+            let start = Instant::now();
+            while received.len() < expected && start.elapsed() < TIMEOUT {
+                self.capture.wait(Some(Duration::from_millis(10))).unwrap();
+                if let Ok(events) = self.output.fetch_events() {
+                    received.extend(
+                        events
+                            .filter(|event| event.event_type() != EventType::SYNCHRONIZATION)
+                            .map(|event| (event.code(), event.value())),
+                    );
+                }
+            }
+            received
+        }
+    }
+
+    fn key(key: KeyCode, value: i32) -> (EventType, u16, i32) {
+        (EventType::KEY, key.0, value)
+    }
+
+    fn always(base: config::BaseLayer) -> config::Config {
+        config::Config {
+            profiles: vec![config::Profile {
+                name: String::from("Test"),
+                condition: config::ProfileCondition::Always,
+                base,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    #[ignore = "needs /dev/uinput"]
+    fn remaps_keys_between_virtual_devices() {
+        let mut base = config::BaseLayer::default();
+        base.policy[Button::from(K::A)] = BaseRemapPolicy::Remap(smallvec![K::B.into()]);
+        base.policy[Button::from(K::D)] = BaseRemapPolicy::Suppress;
+        let mut rig = rig(always(base));
+
+        let b = KeyCode::KEY_B.0;
+        let q = KeyCode::KEY_Q.0;
+        assert_eq!(
+            rig.send(&[key(KeyCode::KEY_A, 1)], 1),
+            vec![(b, PRESSED)],
+            "remapped press"
+        );
+        assert_eq!(
+            rig.send(&[key(KeyCode::KEY_A, 0)], 1),
+            vec![(b, RELEASED)],
+            "remapped release"
+        );
+        assert_eq!(
+            rig.send(&[key(KeyCode::KEY_D, 1), key(KeyCode::KEY_D, 0)], 0),
+            vec![],
+            "suppressed"
+        );
+        assert_eq!(
+            rig.send(&[key(KeyCode::KEY_Q, 1), key(KeyCode::KEY_Q, 0)], 2),
+            vec![(q, PRESSED), (q, RELEASED)],
+            "not remapped"
+        );
+    }
+
+    #[test]
+    #[ignore = "needs /dev/uinput"]
+    fn leaves_everything_alone_under_scroll_lock() {
+        let mut base = config::BaseLayer::default();
+        base.policy[Button::from(K::A)] = BaseRemapPolicy::Remap(smallvec![K::B.into()]);
+        let mut rig = rig(always(base));
+        assert!(
+            !rig.capture.desktop.escape_hatch(),
+            "scroll lock must be off to start with"
+        );
+
+        let scroll = KeyCode::KEY_SCROLLLOCK;
+        rig.send(&[key(scroll, 1), key(scroll, 0)], 2);
+        let a = KeyCode::KEY_A.0;
+        assert_eq!(
+            rig.send(&[key(KeyCode::KEY_A, 1), key(KeyCode::KEY_A, 0)], 2),
+            vec![(a, PRESSED), (a, RELEASED)]
+        );
+    }
+
+    #[test]
+    #[ignore = "needs /dev/uinput"]
+    fn remaps_each_wheel_notch_and_passes_motion_on() {
+        let mut base = config::BaseLayer::default();
+        base.policy[Button::from(W::Up)] = BaseRemapPolicy::Remap(smallvec![K::PRIOR.into()]);
+        let mut rig = rig(always(base));
+
+        let page_up = KeyCode::KEY_PAGEUP.0;
+        let wheel = RelativeAxisCode::REL_WHEEL.0;
+        assert_eq!(
+            rig.send(&[(EventType::RELATIVE, wheel, 2)], 4),
+            vec![
+                (page_up, PRESSED),
+                (page_up, RELEASED),
+                (page_up, PRESSED),
+                (page_up, RELEASED)
+            ]
+        );
+        assert_eq!(
+            rig.send(&[(EventType::RELATIVE, wheel, -1)], 1),
+            vec![(wheel, -1)],
+            "scrolling down is not remapped"
+        );
+        let x = RelativeAxisCode::REL_X.0;
+        assert_eq!(rig.send(&[(EventType::RELATIVE, x, 5)], 1), vec![(x, 5)]);
+    }
+}
@@ -4,70 +4,141 @@
 use crate::buttons;
 use crate::config;
 use crate::config::REMAP_SMALLVEC_LEN;
-use crate::gui;
-use crate::gui::ReemapGuiEvent;
-use crate::query_windows::WindowInfo;
-use crate::query_windows::get_foreground_window;
+use crate::events;
+use crate::hooks::runtime_config::RuntimeConfig;
+use crate::hooks::{ApplyError, UiEvent, UiSink};
+use crate::platform::{self, Rect, WindowInfo};
+use crate::stats;
 use enum_map::EnumMap;
 use smallvec::SmallVec;
-use std::sync::Mutex;
+use std::cell::RefCell;
+use std::sync::Arc;
 use tracing::{info, warn};
-use windows::Win32::Foundation;
 
 /*
     This is the runtime storage for the Hook thread.
     I also dislike global variables. Unfortunately, the nature of Windows hook callbacks make it
     necessary.
 
+    It is thread-local. Windows calls the hook procedures on the thread that installed them, so only
+    the hook thread ever touches it, and nothing has to lock it. Other threads reach it by posting
+    messages to the hook thread. New configurations come through a SharedConfig instead (see
+    runtime_config.rs).
+
     Everything that touches this variable is in the hooks module:
-        hooks/input.rs (the main user of this data):
-            -   acquires the mutex and changes HOOKLOCAL on every button press and release
-        hooks/mod.rs:
+        hooks/input_hooks.rs and hooks/engine.rs (the main users of this data):
+            -   change HOOKLOCAL on every button press and release
+        hooks/thread.rs:
             -   initializes HOOKLOCAL on startup
-            -   acquires the mutex and calls .update_config() on recepit of an Update message
-            -   acquires the mutex and calls .update_new_foreground() on receipt of a Check
-                Foreground Window message
+            -   calls .update_config() on receipt of an Update message
+            -   calls .update_new_foreground() on receipt of a Check Foreground Window message
+        hooks/evdev_capture.rs (Linux, in place of the two above):
+            -   initializes HOOKLOCAL, and reads every input on the same thread
 */
-pub static HOOKLOCAL: Mutex<Option<HookLocalData>> = Mutex::new(None);
+thread_local! {
+    static HOOKLOCAL: RefCell<Option<HookLocalData>> = const { RefCell::new(None) };
+}
+
+/// Set up this thread's HookLocalData. Only the hook thread should call this.
+pub fn init_hook_local(hook_local: HookLocalData) {
+    HOOKLOCAL.set(Some(hook_local));
+}
+
+/// Run a function with this thread's HookLocalData. Panics if it was never set up, which means
+/// this is not the hook thread.
+pub fn with_hook_local<R>(f: impl FnOnce(&mut HookLocalData) -> R) -> R {
+    HOOKLOCAL.with_borrow_mut(|hook_local| {
+        f(hook_local
+            .as_mut()
+            .expect("local data should have been initialized"))
+    })
+}
 
 // -------------------- HookLocalData --------------------
 #[derive(Debug, Clone)]
 pub struct HookLocalData {
-    pub ui_proxy: winit::event_loop::EventLoopProxy<gui::ReemapGuiEvent>,
+    pub desktop: Arc<dyn platform::Desktop>,
+    pub ui: Option<Arc<dyn UiSink>>, // None when detached, or with no UI.
     pub ui_observing_inputs: bool,
-    pub config: config::Config,
+    pub events: events::EventBus,
+    pub config: Arc<RuntimeConfig>,
     pub button_state: EnumMap<buttons::HoldButton, HoldButtonState>,
-    pub active_profile: Option<usize>,
+    pub active_profiles: SmallVec<[usize; REMAP_SMALLVEC_LEN]>, // Profile stack, highest priority first.
     pub active_layers_profile: Vec<SmallVec<[bool; REMAP_SMALLVEC_LEN]>>, // Outer vec: over profiles. Inner vec: over layers.
-    pub last_clip: Option<Foundation::RECT>,
+    pub active_layer_sets: Vec<usize>, // The same, as each profile's tables want it.
+    pub pinned_profile: Option<String>, // By name, so it survives the user rearranging profiles.
+    pub paused: bool,
+    pub stats: stats::ButtonStats,
+    pub foreground: Option<WindowInfo>,
+    pub last_clip: Option<Rect>,
 }
 
 impl HookLocalData {
     /// Create a new HookLocalData struct instance from an initial configuration.
     pub fn init_settings(
-        config: config::Config,
-        ui_proxy: winit::event_loop::EventLoopProxy<ReemapGuiEvent>,
+        config: Arc<RuntimeConfig>,
+        desktop: Arc<dyn platform::Desktop>,
+        ui: Option<Arc<dyn UiSink>>, // None where there is no UI.
+        events: events::EventBus,
     ) -> Self {
-        let mut result = Self {
-            ui_proxy,
+        let mut result = Self::empty(desktop, ui, events);
+        // Problems are logged. There is nobody to tell them to yet.
+        let _ = result.update_config(config);
+        result
+    }
+
+    /// Create an instance that is not connected to the UI, the pin hotkey or the window in focus,
+    /// for running remaps outside the hooks. The benchmarks use this. Every enabled "Always"
+    /// profile is active.
+    pub fn detached(config: config::Config) -> Self {
+        let mut result = Self::empty(
+            Arc::new(platform::Detached),
+            None,
+            events::EventBus::default(),
+        );
+        result.load_config(Arc::new(RuntimeConfig::new(config)));
+        result.update_from_foreground(WindowInfo::default());
+        result
+    }
+
+    fn empty(
+        desktop: Arc<dyn platform::Desktop>,
+        ui: Option<Arc<dyn UiSink>>,
+        events: events::EventBus,
+    ) -> Self {
+        Self {
+            desktop,
+            ui,
             ui_observing_inputs: false,
+            events,
             config: Default::default(),
             button_state: Default::default(),
-            active_profile: Default::default(),
+            active_profiles: Default::default(),
             active_layers_profile: Default::default(),
+            active_layer_sets: Default::default(),
+            pinned_profile: None,
+            paused: false,
+            stats: Default::default(),
+            foreground: None,
             last_clip: Default::default(),
-        };
-        result.update_config(config);
+        }
+    }
+
+    /// Change the remaps to the provided configuration. The remaps are always changed, even if
+    /// something else about the configuration could not be installed.
+    pub fn update_config(&mut self, config: Arc<RuntimeConfig>) -> Result<(), ApplyError> {
+        self.load_config(config);
+        let result = self.register_pin_hotkey();
+        self.update_from_current_foreground();
         result
     }
 
-    /// Change the remaps to the provided configuration
-    pub fn update_config(&mut self, config: config::Config) {
+    // Set self.config, .active_layers_profile and .active_layer_sets. The caller updates
+    // .active_profiles.
+    // Note: it is not necessary to set button_state.
+    fn load_config(&mut self, config: Arc<RuntimeConfig>) {
         use smallvec::smallvec;
 
-        // Set self.config, .active_profile, .active_layers
-        // Note: it is not necessary to set button_state.
-
         self.config = config;
         self.active_layers_profile = self
             .config
@@ -75,101 +146,269 @@ impl HookLocalData {
             .iter()
             .map(|profile| smallvec![false; profile.layers.len()])
             .collect();
+        self.active_layer_sets = vec![0; self.config.profiles.len()];
+        self.stats.add_profiles(
+            self.config
+                .profiles
+                .iter()
+                .map(|profile| profile.name.as_str()),
+        );
+    }
+
+    /// Pin a profile by name so it stays on top regardless of the window in focus, or unpin with
+    /// None.
+    pub fn set_pinned_profile(&mut self, pinned_profile: Option<String>) {
+        if self.pinned_profile == pinned_profile {
+            return;
+        }
+        match &pinned_profile {
+            Some(name) => info!("pinning profile {name}"),
+            None => info!("unpinning profile"),
+        }
+        if let Some(name) = &pinned_profile
+            && self
+                .config
+                .profiles
+                .iter()
+                .any(|profile| profile.name == *name && !profile.enabled)
+        {
+            warn!("profile {name} is disabled; it takes effect once it is enabled");
+        }
+        self.pinned_profile = pinned_profile;
+        self.send_to_ui(UiEvent::ChangedPin(self.pinned_profile.clone()));
+        self.events.publish(events::Event::PinChanged {
+            profile: self.pinned_profile.clone(),
+        });
+        self.update_from_current_foreground();
+    }
+
+    /// Stop or start remapping new button presses.
+    pub fn set_paused(&mut self, paused: bool) {
+        if self.paused == paused {
+            return;
+        }
+        info!(paused, "setting paused");
+        self.paused = paused;
+        self.events.publish(events::Event::PausedChanged { paused });
+        self.update_clip();
+    }
+
+    /// Count a fresh press towards the statistics, under the profile on top right now.
+    pub fn record_press(&mut self, button: buttons::Button) {
+        let profile = self
+            .active_profiles
+            .first()
+            .map(|&profile_idx| self.config.profiles[profile_idx].name.as_str());
+        self.stats.record(button, profile);
+    }
+
+    /// Tell input subscribers which buttons are held now. Call after changing button_state.
+    pub fn publish_held_buttons(&self) {
+        if !self.events.wants_inputs() {
+            return;
+        }
+        let buttons = events::HeldButtons(EnumMap::from_fn(|button| {
+            match &self.button_state[button] {
+                HoldButtonState::NotHeld => None,
+                HoldButtonState::HeldNoRemap => Some(events::HeldAs::Unchanged),
+                HoldButtonState::HeldWithRemap(output) => {
+                    Some(events::HeldAs::Remapped(output.clone()))
+                }
+                HoldButtonState::HeldSuppress => Some(events::HeldAs::Suppressed),
+            }
+        }));
+        self.events.publish(events::Event::HeldButtons { buttons });
+    }
+
+    /// A snapshot of what the hook thread is doing, for other programs.
+    pub fn status(&self) -> events::Status {
+        let profiles = self
+            .active_profiles
+            .iter()
+            .map(|&profile_idx| {
+                let profile = &self.config.profiles[profile_idx];
+                let layers = profile
+                    .layers
+                    .iter()
+                    .zip(self.active_layers_profile[profile_idx].iter())
+                    .filter(|(layer, active)| layer.enabled && **active)
+                    .map(|(layer, _)| layer.name.clone())
+                    .collect();
+                events::ActiveProfile {
+                    name: profile.name.clone(),
+                    layers,
+                }
+            })
+            .collect();
+        events::Status {
+            profiles,
+            all_profiles: self
+                .config
+                .profiles
+                .iter()
+                .map(|profile| profile.name.clone())
+                .collect(),
+            pinned: self.pinned_profile.clone(),
+            paused: self.paused,
+        }
+    }
 
-        match get_foreground_window() {
+    /// Respond to the pin hotkey: pin whichever profile is on top right now, or unpin if a profile
+    /// is already pinned.
+    pub fn toggle_pinned_profile(&mut self) {
+        let pinned_profile = match self.pinned_profile {
+            Some(_) => None,
+            None => self
+                .active_profiles
+                .first()
+                .map(|&profile_idx| self.config.profiles[profile_idx].name.clone()),
+        };
+        self.set_pinned_profile(pinned_profile);
+    }
+
+    // Register the pin hotkey to match the configuration.
+    fn register_pin_hotkey(&self) -> Result<(), ApplyError> {
+        let hotkey = self.config.pin_hotkey.as_ref();
+        match self.desktop.register_pin_hotkey(hotkey) {
+            Ok(()) => {
+                if let Some(hotkey) = hotkey {
+                    info!("registered pin hotkey {hotkey}");
+                }
+                Ok(())
+            }
+            Err(e) => {
+                let hotkey = hotkey.map(ToString::to_string).unwrap_or_default();
+                warn!(?e, "failed to register pin hotkey {hotkey}");
+                Err(ApplyError::PinHotkey { hotkey })
+            }
+        }
+    }
+
+    // There's a possibility the UI thread just barely stopped, so this may fail.
+    // That's OK, so we intentionally ignore any errors.
+    pub fn send_to_ui(&self, event: UiEvent) {
+        let Some(ui) = &self.ui else {
+            return;
+        };
+        if !ui.send(event) {
+            warn!("failed to send message to UI thread");
+        }
+    }
+
+    fn update_from_current_foreground(&mut self) {
+        match self.desktop.foreground_window() {
             Ok(info) => {
                 self.update_from_foreground(info);
             }
             Err(e) => {
+                // Only the window's own profile depends on the window. The pinned and "Always"
+                // profiles stay.
                 warn!(
                     ?e,
-                    "failed to get foreground window; assuming default profile"
+                    "failed to get foreground window; assuming no window profile"
                 );
-                self.active_profile = None;
+                self.set_active_profiles(None);
+                self.foreground = None;
+                self.update_clip();
             }
         }
     }
 
-    /// Update the active profile using information about the current foreground window.
+    /// Update the active profiles using information about the current foreground window.
+    ///
+    /// Profiles are stacked. The first enabled profile whose window condition matches goes on top,
+    /// and every enabled "Always" profile stays active underneath it, in list order. A button the
+    /// top profile does not handle falls through to the profiles below.
+    ///
+    /// A pinned profile takes the top spot instead, whether or not it matches the window, as long
+    /// as it is enabled.
     pub fn update_from_foreground(&mut self, info: WindowInfo) {
-        use windows::Win32::UI::Input::KeyboardAndMouse as KBM;
-        use windows::Win32::UI::WindowsAndMessaging as WM;
+        self.set_active_profiles(Some(&info));
+
+        // Finally, update the cursor clip.
+        self.foreground = Some(info);
+        self.update_clip();
+    }
 
-        let WindowInfo {
-            title,
-            process,
-            rect,
-        } = info;
+    // Work out the profile stack, with no window profile when there is no window to match.
+    fn set_active_profiles(&mut self, info: Option<&WindowInfo>) {
+        let enabled_profiles = || {
+            self.config
+                .profiles
+                .iter()
+                .enumerate()
+                .filter(|(_, profile)| profile.enabled)
+        };
+        let matched_profile = info.and_then(|info| {
+            enabled_profiles()
+                .filter(|(_, profile)| profile.condition != config::ProfileCondition::Always)
+                .find(|(_, profile)| condition_matches(&profile.condition, info))
+                .map(|(i, _)| i)
+        });
+        let pinned_profile = self.pinned_profile.as_ref().and_then(|name| {
+            enabled_profiles()
+                .find(|(_, profile)| profile.name == *name)
+                .map(|(i, _)| i)
+        });
+        let top_profile = pinned_profile.or(matched_profile);
+        let global_profiles = enabled_profiles()
+            .filter(|(_, profile)| profile.condition == config::ProfileCondition::Always)
+            .map(|(i, _)| i)
+            .filter(|&i| Some(i) != top_profile);
+        let new_profiles: SmallVec<[usize; REMAP_SMALLVEC_LEN]> =
+            top_profile.into_iter().chain(global_profiles).collect();
 
-        let mut new_profile = None;
-        for (i, profile_condition) in self
-            .config
-            .profiles
-            .iter()
-            .enumerate()
-            .filter(|(_, profile)| profile.enabled)
-            .map(|(i, profile)| (i, &profile.condition))
-        {
-            let profile_matches = match profile_condition {
-                config::ProfileCondition::Always => true,
-                config::ProfileCondition::TitleAndProcess {
-                    title: condition_title,
-                    process: condition_process,
-                } if title == *condition_title && process == *condition_process => true,
-
-                config::ProfileCondition::Title {
-                    title: condition_title,
-                } if title == *condition_title => true,
-
-                config::ProfileCondition::Process {
-                    process: condition_process,
-                } if process == *condition_process => true,
-                _ => false,
-            };
-            if profile_matches {
-                new_profile = Some(i);
-                break;
-            }
-        }
-        if self.active_profile != new_profile {
+        if self.active_profiles != new_profiles {
             // Inform the UI thread the profile changed.
-            // There's a possibility the UI thread just barely stopped, so this may fail.
-            // That's OK, so we intentionally ignore any errors.
-            let ui_send_result = self
-                .ui_proxy
-                .send_event(gui::ReemapGuiEvent::ChangedProfile(
-                    new_profile.map(|profile_idx| self.config.profiles[profile_idx].name.clone()),
-                ));
-            if ui_send_result.is_err() {
-                warn!("failed to send message to UI thread");
-            }
-            match new_profile {
-                None => info!(?new_profile, "no profile enabled"),
-                Some(profile_idx) => info!(
-                    ?new_profile,
-                    "switching to profile {}", &self.config.profiles[profile_idx].name
-                ),
+            let names: Vec<String> = new_profiles
+                .iter()
+                .map(|&profile_idx| self.config.profiles[profile_idx].name.clone())
+                .collect();
+            self.send_to_ui(UiEvent::ChangedProfile(names.clone()));
+            self.events.publish(events::Event::ProfilesChanged {
+                profiles: names.clone(),
+            });
+            if names.is_empty() {
+                info!("no profile enabled");
+            } else {
+                info!(?new_profiles, "switching to profiles {}", names.join(", "));
             }
         }
-        self.active_profile = new_profile;
+        self.active_profiles = new_profiles;
+    }
 
-        // Finally, update the cursor clip.
+    /// Clip the cursor according to the active profiles and layers.
+    ///
+    /// The hook procedures call this when a layer turns on or off, since a layer can ask for the
+    /// cursor to be confined while it is active. Held buttons alone never change the clip.
+    pub fn update_clip(&mut self) {
         // We should clip only if:
-        //  -   scroll lock is not enabled, and
-        //  -   the profile wants it, and
+        //  -   scroll lock (the escape hatch) is not enabled, and remaps are not paused, and
+        //  -   an active profile wants it, either on its own or through an active layer, and
         //  -   we successfully got the window bounds
-        let profile_wants_to_clip_cursor = match self.active_profile {
-            None => false,
-            Some(idx) => self.config.profiles[idx].clip_cursor,
-        };
+        // The highest-priority profile that wants to clip decides where.
+        let clipping_profile = self
+            .active_profiles
+            .iter()
+            .map(|&idx| (&self.config.profiles[idx], &self.active_layers_profile[idx]))
+            .find(|(profile, layer_actives)| {
+                profile.clip_cursor
+                    || profile
+                        .layers
+                        .iter()
+                        .zip(layer_actives.iter())
+                        .any(|(layer, active)| *active && layer.enabled && layer.clip_cursor)
+            })
+            .map(|(profile, _)| profile);
 
-        let scroll_lock = unsafe { KBM::GetKeyState(KBM::VK_SCROLL.0.into()) & 1 > 0 };
+        let escape_hatch = self.desktop.escape_hatch();
 
-        let will_clip_to = if !scroll_lock
-            && profile_wants_to_clip_cursor
-            && let Some(rect) = rect
+        let will_clip_to = if !escape_hatch
+            && !self.paused
+            && let Some(profile) = clipping_profile
+            && let Some(info) = &self.foreground
         {
-            Some(rect)
+            clip_rect(&profile.clip_area, profile.clip_inset, info)
         } else {
             None
         };
@@ -186,32 +425,289 @@ impl HookLocalData {
         info!(?will_clip_to, "clipping");
 
         // Clip the cursor!
-        // as_ref() is preferable to this, except Option<&T> doesn't get coerced to Option<*const T>
-        let clip_result = unsafe {
-            match will_clip_to {
-                None => WM::ClipCursor(None),
-                Some(rect) => WM::ClipCursor(Some(&rect)),
-            }
-        };
-
-        match clip_result {
+        match self.desktop.clip_cursor(will_clip_to) {
             Ok(()) => (),
             Err(e) => warn!(?e, "failed to clip cursor"),
         }
     }
 }
 
+// Work out the rectangle to confine the cursor to, if the window gave us the bounds we need.
+fn clip_rect(area: &config::ClipArea, inset: i32, info: &WindowInfo) -> Option<Rect> {
+    use config::ClipArea;
+    let rect = match area {
+        ClipArea::Window => info.rect?,
+        ClipArea::ClientArea => info.client_rect?,
+        ClipArea::Monitor => info.monitor_rect?,
+        ClipArea::CenterLock { size } => {
+            // Center on the window's contents where possible, since that's where the crosshair is.
+            let bounds = info.client_rect.or(info.rect)?;
+            let center_x = (bounds.left + bounds.right) / 2;
+            let center_y = (bounds.top + bounds.bottom) / 2;
+            let half = (*size).max(1) / 2;
+            Rect {
+                left: center_x - half,
+                top: center_y - half,
+                right: center_x + half,
+                bottom: center_y + half,
+            }
+        }
+    };
+    // Shrink by the inset, but never past the middle of the rectangle.
+    let inset_x = inset.clamp(0, ((rect.right - rect.left) / 2).max(0));
+    let inset_y = inset.clamp(0, ((rect.bottom - rect.top) / 2).max(0));
+    Some(Rect {
+        left: rect.left + inset_x,
+        top: rect.top + inset_y,
+        right: rect.right - inset_x,
+        bottom: rect.bottom - inset_y,
+    })
+}
+
+// Check whether a window matches a profile condition.
+fn condition_matches(condition: &config::ProfileCondition, info: &WindowInfo) -> bool {
+    use config::ProfileCondition;
+    match condition {
+        ProfileCondition::Always => true,
+        ProfileCondition::TitleAndProcess { title, process } => {
+            info.title == *title && info.process == *process
+        }
+        ProfileCondition::Title { title } => info.title == *title,
+        ProfileCondition::Process { process } => info.process == *process,
+        ProfileCondition::WindowClass { class } => info.class == *class,
+        // Windows paths are case-insensitive.
+        ProfileCondition::ExecutablePath { path } => info.path.eq_ignore_ascii_case(path),
+        // Neither are process names.
+        ProfileCondition::AncestorProcess { process } => info
+            .ancestors
+            .iter()
+            .any(|ancestor| ancestor.eq_ignore_ascii_case(process)),
+        ProfileCondition::All(conditions) => conditions
+            .iter()
+            .all(|condition| condition_matches(condition, info)),
+        ProfileCondition::Any(conditions) => conditions
+            .iter()
+            .any(|condition| condition_matches(condition, info)),
+    }
+}
+
 // -------------------- HoldButtonState --------------------
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum HoldButtonState {
+    #[default]
     NotHeld,
     HeldNoRemap,
     HeldWithRemap(config::Output),
     HeldSuppress,
 }
 
-impl Default for HoldButtonState {
-    fn default() -> Self {
-        Self::NotHeld
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::ProfileCondition as PC;
+
+    fn window() -> WindowInfo {
+        WindowInfo {
+            title: String::from("Game"),
+            process: String::from("game.exe"),
+            class: String::from("UnityWndClass"),
+            path: String::from(r"C:\Games\Game\game.exe"),
+            ancestors: vec![String::from("Launcher.exe"), String::from("explorer.exe")],
+            ..WindowInfo::default()
+        }
+    }
+
+    #[test]
+    fn window_details_match() {
+        let window = window();
+        let class = |class: &str| PC::WindowClass {
+            class: class.to_string(),
+        };
+        assert!(condition_matches(&class("UnityWndClass"), &window));
+        assert!(!condition_matches(&class("unitywndclass"), &window));
+
+        let path = |path: &str| PC::ExecutablePath {
+            path: path.to_string(),
+        };
+        assert!(condition_matches(&path(r"c:\games\GAME\Game.exe"), &window));
+        assert!(!condition_matches(&path(r"C:\Games\game.exe"), &window));
+
+        let ancestor = |process: &str| PC::AncestorProcess {
+            process: process.to_string(),
+        };
+        assert!(condition_matches(&ancestor("launcher.exe"), &window));
+        assert!(condition_matches(&ancestor("EXPLORER.EXE"), &window));
+        assert!(!condition_matches(&ancestor("game.exe"), &window));
+    }
+
+    #[test]
+    fn all_and_any_nest() {
+        let window = window();
+        let title = |title: &str| PC::Title {
+            title: title.to_string(),
+        };
+        let process = |process: &str| PC::Process {
+            process: process.to_string(),
+        };
+
+        assert!(condition_matches(&PC::All(vec![]), &window));
+        assert!(!condition_matches(&PC::Any(vec![]), &window));
+        assert!(condition_matches(
+            &PC::All(vec![title("Game"), process("game.exe")]),
+            &window
+        ));
+        assert!(!condition_matches(
+            &PC::All(vec![title("Game"), process("other.exe")]),
+            &window
+        ));
+        assert!(condition_matches(
+            &PC::Any(vec![title("Other"), process("game.exe")]),
+            &window
+        ));
+
+        // Either launcher, but only for the game itself.
+        let nested = PC::All(vec![
+            process("game.exe"),
+            PC::Any(vec![
+                PC::AncestorProcess {
+                    process: String::from("steam.exe"),
+                },
+                PC::AncestorProcess {
+                    process: String::from("launcher.exe"),
+                },
+            ]),
+        ]);
+        assert!(condition_matches(&nested, &window));
+        let other = WindowInfo {
+            process: String::from("launcher.exe"),
+            ..window
+        };
+        assert!(!condition_matches(&nested, &other));
+    }
+
+    #[test]
+    fn margins_shrink_every_clip_area() {
+        let window = WindowInfo {
+            rect: Some(Rect {
+                left: 0,
+                top: 0,
+                right: 1000,
+                bottom: 800,
+            }),
+            ..WindowInfo::default()
+        };
+        assert_eq!(
+            clip_rect(&config::ClipArea::Window, 10, &window),
+            Some(Rect {
+                left: 10,
+                top: 10,
+                right: 990,
+                bottom: 790,
+            })
+        );
+        assert_eq!(
+            clip_rect(&config::ClipArea::CenterLock { size: 100 }, 10, &window),
+            Some(Rect {
+                left: 460,
+                top: 360,
+                right: 540,
+                bottom: 440,
+            })
+        );
+        // Never past the middle.
+        assert_eq!(
+            clip_rect(&config::ClipArea::CenterLock { size: 100 }, 500, &window),
+            Some(Rect {
+                left: 500,
+                top: 400,
+                right: 500,
+                bottom: 400,
+            })
+        );
+        assert_eq!(clip_rect(&config::ClipArea::Monitor, 10, &window), None);
+    }
+
+    fn profile(name: &str, enabled: bool, condition: PC) -> config::Profile {
+        config::Profile {
+            name: name.to_string(),
+            enabled,
+            condition,
+            ..Default::default()
+        }
+    }
+
+    fn profiles() -> HookLocalData {
+        let game = PC::Process {
+            process: String::from("game.exe"),
+        };
+        HookLocalData::detached(config::Config {
+            profiles: vec![
+                profile("Game", true, game),
+                profile("Off", false, PC::Always),
+                profile("Everywhere", true, PC::Always),
+            ],
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn disabled_profiles_are_not_pinned() {
+        let mut hook_local = profiles();
+        hook_local.set_pinned_profile(Some(String::from("Off")));
+        assert_eq!(hook_local.active_profiles.as_slice(), &[2]);
+        hook_local.update_from_foreground(window());
+        assert_eq!(hook_local.active_profiles.as_slice(), &[0, 2]);
+
+        hook_local.set_pinned_profile(Some(String::from("Game")));
+        hook_local.update_from_foreground(WindowInfo::default());
+        assert_eq!(hook_local.active_profiles.as_slice(), &[0, 2]);
+    }
+
+    // A desktop whose window in focus cannot be found.
+    #[derive(Debug)]
+    struct NoWindow;
+
+    impl platform::InputInjector for NoWindow {
+        fn inject(&self, _inputs: &[buttons::InputEvent]) {}
+    }
+
+    impl platform::InputCapture for NoWindow {
+        fn escape_hatch(&self) -> bool {
+            false
+        }
+        fn register_pin_hotkey(&self, _hotkey: Option<&config::Hotkey>) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl platform::WindowQuery for NoWindow {
+        fn foreground_window(&self) -> std::io::Result<WindowInfo> {
+            Err(std::io::Error::other("no window"))
+        }
+        fn open_windows(&self) -> Vec<WindowInfo> {
+            Vec::new()
+        }
+    }
+
+    impl platform::CursorClip for NoWindow {
+        fn clip_cursor(&self, _rect: Option<Rect>) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn always_profiles_outlast_a_lost_window() {
+        let mut hook_local = profiles();
+        hook_local.update_from_foreground(window());
+        assert_eq!(hook_local.active_profiles.as_slice(), &[0, 2]);
+
+        hook_local.desktop = Arc::new(NoWindow);
+        hook_local.update_from_current_foreground();
+        assert_eq!(hook_local.active_profiles.as_slice(), &[2]);
+        assert_eq!(hook_local.foreground, None);
+
+        // A pin does not need the window.
+        hook_local.set_pinned_profile(Some(String::from("Game")));
+        assert_eq!(hook_local.active_profiles.as_slice(), &[0, 2]);
     }
 }
@@ -1,14 +1,18 @@
 // Copyright 2025 Jordan Johnson
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
-use crate::config::{BaseLayer, BaseRemapPolicy, Layer, LayerType, RemapPolicy};
+//! The low-level keyboard and mouse hooks on Windows. They turn what Windows reports into presses,
+//! releases and taps for the remap engine (see engine.rs), and send what it answers with.
 
 use crate::buttons::key::KeyButton;
 use crate::buttons::mouse::MouseButton;
 use crate::buttons::wheel::MouseWheelButton;
-use crate::buttons::{Button, HoldButton, TapButton};
+use crate::buttons::{HoldButton, TapButton};
 
-use crate::hooks::hooklocal::{HOOKLOCAL, HoldButtonState};
+use crate::hooks::engine::{self, InputBuffer};
+use crate::hooks::hooklocal::with_hook_local;
+use crate::hooks::supervisor;
+use crate::platform::{InputCapture, InputInjector, WindowsDesktop};
 
 use tracing::{instrument, trace, warn};
 use windows::Win32::Foundation;
@@ -57,15 +61,21 @@ unsafe extern "system" fn keybd_hook(
         }
     }
 
+    let hookstruct: WM::KBDLLHOOKSTRUCT = unsafe { *(lParam.0 as *const WM::KBDLLHOOKSTRUCT) };
+
+    // The supervisor checks that this hook is still installed. Nobody else should see its probes.
+    if hookstruct.dwExtraInfo == supervisor::PROBE_MARKER {
+        supervisor::probe_arrived(supervisor::Hook::Keybd);
+        return Foundation::LRESULT(1);
+    }
+
     // Let's disable hooks while scroll lock is enabled.
-    if unsafe { KeyboardAndMouse::GetKeyState(KeyboardAndMouse::VK_SCROLL.0.into()) & 1 > 0 } {
+    if WindowsDesktop.escape_hatch() {
         unsafe {
             return WM::CallNextHookEx(None, nCode, wParam, lParam);
         }
     }
 
-    let hookstruct: WM::KBDLLHOOKSTRUCT = unsafe { *(lParam.0 as *const WM::KBDLLHOOKSTRUCT) };
-
     // Filter out any synthesized inputs to:
     //  1.  Avoid responding to our own inputs (note: could also do this with dwExtraInfo)
     //  2.  Avoid responding to inputs from something like AHK; this could create a loop depending
@@ -115,15 +125,21 @@ unsafe extern "system" fn mouse_hook(
         }
     }
 
+    let hookstruct: WM::MSLLHOOKSTRUCT = unsafe { *(lParam.0 as *const WM::MSLLHOOKSTRUCT) };
+
+    // The supervisor checks that this hook is still installed. Nobody else should see its probes.
+    if hookstruct.dwExtraInfo == supervisor::PROBE_MARKER {
+        supervisor::probe_arrived(supervisor::Hook::Mouse);
+        return Foundation::LRESULT(1);
+    }
+
     // Let's disable hooks while scroll lock is enabled.
-    if unsafe { KeyboardAndMouse::GetKeyState(KeyboardAndMouse::VK_SCROLL.0.into()) & 1 > 0 } {
+    if WindowsDesktop.escape_hatch() {
         unsafe {
             return WM::CallNextHookEx(None, nCode, wParam, lParam);
         }
     }
 
-    let hookstruct: WM::MSLLHOOKSTRUCT = unsafe { *(lParam.0 as *const WM::MSLLHOOKSTRUCT) };
-
     // Filter out any synthesized inputs to:
     //  1.  Avoid responding to our own inputs (note: could also do this with dwExtraInfo)
     //  2.  Avoid responding to inputs from something like AHK; this could create a loop depending
@@ -278,93 +294,6 @@ unsafe extern "system" fn mouse_hook(
     unsafe { WM::CallNextHookEx(None, nCode, wParam, lParam) }
 }
 
-/*
-
-On button down:
-
-    1.  Check button_state. Key repeat means it is likely we receive many DOWN inputs before an UP
-        input. We want to map subsequent DOWN inputs the same as we mapped the first DOWN input.
-        Additionally, if this DOWN input is due to key repeat, we do not want to toggle any toggle
-        layers.
-
-        i.  If the input state is "HeldNoRemap", forward the input unmodified and quit.
-
-        ii. If the input state is "HeldWithRemap", send the listed Hold inputs as DOWN and quit.
-            Do not send any Tap inputs because we've already sent the one for this button press.
-
-        iii.If the input state is "NotHeld", we have a new button press. Let's process it.
-
-    2.  Update which layers are enabled.
-
-        For each layer (exclduing the base layer, which is always enabled):
-
-        a.  If this input is in the layer's condition list, and all other buttons in this list
-            are already held:
-
-            i.  If this is a modifier layer, make it enabled.
-
-            ii. If this is a toggle layer, toggle whether it is enabled.
-
-    3.  Layers are now up-to-date with this latest button press. Now, dispatch inputs and mark
-        button_state.
-
-        For each enabled layer, starting from the highest priority, check the layer's policy
-        for this button.
-
-        a.  If this layer's policy is "Defer", check the next layer (not available for base layer).
-
-        b.  If this layer's policy is "NoRemap", immediately forward the input unmodified. Mark the
-            input in button_state as "HeldNoRemap".
-
-        c.  If this layer's policy is "Remap", immediately send the specified inputs.
-            Send Hold inputs as a DOWN input.
-            Send Tap inputs.
-            Mark the input in button_state as "HeldWithRemap".
-
-On button up:
-
-    1.  Every button up is a fresh, new button up. Update which layers are enabled.
-
-        For each layer (excluding the base layer, which is always enabled):
-
-        a.  If this input is in the layer's condition list:
-
-            i.  If this is a modifier layer, make it disabled.
-
-            ii. If this is a toggle layer, do nothing. Toggle layers only change on a depress.
-
-
-    2.  Layers are now up-to-date with this latest button press. Now check and update button_state:
-
-        a.  If the input is "HeldNoRemap", immediately forward the input unmodified.
-
-        b.  If the input is "HeldWithRemap", immediately send the specified inputs as UP inputs.
-
-        c.  If the input is "NotHeld", well, this shouldn't have happened. We got a KEYUP or
-            MOUSEUP without remembering seeing a corresponding KEYDOWN or MOUSEDOWN. Ah well - let's
-            just forward the input unmodified.
-
-    (Notice we do not check the mappings in the layers on a button up.)
-
-    3.  Mark the button_state as NotHeld.
-
-On tap:
-
-    Layers cannot be conditional on tap inputs. Therefore, we do not need to adjust any "enabled"
-    values for the layers.
-
-    For each layer, check the layer's policy for this button.
-
-    1.  If this layer's policy is "Defer", check the next layer (not available for base layer).
-
-    2.  If this layer's policy is "NoRemap", immediately forward the input unmodified.
-
-    3.  If this layer's policy is "Remap", immediately send the specified inputs.
-        Every Hold input in the policy should be sent together as a DOWN/UP pair.
-        Every Tap input should be sent.
-
-*/
-
 /*
 
     Intercept Hold Down Input
@@ -372,145 +301,17 @@ On tap:
 */
 
 // Returns "true" if the input is intercepted.
-// Refer to the above pseudocode.
+// Refer to the pseudocode in engine.rs.
 #[instrument(name = "btn_down")]
 fn intercept_hold_down_input(hold_button: HoldButton) -> bool {
-    use crate::gui::ReemapGuiEvent::ButtonPressed;
-
     trace!("got button down");
 
-    let mut hook_local_guard = HOOKLOCAL.lock().expect("mutex poisoned");
-    let hook_local = hook_local_guard
-        .as_mut()
-        .expect("local data should have been initialized");
-
-    // Inform the UI of this button press if it wants to know.
-    if hook_local.ui_observing_inputs {
-        let _ = hook_local
-            .ui_proxy
-            .send_event(ButtonPressed(hold_button.into()));
-    }
-
-    // Step 1
-    // An early return to handle key repeat - the case when you get multiple keydowns before a keyup
-    match &hook_local.button_state[hold_button] {
-        // Already held (key repeat), and there's no remap. So do not intercept it.
-        HoldButtonState::HeldNoRemap => {
-            return false;
-        }
-
-        // Already held (key repeat), and there is a remap.
-        // Let's intercept it and repeat any keyboard keys this remap targets.
-        HoldButtonState::HeldWithRemap(targets) => {
-            let target_keys: Vec<KeyboardAndMouse::INPUT> = targets
-                .iter()
-                .filter_map(|btn| match btn {
-                    Button::Key(key) => Some(key.to_keydown_input()),
-                    _ => None,
-                })
-                .collect();
-
-            send_input_batch(&target_keys);
-            return true;
-        }
-
-        // ALready held (key repeat), and the input is suppressed.
-        // Exact same as HeldWithRemap, except just don't send any input. So, intercept it.
-        HoldButtonState::HeldSuppress => return true,
-
-        // Not held - this is a fresh input. Let's continue processing.
-        HoldButtonState::NotHeld => {}
-    }
-
-    // Check that a profile is actually active. Otherwise, do not intercept.
-    let Some(profile_idx) = hook_local.active_profile else {
-        return false;
-    };
-    let current_base: &BaseLayer = &hook_local.config.profiles[profile_idx].base;
-    let current_layers: &[Layer] = &hook_local.config.profiles[profile_idx].layers;
-    let current_layer_actives: &mut [bool] = &mut hook_local.active_layers_profile[profile_idx];
-
-    // Step 2
-    // Update layers
-    for (layer, active) in current_layers
-        .iter()
-        .zip(current_layer_actives.iter_mut())
-        .filter(|(layer, _)| layer.enabled)
-    {
-        // Only update layers for which this button is a condition.
-        if layer.condition.contains(&hold_button) {
-            // All conditions met?
-            if layer
-                .condition
-                .iter()
-                .filter(|&condition| *condition != hold_button)
-                .all(|condition| hook_local.button_state[*condition] != HoldButtonState::NotHeld)
-            {
-                // All conditions met. Let's enable/toggle this layer.
-                match &layer.layer_type {
-                    LayerType::Modifier => *active = true,
-                    LayerType::Toggle => *active = !*active,
-                }
-            }
-        }
-    }
-
-    // Step 3
-    // Identify the appropriate remap and apply it. At the same time, set button_state.
-    for (layer, _) in current_layers
-        .iter()
-        .zip(current_layer_actives.iter())
-        .filter(|(layer, active)| layer.enabled && **active)
-    {
-        match &layer.policy[Button::from(hold_button)] {
-            RemapPolicy::Defer => {}
-            RemapPolicy::Remap(output) => {
-                let target_buttons: Vec<KeyboardAndMouse::INPUT> = output
-                    .iter()
-                    .map(|button| match button {
-                        Button::Key(key) => key.to_keydown_input(),
-                        Button::Mouse(mouse) => mouse.to_mousedown_input(),
-                        Button::Wheel(wheel) => wheel.to_input(),
-                    })
-                    .collect();
-                send_input_batch(&target_buttons);
-                hook_local.button_state[hold_button] =
-                    HoldButtonState::HeldWithRemap(output.clone());
-                return true;
-            }
-            RemapPolicy::Suppress => {
-                hook_local.button_state[hold_button] = HoldButtonState::HeldSuppress;
-                return true;
-            }
-            RemapPolicy::NoRemap => {
-                hook_local.button_state[hold_button] = HoldButtonState::HeldNoRemap;
-                return false;
-            }
-        }
-    }
-    match &current_base.policy[Button::from(hold_button)] {
-        BaseRemapPolicy::Remap(output) => {
-            let target_buttons: Vec<KeyboardAndMouse::INPUT> = output
-                .iter()
-                .map(|button| match button {
-                    Button::Key(key) => key.to_keydown_input(),
-                    Button::Mouse(mouse) => mouse.to_mousedown_input(),
-                    Button::Wheel(wheel) => wheel.to_input(),
-                })
-                .collect();
-            send_input_batch(&target_buttons);
-            hook_local.button_state[hold_button] = HoldButtonState::HeldWithRemap(output.clone());
-            true
-        }
-        BaseRemapPolicy::Suppress => {
-            hook_local.button_state[hold_button] = HoldButtonState::HeldSuppress;
-            true
-        }
-        BaseRemapPolicy::NoRemap => {
-            hook_local.button_state[hold_button] = HoldButtonState::HeldNoRemap;
-            false
-        }
-    }
+    let mut inputs = InputBuffer::new();
+    let intercepted =
+        with_hook_local(|hook_local| engine::hold_down(hook_local, hold_button, &mut inputs));
+    // Sent once the hook thread's data is released, in case sending comes back around to a hook.
+    WindowsDesktop.inject(&inputs);
+    intercepted
 }
 
 /*
@@ -520,66 +321,16 @@ fn intercept_hold_down_input(hold_button: HoldButton) -> bool {
 */
 
 // Returns "true" if the input is intercepted.
-// Refer to the above pseudocode.
+// Refer to the pseudocode in engine.rs.
 #[instrument(name = "btn_up")]
 fn intercept_hold_up_input(hold_button: HoldButton) -> bool {
     trace!("got button up");
 
-    let mut hook_local_guard = HOOKLOCAL.lock().expect("mutex poisoned");
-    let hook_local = hook_local_guard
-        .as_mut()
-        .expect("local data should have been initialized");
-
-    // Step 1
-    // Update layers
-    if let Some(profile_idx) = hook_local.active_profile {
-        let current_layers: &[Layer] = &hook_local.config.profiles[profile_idx].layers;
-        let current_layer_actives: &mut [bool] = &mut hook_local.active_layers_profile[profile_idx];
-        for (layer, active) in current_layers
-            .iter()
-            .zip(current_layer_actives.iter_mut())
-            .filter(|(layer, _)| layer.enabled)
-        {
-            // Only update layers for which this button is a condition.
-            // These layers are no longer active.
-            if layer.condition.contains(&hold_button) {
-                match &layer.layer_type {
-                    LayerType::Modifier => *active = false,
-                    LayerType::Toggle => (), // Toggle buttons not affected by keyup
-                }
-            }
-        }
-    }
-
-    // Step 2
-    // See what this button was mapped to.
-    // Note we never consult the profile. The original decision of what a button maps to is only
-    // made when the button is first pressed.
-    let intercepted = match &hook_local.button_state[hold_button] {
-        // This button down was not intercepted, so let's not intercept the button up.
-        HoldButtonState::HeldNoRemap | HoldButtonState::NotHeld => false,
-
-        // This button down was intercepted, so let's intercept the button up the same way.
-        HoldButtonState::HeldWithRemap(targets) => {
-            let target_buttons: Vec<KeyboardAndMouse::INPUT> = targets
-                .iter()
-                .filter_map(|button| match button {
-                    Button::Key(key) => Some(key.to_keyup_input()),
-                    Button::Mouse(mouse) => Some(mouse.to_mouseup_input()),
-                    Button::Wheel(_wheel) => None, // Wheel input only sent on down press
-                })
-                .collect();
-
-            send_input_batch(&target_buttons);
-            true
-        }
-
-        // This button down was intercepted and suppressed, so let's suppress the button up, too.
-        HoldButtonState::HeldSuppress => true,
-    };
-    // Step 3
-    hook_local.button_state[hold_button] = HoldButtonState::NotHeld;
-
+    let mut inputs = InputBuffer::new();
+    let intercepted =
+        with_hook_local(|hook_local| engine::hold_up(hook_local, hold_button, &mut inputs));
+    // Sent once the hook thread's data is released, in case sending comes back around to a hook.
+    WindowsDesktop.inject(&inputs);
     intercepted
 }
 
@@ -590,91 +341,25 @@ fn intercept_hold_up_input(hold_button: HoldButton) -> bool {
 */
 
 // Returns "true" if the input is intercepted.
-// Refer to the above pseudocode.
+// Refer to the pseudocode in engine.rs.
 #[instrument(name = "tap")]
 fn intercept_tap_input(tap_button: TapButton) -> bool {
-    use crate::gui::ReemapGuiEvent::ButtonPressed;
-
     trace!("got tap input");
-    // Layers are not allowed to depend on tap inputs.
-    // Additionally, we do not try to remember which tap inputs are "held", because it is
-    // meaningless to "hold" a scroll wheel button.
-    // This makes the job much easier.
-
-    let mut hook_local_guard = HOOKLOCAL.lock().expect("mutex poisoned");
-    let hook_local = hook_local_guard
-        .as_mut()
-        .expect("local data should have been initialized");
-
-    // Inform the UI of this button press if it wants to know.
-    if hook_local.ui_observing_inputs {
-        let _ = hook_local
-            .ui_proxy
-            .send_event(ButtonPressed(tap_button.into()));
-    }
 
-    // Check that a profile is actually active. Otherwise, do not intercept.
-    let Some(profile_idx) = hook_local.active_profile else {
-        return false;
-    };
-    let current_base: &BaseLayer = &hook_local.config.profiles[profile_idx].base;
-    let current_layers: &[Layer] = &hook_local.config.profiles[profile_idx].layers;
-    let current_layer_actives: &[bool] = &hook_local.active_layers_profile[profile_idx];
-
-    for (layer, _) in current_layers
-        .iter()
-        .zip(current_layer_actives.iter())
-        .filter(|(layer, active)| layer.enabled && **active)
-    {
-        match &layer.policy[Button::from(tap_button)] {
-            RemapPolicy::Defer => {}
-            RemapPolicy::Remap(output) => {
-                let target_buttons: Vec<KeyboardAndMouse::INPUT> = output
-                    .iter()
-                    .flat_map(|button| match button {
-                        Button::Key(key) => {
-                            vec![key.to_keydown_input(), key.to_keyup_input()].into_iter()
-                        }
-                        Button::Mouse(mouse) => {
-                            vec![mouse.to_mousedown_input(), mouse.to_mouseup_input()].into_iter()
-                        }
-                        Button::Wheel(wheel) => vec![wheel.to_input()].into_iter(),
-                    })
-                    .collect();
-                send_input_batch(&target_buttons);
-                return true;
-            }
-            RemapPolicy::Suppress => {
-                return true;
-            }
-            RemapPolicy::NoRemap => {
-                return false;
-            }
-        }
-    }
-    match &current_base.policy[Button::from(tap_button)] {
-        BaseRemapPolicy::Remap(output) => {
-            let target_buttons: Vec<KeyboardAndMouse::INPUT> = output
-                .iter()
-                .flat_map(|button| match button {
-                    Button::Key(key) => {
-                        vec![key.to_keydown_input(), key.to_keyup_input()].into_iter()
-                    }
-                    Button::Mouse(mouse) => {
-                        vec![mouse.to_mousedown_input(), mouse.to_mouseup_input()].into_iter()
-                    }
-                    Button::Wheel(wheel) => vec![wheel.to_input()].into_iter(),
-                })
-                .collect();
-            send_input_batch(&target_buttons);
-            true
-        }
-        BaseRemapPolicy::Suppress => true,
-        BaseRemapPolicy::NoRemap => false,
-    }
+    let mut inputs = InputBuffer::new();
+    let intercepted =
+        with_hook_local(|hook_local| engine::tap(hook_local, tap_button, &mut inputs));
+    // Sent once the hook thread's data is released, in case sending comes back around to a hook.
+    WindowsDesktop.inject(&inputs);
+    intercepted
 }
 
-fn send_input_batch(input: &[KeyboardAndMouse::INPUT]) {
+// Send inputs Windows already understands. The supervisor's probes are the only ones; everything
+// else goes through WindowsDesktop.
+pub fn send_input_batch(input: &[KeyboardAndMouse::INPUT]) {
+    if input.is_empty() {
+        return;
+    }
     let cbsize = std::mem::size_of::<KeyboardAndMouse::INPUT>() as i32;
     unsafe {
         KeyboardAndMouse::SendInput(input, cbsize);
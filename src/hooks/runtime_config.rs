@@ -0,0 +1,54 @@
+// Copyright 2025 Jordan Johnson
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! The configuration as the hook thread runs it.
+//!
+//! It is built from a [`config::Config`] on whichever thread applies the configuration, and never
+//! changes afterwards. The hook thread is handed new ones through a [`SharedConfig`]: applying a
+//! configuration swaps the pointer, and the hook thread loads it between inputs, then answers with
+//! whether everything was installed. The hook thread never waits on the side applying it.
+
+use crate::buttons::HoldButton;
+use crate::config;
+use crate::tables::ProfileTables;
+use arc_swap::ArcSwap;
+use enum_map::EnumMap;
+use std::sync::Arc;
+
+pub type SharedConfig = Arc<ArcSwap<RuntimeConfig>>;
+
+#[derive(Debug, Default)]
+pub struct RuntimeConfig {
+    pub profiles: Vec<config::Profile>,
+    pub pin_hotkey: Option<config::Hotkey>,
+    // Whether each button is a condition of an enabled layer in an enabled profile. No other
+    // button can turn a layer on or off, so the hooks skip the layers for them.
+    pub layer_conditions: EnumMap<HoldButton, bool>,
+    // One for each profile, to look up what it does with a button.
+    pub tables: Vec<ProfileTables>,
+}
+
+impl RuntimeConfig {
+    /// Prepare a configuration for the hook thread. Inheritance should already be resolved.
+    pub fn new(config: config::Config) -> Self {
+        let mut layer_conditions: EnumMap<HoldButton, bool> = EnumMap::default();
+        let layers = config
+            .profiles
+            .iter()
+            .filter(|profile| profile.enabled)
+            .flat_map(|profile| profile.layers.iter())
+            .filter(|layer| layer.enabled);
+        for layer in layers {
+            for &button in layer.condition.iter() {
+                layer_conditions[button] = true;
+            }
+        }
+        let tables = config.profiles.iter().map(ProfileTables::new).collect();
+        Self {
+            profiles: config.profiles,
+            pin_hotkey: config.pin_hotkey,
+            layer_conditions,
+            tables,
+        }
+    }
+}
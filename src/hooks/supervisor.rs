@@ -0,0 +1,256 @@
+// Copyright 2025 Jordan Johnson
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Keeps the input hooks installed.
+//!
+//! Installing a hook can fail, and Windows quietly removes a low-level hook that takes longer than
+//! LowLevelHooksTimeout to answer, without telling the program that installed it. So the hook
+//! thread checks on its hooks with probes: a keyboard input and a mouse input that do nothing,
+//! marked as Reemap's. Each hook procedure swallows its probe and checks it off. A hook whose probe
+//! is not checked off in time is taken to be gone, and is installed again.
+//!
+//! Presses and releases made while a hook was gone went past Reemap. So once the hooks are back,
+//! every button Reemap thinks is held is released the way it was remapped, and nothing is left
+//! stuck down.
+//!
+//! Windows counts injected inputs as activity. A probe is only sent if something else happened
+//! since the last one, so a computer left alone can still lock and go to sleep.
+
+use super::engine::{self, InputBuffer};
+use super::hooklocal::with_hook_local;
+use super::input_hooks;
+use super::{HookHealth, UiEvent};
+use crate::platform::{InputInjector, WindowsDesktop};
+use enum_map::EnumMap;
+use smallvec::SmallVec;
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+use windows::Win32::UI::Input::KeyboardAndMouse as KBM;
+use windows::Win32::UI::WindowsAndMessaging as WM;
+
+// Marks the probes in dwExtraInfo, so the hook procedures can tell them from other inputs.
+pub const PROBE_MARKER: usize = 0x5245_454D;
+// An unassigned virtual-key code. The keyboard probe releases it.
+const PROBE_VK: u16 = 0x97;
+const PROBE_INTERVAL: Duration = Duration::from_secs(5);
+// How long the hooks get to check off a probe.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, enum_map::Enum)]
+pub enum Hook {
+    Keybd,
+    Mouse,
+}
+
+// Set by each hook procedure when its probe comes through. Only the hook thread uses these.
+thread_local! {
+    static KEYBD_PROBE_SEEN: Cell<bool> = const { Cell::new(false) };
+    static MOUSE_PROBE_SEEN: Cell<bool> = const { Cell::new(false) };
+}
+
+impl Hook {
+    fn probe_seen(self) -> &'static std::thread::LocalKey<Cell<bool>> {
+        match self {
+            Self::Keybd => &KEYBD_PROBE_SEEN,
+            Self::Mouse => &MOUSE_PROBE_SEEN,
+        }
+    }
+    fn install(self) -> Result<WM::HHOOK, windows::core::Error> {
+        match self {
+            Self::Keybd => input_hooks::set_keybd_hook(),
+            Self::Mouse => input_hooks::set_mouse_hook(),
+        }
+    }
+    fn probe_input(self) -> KBM::INPUT {
+        match self {
+            Self::Keybd => KBM::INPUT {
+                r#type: KBM::INPUT_KEYBOARD,
+                Anonymous: KBM::INPUT_0 {
+                    ki: KBM::KEYBDINPUT {
+                        wVk: KBM::VIRTUAL_KEY(PROBE_VK),
+                        wScan: 0,
+                        dwFlags: KBM::KEYEVENTF_KEYUP,
+                        time: 0,
+                        dwExtraInfo: PROBE_MARKER,
+                    },
+                },
+            },
+            // A move by nothing.
+            Self::Mouse => KBM::INPUT {
+                r#type: KBM::INPUT_MOUSE,
+                Anonymous: KBM::INPUT_0 {
+                    mi: KBM::MOUSEINPUT {
+                        dx: 0,
+                        dy: 0,
+                        mouseData: 0,
+                        dwFlags: KBM::MOUSEEVENTF_MOVE,
+                        time: 0,
+                        dwExtraInfo: PROBE_MARKER,
+                    },
+                },
+            },
+        }
+    }
+}
+
+/// The hook procedures call this when an input carries PROBE_MARKER, then swallow the input.
+pub fn probe_arrived(hook: Hook) {
+    hook.probe_seen().set(true);
+}
+
+#[derive(Debug, Default)]
+pub struct Supervisor {
+    hooks: EnumMap<Hook, Option<WM::HHOOK>>,
+    // When the probes still waiting to be checked off were sent.
+    probe_sent: Option<Instant>,
+    last_probe: Option<Instant>,
+    // The time of the last input Windows knows about, once the last probes were through. If it
+    // has changed, something other than a probe happened since.
+    probe_input_time: Option<u32>,
+    // Whether the UI was told a hook is missing, so it is only told once.
+    reported_failure: bool,
+}
+
+impl Supervisor {
+    /// Install the input hooks. Any that cannot be installed are tried again on each check.
+    pub fn start() -> Self {
+        let mut supervisor = Self::default();
+        let mut failure = None;
+        for hook in [Hook::Keybd, Hook::Mouse] {
+            if let Err(e) = supervisor.install(hook) {
+                failure = Some(e);
+            }
+        }
+        if let Some(e) = failure {
+            supervisor.report_failure(e);
+        }
+        supervisor
+    }
+
+    /// Make sure the hooks are still there. The hook thread calls this on its timer.
+    pub fn check(&mut self) {
+        let now = Instant::now();
+        let mut reinstalled = false;
+        let mut failure = None;
+
+        // Hooks that could not be installed before.
+        for hook in [Hook::Keybd, Hook::Mouse] {
+            if self.hooks[hook].is_some() {
+                continue;
+            }
+            match self.install(hook) {
+                Ok(()) => reinstalled = true,
+                Err(e) => failure = Some(e),
+            }
+        }
+
+        // Hooks that did not check off the last probe.
+        if let Some(probe_sent) = self.probe_sent
+            && now.duration_since(probe_sent) >= PROBE_TIMEOUT
+        {
+            self.probe_sent = None;
+            self.probe_input_time = last_input_time();
+            for hook in [Hook::Keybd, Hook::Mouse] {
+                let seen = hook.probe_seen().replace(false);
+                if seen || self.hooks[hook].is_none() {
+                    continue;
+                }
+                warn!(?hook, "hook did not see its probe; installing it again");
+                match self.install(hook) {
+                    Ok(()) => reinstalled = true,
+                    Err(e) => failure = Some(e),
+                }
+            }
+        }
+
+        if reinstalled {
+            release_held_buttons();
+        }
+        match failure {
+            Some(e) => self.report_failure(e),
+            None if reinstalled => {
+                info!("hooks recovered");
+                self.reported_failure = false;
+                send_health(HookHealth::Recovered);
+            }
+            None => (),
+        }
+
+        let probe_due = self
+            .last_probe
+            .is_none_or(|last_probe| now.duration_since(last_probe) >= PROBE_INTERVAL);
+        let input_since_probe =
+            self.probe_input_time.is_none() || last_input_time() != self.probe_input_time;
+        if self.probe_sent.is_none() && probe_due && input_since_probe {
+            self.send_probes(now);
+        }
+    }
+
+    /// Remove the hooks. The hook thread calls this when it quits.
+    pub fn remove(&mut self) {
+        for hhk in self.hooks.values_mut().filter_map(Option::take) {
+            let _ = unsafe { input_hooks::remove_hook(hhk) };
+        }
+    }
+
+    // Install a hook, removing it first if it is supposedly there already.
+    fn install(&mut self, hook: Hook) -> Result<(), windows::core::Error> {
+        if let Some(hhk) = self.hooks[hook].take() {
+            let _ = unsafe { input_hooks::remove_hook(hhk) };
+        }
+        match hook.install() {
+            Ok(hhk) => {
+                self.hooks[hook] = Some(hhk);
+                Ok(())
+            }
+            Err(e) => {
+                error!(?e, ?hook, "could not install hook");
+                Err(e)
+            }
+        }
+    }
+
+    fn send_probes(&mut self, now: Instant) {
+        let inputs: SmallVec<[KBM::INPUT; 2]> = [Hook::Keybd, Hook::Mouse]
+            .into_iter()
+            .filter(|&hook| self.hooks[hook].is_some())
+            .map(Hook::probe_input)
+            .collect();
+        if inputs.is_empty() {
+            return;
+        }
+        input_hooks::send_input_batch(&inputs);
+        self.probe_sent = Some(now);
+        self.last_probe = Some(now);
+    }
+
+    fn report_failure(&mut self, e: windows::core::Error) {
+        if self.reported_failure {
+            return;
+        }
+        self.reported_failure = true;
+        send_health(HookHealth::Failed(e.to_string()));
+    }
+}
+
+// Release every button Reemap thinks is held. Their releases may have gone past a missing hook.
+fn release_held_buttons() {
+    let mut inputs = InputBuffer::new();
+    with_hook_local(|hook_local| engine::release_all(hook_local, &mut inputs));
+    WindowsDesktop.inject(&inputs);
+}
+
+fn send_health(health: HookHealth) {
+    with_hook_local(|hook_local| hook_local.send_to_ui(UiEvent::HookHealth(health)));
+}
+
+fn last_input_time() -> Option<u32> {
+    let mut info = KBM::LASTINPUTINFO {
+        cbSize: std::mem::size_of::<KBM::LASTINPUTINFO>() as u32,
+        dwTime: 0,
+    };
+    unsafe { KBM::GetLastInputInfo(&mut info) }
+        .as_bool()
+        .then_some(info.dwTime)
+}
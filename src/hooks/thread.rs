@@ -0,0 +1,398 @@
+// Copyright 2025 Jordan Johnson
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! The hook thread on Windows: installs the hooks, runs their message loop, and takes requests
+//! from the other threads as messages.
+
+use super::hooklocal::{self, with_hook_local};
+use super::runtime_config::{self, RuntimeConfig};
+use super::{ApplyError, UiSink, foreground_hook, minimize_end_hook, supervisor};
+use crate::config;
+use crate::events;
+use crate::platform::win32::PIN_HOTKEY_ID;
+use crate::platform::{CursorClip, InputCapture, WindowsDesktop};
+use crate::query_windows::get_foreground_window;
+use crate::stats;
+use arc_swap::ArcSwap;
+use tracing::{debug, info, instrument, trace, warn};
+
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+use windows::Win32::Foundation;
+use windows::Win32::System::Threading;
+use windows::Win32::UI::WindowsAndMessaging;
+
+const CHECK_FOREGROUND_INTERVAL_MS: u32 = 500;
+
+// What the hook thread answers an Update with.
+pub type UpdateResult = std::result::Result<(), ApplyError>;
+
+// The main way to launch the hook thread. Pass in a std::thread::scope, and this function
+// will spawn the thread to handle all the hooks involved in Reemap. It will return a proxy to the
+// thread.
+pub fn spawn_scoped<'scope, 'env>(
+    s: &'scope std::thread::Scope<'scope, 'env>,
+    config: config::Config,
+    ui: Arc<dyn UiSink>,
+    events: events::EventBus,
+) -> HookthreadProxy {
+    let (oneshot_sender, oneshot_receiver) = oneshot::channel();
+    s.spawn(|| {
+        run(oneshot_sender, config, ui, events);
+    });
+    oneshot_receiver.recv().unwrap()
+}
+
+// Run the hook thread and return a proxy through the oneshot.
+// Panics if the hook thread is already running.
+#[instrument(skip_all, name = "hooks")]
+pub fn run(
+    sender: oneshot::Sender<HookthreadProxy>,
+    config: config::Config,
+    ui: Arc<dyn UiSink>,
+    events: events::EventBus,
+) {
+    debug!("entering hook thread");
+    use WindowsAndMessaging as WM;
+    use num_traits::FromPrimitive;
+
+    static RUNNING: Mutex<bool> = Mutex::new(false);
+
+    let mut running = RUNNING.lock().unwrap();
+    if *running {
+        panic!("Attempted to start hook thread while it was already running");
+    } else {
+        *running = true;
+    }
+    std::mem::drop(running);
+
+    // Initialize the persistent thread data.
+    let shared_config = Arc::new(ArcSwap::from_pointee(RuntimeConfig::new(config)));
+    hooklocal::init_hook_local(hooklocal::HookLocalData::init_settings(
+        shared_config.load_full(),
+        Arc::new(WindowsDesktop),
+        Some(ui),
+        events,
+    ));
+
+    // Force Windows to create a message queue for this thread. We want to have one before we
+    // give out our thread ID, which other threads use to post messages to.
+    unsafe {
+        let mut lpmsg_unused = WM::MSG::default();
+        let _ = WM::PeekMessageW(&mut lpmsg_unused, None, 0, 0, WM::PM_NOREMOVE);
+    }
+
+    // Create a proxy and give it back to whoever spawned us.
+    let thread_id = unsafe { Threading::GetCurrentThreadId() };
+    let proxy = HookthreadProxy {
+        thread_id,
+        config: shared_config.clone(),
+    };
+    sender.send(proxy.clone()).unwrap();
+
+    // Create a timer with which to check the foreground window.
+    // It is the only polling done in Reemap, and it is only done as a backup.
+    // Windows is not perfectly reliable in sending events when the foreground window changes.
+    // Nor in keeping the input hooks installed, so the supervisor checks on them with it too.
+    let timer_id = unsafe { WM::SetTimer(None, 0, CHECK_FOREGROUND_INTERVAL_MS, None) };
+    if timer_id == 0 {
+        panic!("could not create timer");
+    }
+
+    // Establish all of the hooks. The supervisor keeps the input hooks installed from here on.
+    let mut supervisor = supervisor::Supervisor::start();
+    let foreground_hhk = foreground_hook::set_hook(proxy.clone()).unwrap();
+    let minimize_end_hhk = minimize_end_hook::set_hook(proxy.clone()).unwrap();
+
+    let mut lpmsg = WM::MSG::default();
+    unsafe {
+        loop {
+            let bret = WM::GetMessageW(&mut lpmsg, None, 0, 0);
+            if !bret.as_bool() {
+                break;
+            }
+            if bret.0 == -1 {
+                warn!(?bret, "error from GetMessageW");
+                break;
+            }
+            trace!("handling a new message");
+            match HookMessage::from_u32(lpmsg.message) {
+                Some(HookMessage::Quit) => {
+                    trace!("handling Quit message");
+                    supervisor.remove();
+                    let _ = foreground_hook::remove_hook(foreground_hhk);
+                    let _ = minimize_end_hook::remove_hook(minimize_end_hhk);
+                    let _ = WindowsDesktop.register_pin_hotkey(None);
+                    WM::PostQuitMessage(0);
+                    trace!("done handling Quit message");
+                }
+                Some(HookMessage::Update) => {
+                    trace!("handling Update message");
+                    let Foundation::WPARAM(raw_usize) = lpmsg.wParam;
+                    let raw = raw_usize as *mut oneshot::Sender<UpdateResult>;
+                    let sender_boxed = Box::from_raw(raw);
+                    let sender = *sender_boxed;
+
+                    // Several updates may have been stored before this message was handled.
+                    // Only the newest matters. If it was already installed, the message for it
+                    // reported any problems.
+                    let config = shared_config.load_full();
+                    let result = with_hook_local(|hook_local| {
+                        if Arc::ptr_eq(&hook_local.config, &config) {
+                            return Ok(());
+                        }
+                        info!("updating config");
+                        hook_local.update_config(config)
+                    });
+                    // Whoever asked may have given up waiting. That's OK.
+                    let _ = sender.send(result);
+                    trace!("done handling Update message");
+                }
+                Some(message @ (HookMessage::TimerExpire | HookMessage::CheckForeground)) => {
+                    trace!("handling TimerExpire or CheckForeground message");
+                    if message == HookMessage::TimerExpire {
+                        supervisor.check();
+                    }
+                    match get_foreground_window() {
+                        Ok(info) => {
+                            with_hook_local(|hook_local| hook_local.update_from_foreground(info));
+                        }
+                        Err(e) => {
+                            warn!(?e, "failed to get foreground window");
+                        }
+                    }
+                    trace!("done handling TimerExpire or CheckForeground message");
+                }
+                Some(HookMessage::RegisterUIObserveInputs) => {
+                    trace!("handling RegisterUIObserveInputs message");
+                    debug!("registered to send inputs to UI");
+                    with_hook_local(|hook_local| hook_local.ui_observing_inputs = true);
+                    trace!("done handling RegisterUIObserveInputs message");
+                }
+                Some(HookMessage::UnregisterUIObserveInputs) => {
+                    trace!("handling UnregisterUIObserveInputs message");
+                    debug!("unregistered to send inputs to UI");
+                    with_hook_local(|hook_local| hook_local.ui_observing_inputs = false);
+                    trace!("done handling UnregisterUIObserveInputs message");
+                }
+                Some(HookMessage::Pin) => {
+                    trace!("handling Pin message");
+                    let Foundation::WPARAM(raw_usize) = lpmsg.wParam;
+                    let raw = raw_usize as *mut Option<String>;
+                    let pinned_boxed = Box::from_raw(raw);
+                    let pinned = *pinned_boxed;
+
+                    with_hook_local(|hook_local| hook_local.set_pinned_profile(pinned));
+                    trace!("done handling Pin message");
+                }
+                Some(HookMessage::SetPaused) => {
+                    trace!("handling SetPaused message");
+                    let paused = lpmsg.wParam.0 != 0;
+
+                    with_hook_local(|hook_local| hook_local.set_paused(paused));
+                    trace!("done handling SetPaused message");
+                }
+                Some(HookMessage::Status) => {
+                    trace!("handling Status message");
+                    let Foundation::WPARAM(raw_usize) = lpmsg.wParam;
+                    let raw = raw_usize as *mut oneshot::Sender<events::Status>;
+                    let sender_boxed = Box::from_raw(raw);
+                    let sender = *sender_boxed;
+
+                    let status = with_hook_local(|hook_local| hook_local.status());
+                    // Whoever asked may have given up waiting. That's OK.
+                    let _ = sender.send(status);
+                    trace!("done handling Status message");
+                }
+                Some(HookMessage::Stats) => {
+                    trace!("handling Stats message");
+                    let Foundation::WPARAM(raw_usize) = lpmsg.wParam;
+                    let raw = raw_usize as *mut oneshot::Sender<stats::ButtonStats>;
+                    let sender_boxed = Box::from_raw(raw);
+                    let sender = *sender_boxed;
+
+                    let stats = with_hook_local(|hook_local| hook_local.stats.clone());
+                    let _ = sender.send(stats);
+                    trace!("done handling Stats message");
+                }
+                Some(HookMessage::ResetStats) => {
+                    trace!("handling ResetStats message");
+                    with_hook_local(|hook_local| hook_local.stats.reset());
+                    trace!("done handling ResetStats message");
+                }
+                Some(HookMessage::Hotkey) => {
+                    trace!("handling Hotkey message");
+                    if lpmsg.wParam.0 == PIN_HOTKEY_ID as usize {
+                        with_hook_local(|hook_local| hook_local.toggle_pinned_profile());
+                    }
+                    trace!("done handling Hotkey message");
+                }
+                None => {
+                    trace!("got unknown message; treat as normal");
+                    let _ = WM::TranslateMessage(&lpmsg);
+                    let _ = WM::DispatchMessageA(&lpmsg);
+                    trace!("done treating as normal");
+                }
+            }
+        }
+    }
+
+    let mut running = RUNNING.lock().unwrap();
+    *running = false;
+    if let Err(e) = unsafe { WM::KillTimer(None, timer_id) } {
+        warn!(?e, "error killing timer");
+    }
+    if let Err(e) = WindowsDesktop.clip_cursor(None) {
+        warn!(?e, "error removing the cursor clip");
+    };
+    debug!("exiting hook thread");
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    num_derive::FromPrimitive,
+    num_derive::ToPrimitive,
+)]
+#[repr(u32)]
+enum HookMessage {
+    TimerExpire = WindowsAndMessaging::WM_TIMER,
+    Hotkey = WindowsAndMessaging::WM_HOTKEY,
+    Quit = WindowsAndMessaging::WM_APP,
+    Update = WindowsAndMessaging::WM_APP + 1,
+    CheckForeground = WindowsAndMessaging::WM_APP + 2,
+    RegisterUIObserveInputs = WindowsAndMessaging::WM_APP + 3,
+    UnregisterUIObserveInputs = WindowsAndMessaging::WM_APP + 4,
+    Pin = WindowsAndMessaging::WM_APP + 5,
+    SetPaused = WindowsAndMessaging::WM_APP + 6,
+    Status = WindowsAndMessaging::WM_APP + 7,
+    Stats = WindowsAndMessaging::WM_APP + 8,
+    ResetStats = WindowsAndMessaging::WM_APP + 9,
+}
+
+#[derive(Debug, Clone)]
+pub struct HookthreadProxy {
+    thread_id: u32,
+    config: runtime_config::SharedConfig,
+}
+
+impl HookthreadProxy {
+    pub fn quit(&self) {
+        debug!("telling hookthread to quit");
+        if let Err(e) = self.post(HookMessage::Quit, 0) {
+            warn!("{e}");
+        }
+    }
+    // Hand the hook thread a new configuration without waiting for it. It is prepared here, on the
+    // calling thread, and swapped in. The hook thread installs it when it gets to the message and
+    // answers on the receiver whether that worked. However late that answer comes, this is the
+    // configuration that runs, unless a newer one is stored first.
+    pub fn update(&self, config: config::Config) -> Result<oneshot::Receiver<UpdateResult>> {
+        let runtime_config = Arc::new(RuntimeConfig::new(config));
+        let previous = self.config.swap(runtime_config);
+        let (sender, receiver) = oneshot::channel();
+        if let Err(e) = self.post_boxed(HookMessage::Update, sender) {
+            // The hook thread never heard about it, so it should not pick it up later either.
+            self.config.store(previous);
+            return Err(e);
+        }
+        Ok(receiver)
+    }
+    // Pin a profile by name, keeping it active regardless of the foreground window.
+    // None unpins.
+    pub fn pin(&self, profile: Option<String>) -> Result<()> {
+        self.post_boxed(HookMessage::Pin, profile)
+    }
+    // Stop remapping without quitting. Buttons held at the time are still released as remapped.
+    pub fn set_paused(&self, paused: bool) {
+        if let Err(e) = self.post(HookMessage::SetPaused, paused as usize) {
+            warn!("{e}");
+        }
+    }
+    // Ask the hook thread what it is doing. None if it does not answer in time.
+    pub fn status(&self) -> Option<events::Status> {
+        const STATUS_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+
+        let (sender, receiver) = oneshot::channel();
+        if let Err(e) = self.post_boxed(HookMessage::Status, sender) {
+            warn!("{e}");
+            return None;
+        }
+        receiver.recv_timeout(STATUS_TIMEOUT).ok()
+    }
+    // Ask for a copy of the button statistics without waiting for it. The copy arrives on the
+    // receiver once the hook thread gets to the message. None if the message could not be sent.
+    pub fn request_stats(&self) -> Option<oneshot::Receiver<stats::ButtonStats>> {
+        let (sender, receiver) = oneshot::channel();
+        if let Err(e) = self.post_boxed(HookMessage::Stats, sender) {
+            warn!("{e}");
+            return None;
+        }
+        Some(receiver)
+    }
+    pub fn reset_stats(&self) {
+        if let Err(e) = self.post(HookMessage::ResetStats, 0) {
+            warn!("{e}");
+        }
+    }
+    pub fn check_foreground(&self) {
+        if let Err(e) = self.post(HookMessage::CheckForeground, 0) {
+            warn!("{e}");
+        }
+    }
+    // The UI thread calls this when it wants to be notified of inputs.
+    pub fn register_observe_inputs(&self) {
+        if let Err(e) = self.post(HookMessage::RegisterUIObserveInputs, 0) {
+            warn!("{e}");
+        }
+    }
+    // The UI thread calls this when it is done being notified of inputs.
+    pub fn unregister_observe_inputs(&self) {
+        if let Err(e) = self.post(HookMessage::UnregisterUIObserveInputs, 0) {
+            warn!("{e}");
+        }
+    }
+
+    fn post(&self, message: HookMessage, wparam: usize) -> Result<()> {
+        use num_traits::ToPrimitive;
+        unsafe {
+            WindowsAndMessaging::PostThreadMessageW(
+                self.thread_id,
+                message
+                    .to_u32()
+                    .expect("msg should always be representable as u32"),
+                Foundation::WPARAM(wparam),
+                Foundation::LPARAM(0),
+            )
+        }
+        .map_err(Error::CannotSend)
+    }
+    // Post a message with a value for the hook thread in WPARAM. The hook thread takes ownership of
+    // it, unless the message could not be posted.
+    fn post_boxed<T>(&self, message: HookMessage, value: T) -> Result<()> {
+        let raw = Box::into_raw(Box::new(value));
+        let result = self.post(message, raw as usize);
+        if result.is_err() {
+            drop(unsafe { Box::from_raw(raw) });
+        }
+        result
+    }
+}
+
+// -------------------- Errors --------------------
+
+#[derive(Debug, Error)]
+pub enum Error {
+    // Usually because the hook thread is gone, or its message queue is full.
+    #[error("could not send a message to the hook thread")]
+    CannotSend(#[source] windows::core::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,534 @@
+// Copyright 2025 Jordan Johnson
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! A control channel other programs can use to talk to a running Reemap. It is a named pipe on
+//! Windows and a Unix socket elsewhere, and only reachable from this computer. The Unix socket is a
+//! file in the user's runtime directory that only they may connect to.
+//!
+//! The protocol is JSON, one message per line. The client sends a Request and gets one Response
+//! back, and can keep sending requests on the same connection. After a "subscribe" request, the
+//! server answers "ok" and from then on sends one Event per line until the client hangs up. Input
+//! events are only sent if the request asks for them with `"inputs":true`. A client that stops
+//! reading is disconnected once it falls too far behind.
+//!
+//! ```text
+//! > {"request":"status"}
+//! < {"response":"status","profiles":[{"name":"Game","layers":[]}],"all_profiles":["Game"],...}
+//! > {"request":"pin","profile":"Game"}
+//! < {"response":"ok"}
+//! ```
+
+#[cfg(windows)]
+use crate::events;
+use crate::events::{Event, Status};
+#[cfg(windows)]
+use crate::gui;
+#[cfg(windows)]
+use crate::hooks;
+#[cfg(not(windows))]
+use interprocess::local_socket::GenericFilePath;
+#[cfg(windows)]
+use interprocess::local_socket::GenericNamespaced;
+use interprocess::local_socket::prelude::*;
+use interprocess::local_socket::{ListenerOptions, Name, Stream};
+#[cfg(not(windows))]
+use interprocess::os::unix::local_socket::ListenerOptionsExt;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::sync::{Arc, mpsc};
+use thiserror::Error;
+use tracing::{debug, info, warn};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "request", rename_all = "snake_case")]
+pub enum Request {
+    Status,
+    // Keep a profile active no matter which window is in focus, or go back to following the
+    // window with None.
+    Pin {
+        profile: Option<String>,
+    },
+    Pause,
+    Resume,
+    // Load the configuration file again.
+    Reload,
+    // Bring the window to the front.
+    Show,
+    Subscribe {
+        #[serde(default)]
+        inputs: bool,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "response", rename_all = "snake_case")]
+pub enum Response {
+    Ok,
+    Status(Status),
+    Error { message: String },
+}
+
+/// Everything the control channel can do to Reemap. The server only talks to Reemap through this,
+/// so it can be tested against a stand-in.
+pub trait Control: Send + Sync + 'static {
+    // None if Reemap did not answer.
+    fn status(&self) -> Option<Status>;
+    fn pin(&self, profile: Option<String>) -> std::result::Result<(), String>;
+    fn set_paused(&self, paused: bool);
+    fn reload(&self);
+    fn show(&self);
+    fn subscribe(&self, inputs: bool) -> mpsc::Receiver<Event>;
+}
+
+/// The real thing: controls the hook thread and UI thread of this process.
+#[cfg(windows)]
+pub struct Controls {
+    pub hookthread_proxy: hooks::HookthreadProxy,
+    pub ui_proxy: winit::event_loop::EventLoopProxy<gui::ReemapGuiEvent>,
+    pub events: events::EventBus,
+}
+
+#[cfg(windows)]
+impl Control for Controls {
+    fn status(&self) -> Option<Status> {
+        self.hookthread_proxy.status()
+    }
+    fn pin(&self, profile: Option<String>) -> std::result::Result<(), String> {
+        if let Some(name) = &profile {
+            let exists = self
+                .status()
+                .is_some_and(|status| status.all_profiles.contains(name));
+            if !exists {
+                return Err(format!("no profile is named \"{name}\""));
+            }
+        }
+        self.hookthread_proxy
+            .pin(profile)
+            .map_err(|e| e.to_string())
+    }
+    fn set_paused(&self, paused: bool) {
+        self.hookthread_proxy.set_paused(paused);
+    }
+    fn reload(&self) {
+        // The UI thread already knows how to reload the file, and how to ask first if that would
+        // throw away unsaved changes.
+        if self
+            .ui_proxy
+            .send_event(gui::ReemapGuiEvent::ConfigFileChanged)
+            .is_err()
+        {
+            warn!("failed to send message to UI thread");
+        }
+    }
+    fn show(&self) {
+        if self
+            .ui_proxy
+            .send_event(gui::ReemapGuiEvent::ShowWindow)
+            .is_err()
+        {
+            warn!("failed to send message to UI thread");
+        }
+    }
+    fn subscribe(&self, inputs: bool) -> mpsc::Receiver<Event> {
+        self.events.subscribe(inputs)
+    }
+}
+
+// One name per user, so two people signed in to the same computer each get their own.
+fn socket_name() -> String {
+    let user = std::env::var("USERNAME")
+        .or_else(|_| std::env::var("USER"))
+        .unwrap_or_default();
+    format!("reemap-control-{user}")
+}
+
+#[cfg(windows)]
+fn to_name(name: String) -> Result<Name<'static>> {
+    name.to_ns_name::<GenericNamespaced>()
+        .map_err(Error::InvalidName)
+}
+
+// Not an abstract socket, which any user could connect to. A socket file can be kept to its owner.
+// The runtime directory is the user's alone; the temporary directory is shared, but the socket
+// itself is still only for the user (see listener_options).
+#[cfg(not(windows))]
+fn to_name(name: String) -> Result<Name<'static>> {
+    socket_path(&name)
+        .to_fs_name::<GenericFilePath>()
+        .map_err(Error::InvalidName)
+}
+
+#[cfg(not(windows))]
+fn socket_path(name: &str) -> std::path::PathBuf {
+    let dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(std::path::PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .unwrap_or_else(std::env::temp_dir);
+    dir.join(format!("{name}.sock"))
+}
+
+#[cfg(windows)]
+fn listener_options(name: Name<'static>) -> ListenerOptions<'static> {
+    ListenerOptions::new().name(name)
+}
+
+// Only the user may connect. Only one Reemap runs at a time, so a socket file already there was
+// left behind by one that did not exit cleanly, and is replaced.
+#[cfg(not(windows))]
+fn listener_options(name: Name<'static>) -> ListenerOptions<'static> {
+    ListenerOptions::new()
+        .name(name)
+        .mode(0o600)
+        .try_overwrite(true)
+}
+
+// -------------------- Server --------------------
+
+/// Start listening for clients.
+///
+/// Unlike Reemap's other threads, these are not scoped. Clients can keep a connection open as long
+/// as they like, and nothing they hold needs cleaning up, so they simply end with the process.
+pub fn spawn(control: impl Control) -> Result<()> {
+    spawn_named(socket_name(), control)
+}
+
+fn spawn_named(name: String, control: impl Control) -> Result<()> {
+    let listener = listener_options(to_name(name)?)
+        .create_sync()
+        .map_err(Error::CannotListen)?;
+    let control = Arc::new(control);
+    std::thread::spawn(move || {
+        debug!("listening for control clients");
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let control = control.clone();
+                    std::thread::spawn(move || serve(stream, control.as_ref()));
+                }
+                Err(e) => warn!("failed to accept a control client: {e}"),
+            }
+        }
+    });
+    Ok(())
+}
+
+fn serve(stream: Stream, control: &impl Control) {
+    debug!("control client connected");
+    let mut stream = BufReader::new(stream);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        match stream.read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) => (),
+            Err(e) => {
+                debug!("control client went away: {e}");
+                break;
+            }
+        }
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(Request::Subscribe { inputs }) => {
+                // This connection only carries events from now on.
+                let events = control.subscribe(inputs);
+                if send(stream.get_mut(), &Response::Ok).is_err() {
+                    break;
+                }
+                for event in events {
+                    if send(stream.get_mut(), &event).is_err() {
+                        break;
+                    }
+                }
+                break;
+            }
+            Ok(request) => handle(request, control),
+            Err(e) => Response::Error {
+                message: format!("not a request Reemap understands: {e}"),
+            },
+        };
+        if send(stream.get_mut(), &response).is_err() {
+            break;
+        }
+    }
+    debug!("control client disconnected");
+}
+
+fn handle(request: Request, control: &impl Control) -> Response {
+    info!(?request, "control request");
+    match request {
+        Request::Status => match control.status() {
+            Some(status) => Response::Status(status),
+            None => Response::Error {
+                message: String::from("Reemap did not answer"),
+            },
+        },
+        Request::Pin { profile } => match control.pin(profile) {
+            Ok(()) => Response::Ok,
+            Err(message) => Response::Error { message },
+        },
+        Request::Pause => {
+            control.set_paused(true);
+            Response::Ok
+        }
+        Request::Resume => {
+            control.set_paused(false);
+            Response::Ok
+        }
+        Request::Reload => {
+            control.reload();
+            Response::Ok
+        }
+        Request::Show => {
+            control.show();
+            Response::Ok
+        }
+        Request::Subscribe { .. } => unreachable!("subscriptions are handled by serve"),
+    }
+}
+
+fn send(stream: &mut Stream, message: &impl Serialize) -> std::io::Result<()> {
+    let mut line = serde_json::to_string(message).expect("messages should always serialize");
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+    stream.flush()
+}
+
+// -------------------- Client --------------------
+
+/// A connection to a running Reemap.
+pub struct Client {
+    stream: BufReader<Stream>,
+}
+
+impl Client {
+    pub fn connect() -> Result<Self> {
+        Self::connect_named(socket_name())
+    }
+
+    fn connect_named(name: String) -> Result<Self> {
+        let stream = Stream::connect(to_name(name)?).map_err(Error::NotRunning)?;
+        Ok(Self {
+            stream: BufReader::new(stream),
+        })
+    }
+
+    /// Send a request and wait for the answer. An error from Reemap comes back as Err.
+    pub fn request(&mut self, request: &Request) -> Result<Response> {
+        send(self.stream.get_mut(), request).map_err(Error::Disconnected)?;
+        match self.receive::<Response>()? {
+            Response::Error { message } => Err(Error::Refused(message)),
+            response => Ok(response),
+        }
+    }
+
+    /// Subscribe, then wait for events. The iterator ends when Reemap closes.
+    pub fn events(mut self, inputs: bool) -> Result<impl Iterator<Item = Result<Event>>> {
+        self.request(&Request::Subscribe { inputs })?;
+        Ok(std::iter::from_fn(move || match self.receive::<Event>() {
+            Err(Error::Disconnected(_)) => None,
+            event => Some(event),
+        }))
+    }
+
+    fn receive<T: serde::de::DeserializeOwned>(&mut self) -> Result<T> {
+        let mut line = String::new();
+        let len = self
+            .stream
+            .read_line(&mut line)
+            .map_err(Error::Disconnected)?;
+        if len == 0 {
+            return Err(Error::Disconnected(
+                std::io::ErrorKind::UnexpectedEof.into(),
+            ));
+        }
+        serde_json::from_str(&line).map_err(Error::BadMessage)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("invalid name for the control channel: {0}")]
+    InvalidName(#[source] std::io::Error),
+    #[error("could not open the control channel: {0}")]
+    CannotListen(#[source] std::io::Error),
+    #[error("could not connect to Reemap; is it running? ({0})")]
+    NotRunning(#[source] std::io::Error),
+    #[error("lost the connection to Reemap: {0}")]
+    Disconnected(#[source] std::io::Error),
+    #[error("Reemap sent something unexpected: {0}")]
+    BadMessage(#[source] serde_json::Error),
+    #[error("{0}")]
+    Refused(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events;
+    use std::sync::Mutex;
+
+    // Stands in for Reemap, so the protocol can be tested without hooks or a window.
+    #[derive(Default)]
+    struct StandIn {
+        status: Mutex<Status>,
+        reloads: Mutex<usize>,
+        events: events::EventBus,
+    }
+
+    impl Control for Arc<StandIn> {
+        fn status(&self) -> Option<Status> {
+            Some(self.status.lock().unwrap().clone())
+        }
+        fn pin(&self, profile: Option<String>) -> std::result::Result<(), String> {
+            let mut status = self.status.lock().unwrap();
+            if let Some(name) = &profile
+                && !status.all_profiles.contains(name)
+            {
+                return Err(format!("no profile is named \"{name}\""));
+            }
+            status.pinned = profile.clone();
+            self.events.publish(Event::PinChanged { profile });
+            Ok(())
+        }
+        fn set_paused(&self, paused: bool) {
+            self.status.lock().unwrap().paused = paused;
+            self.events.publish(Event::PausedChanged { paused });
+        }
+        fn reload(&self) {
+            *self.reloads.lock().unwrap() += 1;
+        }
+        fn show(&self) {}
+        fn subscribe(&self, inputs: bool) -> mpsc::Receiver<Event> {
+            self.events.subscribe(inputs)
+        }
+    }
+
+    // Each test gets its own channel so they can run in parallel.
+    fn start(test: &str) -> (Arc<StandIn>, String) {
+        let stand_in = Arc::new(StandIn::default());
+        stand_in.status.lock().unwrap().all_profiles = vec![String::from("Game")];
+        let name = format!("reemap-test-{test}-{}", std::process::id());
+        spawn_named(name.clone(), stand_in.clone()).unwrap();
+        (stand_in, name)
+    }
+
+    #[test]
+    fn requests_reach_reemap() {
+        let (stand_in, name) = start("requests");
+        let mut client = Client::connect_named(name).unwrap();
+
+        let response = client
+            .request(&Request::Pin {
+                profile: Some(String::from("Game")),
+            })
+            .unwrap();
+        assert_eq!(response, Response::Ok);
+        client.request(&Request::Pause).unwrap();
+        client.request(&Request::Reload).unwrap();
+
+        let Response::Status(status) = client.request(&Request::Status).unwrap() else {
+            panic!("expected a status");
+        };
+        assert_eq!(status.pinned.as_deref(), Some("Game"));
+        assert!(status.paused);
+        assert_eq!(*stand_in.reloads.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn errors_come_back_to_the_client() {
+        let (_stand_in, name) = start("errors");
+        let mut client = Client::connect_named(name).unwrap();
+
+        let result = client.request(&Request::Pin {
+            profile: Some(String::from("Nope")),
+        });
+        assert!(matches!(result, Err(Error::Refused(_))));
+
+        // The connection is still good after an error.
+        assert!(client.request(&Request::Status).is_ok());
+    }
+
+    #[test]
+    fn subscribers_get_events() {
+        let (_stand_in, name) = start("events");
+        let mut events = Client::connect_named(name.clone())
+            .unwrap()
+            .events(false)
+            .unwrap();
+
+        let mut client = Client::connect_named(name).unwrap();
+        client.request(&Request::Pause).unwrap();
+        client.request(&Request::Pin { profile: None }).unwrap();
+
+        assert_eq!(
+            events.next().unwrap().unwrap(),
+            Event::PausedChanged { paused: true }
+        );
+        assert_eq!(
+            events.next().unwrap().unwrap(),
+            Event::PinChanged { profile: None }
+        );
+    }
+
+    #[test]
+    fn only_input_subscribers_get_input_events() {
+        use crate::buttons::{Button, key::KeyButton};
+
+        let (stand_in, name) = start("inputs");
+        let mut with_inputs = Client::connect_named(name.clone())
+            .unwrap()
+            .events(true)
+            .unwrap();
+        let mut without_inputs = Client::connect_named(name).unwrap().events(false).unwrap();
+        assert!(stand_in.events.wants_inputs());
+
+        let button = Button::Key(KeyButton::SPACE);
+        stand_in.events.publish(Event::ButtonPressed { button });
+        stand_in
+            .events
+            .publish(Event::PausedChanged { paused: true });
+
+        assert_eq!(
+            with_inputs.next().unwrap().unwrap(),
+            Event::ButtonPressed { button }
+        );
+        assert_eq!(
+            without_inputs.next().unwrap().unwrap(),
+            Event::PausedChanged { paused: true }
+        );
+    }
+
+    #[test]
+    fn requests_are_plain_json() {
+        let request: Request =
+            serde_json::from_str(r#"{"request":"pin","profile":"Game"}"#).unwrap();
+        assert_eq!(
+            request,
+            Request::Pin {
+                profile: Some(String::from("Game"))
+            }
+        );
+        // Older clients leave out "inputs".
+        let request: Request = serde_json::from_str(r#"{"request":"subscribe"}"#).unwrap();
+        assert_eq!(request, Request::Subscribe { inputs: false });
+        assert_eq!(
+            serde_json::to_string(&Response::Ok).unwrap(),
+            r#"{"response":"ok"}"#
+        );
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn only_the_user_may_connect() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (_stand_in, name) = start("mode");
+        let metadata = std::fs::metadata(socket_path(&name)).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+
+        // A socket left behind by a Reemap that crashed does not stop the next one.
+        spawn_named(name.clone(), Arc::new(StandIn::default())).unwrap();
+        assert!(Client::connect_named(name).is_ok());
+    }
+}
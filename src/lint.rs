@@ -0,0 +1,479 @@
+// Copyright 2025 Jordan Johnson
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Looks over a configuration for mistakes that are easy to make and hard to notice.
+//!
+//! None of these stop Reemap from running. They are shown in the UI next to the profile or layer
+//! they concern, and printed by `reemap config check`.
+
+use crate::buttons::{Button, HoldButton};
+use crate::config::{
+    self, BaseRemapPolicy, Config, ProfileCondition, REMAP_SMALLVEC_LEN, RemapPolicy,
+};
+use crate::tables::{HiddenRemap, ProfileTables};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub profile_idx: usize,
+    pub profile_name: String,
+    // The layer this warning is about, if it is about a layer rather than the profile.
+    pub layer: Option<(usize, String)>,
+    pub problem: Problem,
+}
+
+impl Warning {
+    pub fn is_for_profile(&self, profile_idx: usize) -> bool {
+        self.profile_idx == profile_idx && self.layer.is_none()
+    }
+
+    pub fn is_for_layer(&self, profile_idx: usize, layer_idx: usize) -> bool {
+        self.profile_idx == profile_idx
+            && self
+                .layer
+                .as_ref()
+                .is_some_and(|(idx, _)| *idx == layer_idx)
+    }
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "profile \"{}\"", self.profile_name)?;
+        if let Some((_, layer_name)) = &self.layer {
+            write!(f, ", layer \"{layer_name}\"")?;
+        }
+        write!(f, ": {}", self.problem)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    // A remap with nothing to remap to. The UI shows these as "(invalid)".
+    EmptyRemap {
+        button: Button,
+    },
+    // A remap to more buttons than the hooks have room for, so sending it allocates.
+    LongRemap {
+        button: Button,
+        len: usize,
+    },
+    // A layer with no condition buttons can never turn on.
+    EmptyCondition,
+    // The base layer remaps one of the layer's condition buttons, so holding it for the layer also
+    // sends the remap.
+    ConditionRemappedInBase {
+        button: HoldButton,
+        output: config::Output,
+    },
+    // A layer that comes first is on whenever this one is, and has its own policy for the button.
+    HiddenRemap {
+        button: Button,
+        by_layer: String,
+    },
+    // The same, for every button the layer has a policy for.
+    LayerNeverUsed,
+    // An earlier profile has exactly the same condition.
+    DuplicateCondition {
+        profile: String,
+    },
+    // An earlier profile matches every window this one does.
+    Shadowed {
+        profile: String,
+    },
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::EmptyRemap { button } => {
+                write!(
+                    f,
+                    "{button} is remapped to nothing; pick an output or suppress it"
+                )
+            }
+            Self::LongRemap { button, len } => {
+                write!(
+                    f,
+                    "{button} is remapped to {len} buttons; remaps to more than \
+                    {REMAP_SMALLVEC_LEN} can be slower to send"
+                )
+            }
+            Self::EmptyCondition => {
+                write!(
+                    f,
+                    "the layer has no condition buttons, so it can never turn on"
+                )
+            }
+            Self::ConditionRemappedInBase { button, output } => {
+                let output: Vec<String> = output.iter().map(|btn| btn.to_string()).collect();
+                write!(
+                    f,
+                    "{button} turns on this layer, but the base layer also remaps it to {}",
+                    output.join(", ")
+                )
+            }
+            Self::HiddenRemap { button, by_layer } => write!(
+                f,
+                "{button} is never handled by this layer; layer \"{by_layer}\" comes first, is on \
+                whenever this one is, and handles it instead"
+            ),
+            Self::LayerNeverUsed => write!(
+                f,
+                "every button this layer handles is handled first by a layer that is on whenever \
+                this one is, so it never does anything"
+            ),
+            Self::DuplicateCondition { profile } => write!(
+                f,
+                "profile \"{profile}\" comes first and has the same condition, so this profile \
+                is never used"
+            ),
+            Self::Shadowed { profile } => write!(
+                f,
+                "profile \"{profile}\" comes first and matches every window this profile does, \
+                so this profile is never used"
+            ),
+        }
+    }
+}
+
+pub fn check(config: &Config) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+
+    for (profile_idx, profile) in config.profiles.iter().enumerate() {
+        let profile_warning = |problem| Warning {
+            profile_idx,
+            profile_name: profile.name.clone(),
+            layer: None,
+            problem,
+        };
+
+        for (button, policy) in profile.base.policy.iter() {
+            if let BaseRemapPolicy::Remap(output) = policy
+                && let Some(problem) = output_problem(button, output)
+            {
+                warnings.push(profile_warning(problem));
+            }
+        }
+
+        // Only enabled, window-matched profiles compete for the top spot. Always profiles are
+        // stacked underneath instead, so they never hide each other.
+        if profile.enabled && profile.condition != ProfileCondition::Always {
+            let earlier = config.profiles[..profile_idx].iter().find(|earlier| {
+                earlier.enabled
+                    && earlier.condition != ProfileCondition::Always
+                    && covers(&earlier.condition, &profile.condition)
+            });
+            if let Some(earlier) = earlier {
+                let problem = if earlier.condition == profile.condition {
+                    Problem::DuplicateCondition {
+                        profile: earlier.name.clone(),
+                    }
+                } else {
+                    Problem::Shadowed {
+                        profile: earlier.name.clone(),
+                    }
+                };
+                warnings.push(profile_warning(problem));
+            }
+        }
+
+        // Layers whose policies are hidden by others fall out of compiling the profile's tables.
+        let hidden_remaps = ProfileTables::new(profile).hidden_remaps(profile);
+
+        for (layer_idx, layer) in profile.layers.iter().enumerate() {
+            let layer_warning = |problem| Warning {
+                profile_idx,
+                profile_name: profile.name.clone(),
+                layer: Some((layer_idx, layer.name.clone())),
+                problem,
+            };
+
+            if layer.condition.is_empty() {
+                warnings.push(layer_warning(Problem::EmptyCondition));
+            }
+
+            for &hold_button in layer.condition.iter() {
+                let button = Button::from(hold_button);
+                // The layer turns on before the press is remapped, so its own policy wins if it
+                // has one.
+                if layer.policy[button] != RemapPolicy::Defer {
+                    continue;
+                }
+                if let BaseRemapPolicy::Remap(output) = &profile.base.policy[button]
+                    && !output.is_empty()
+                {
+                    warnings.push(layer_warning(Problem::ConditionRemappedInBase {
+                        button: hold_button,
+                        output: output.clone(),
+                    }));
+                }
+            }
+
+            for (button, policy) in layer.policy.iter() {
+                if let RemapPolicy::Remap(output) = policy
+                    && let Some(problem) = output_problem(button, output)
+                {
+                    warnings.push(layer_warning(problem));
+                }
+            }
+
+            let hidden: Vec<&HiddenRemap> = hidden_remaps
+                .iter()
+                .filter(|hidden| hidden.layer_idx == layer_idx)
+                .collect();
+            let policies = layer
+                .policy
+                .values()
+                .filter(|policy| **policy != RemapPolicy::Defer)
+                .count();
+            if !hidden.is_empty() && hidden.len() == policies {
+                warnings.push(layer_warning(Problem::LayerNeverUsed));
+            } else {
+                for hidden in hidden {
+                    warnings.push(layer_warning(Problem::HiddenRemap {
+                        button: hidden.button,
+                        by_layer: profile.layers[hidden.hidden_by].name.clone(),
+                    }));
+                }
+            }
+        }
+    }
+
+    warnings
+}
+
+fn output_problem(button: Button, output: &config::Output) -> Option<Problem> {
+    if output.is_empty() {
+        Some(Problem::EmptyRemap { button })
+    } else if output.len() > REMAP_SMALLVEC_LEN {
+        Some(Problem::LongRemap {
+            button,
+            len: output.len(),
+        })
+    } else {
+        None
+    }
+}
+
+// Whether every window matching `later` also matches `earlier`. This errs on the side of saying no;
+// it only needs to catch the obvious cases.
+fn covers(earlier: &ProfileCondition, later: &ProfileCondition) -> bool {
+    use ProfileCondition as PC;
+    if earlier == later {
+        return true;
+    }
+    match (earlier, later) {
+        (PC::All(conditions), _) => conditions.iter().all(|c| covers(c, later)),
+        (_, PC::Any(conditions)) => conditions.iter().all(|c| covers(earlier, c)),
+        (PC::Any(conditions), _) => conditions.iter().any(|c| covers(c, later)),
+        (_, PC::All(conditions)) => conditions.iter().any(|c| covers(earlier, c)),
+        (
+            PC::Title { title },
+            PC::TitleAndProcess {
+                title: later_title, ..
+            },
+        ) => title == later_title,
+        (
+            PC::Process { process },
+            PC::TitleAndProcess {
+                process: later_process,
+                ..
+            },
+        ) => process == later_process,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buttons::key::KeyButton as K;
+    use crate::config::{Layer, LayerType, Profile};
+    use smallvec::smallvec;
+
+    fn process(process: &str) -> ProfileCondition {
+        ProfileCondition::Process {
+            process: process.to_string(),
+        }
+    }
+
+    fn title(title: &str) -> ProfileCondition {
+        ProfileCondition::Title {
+            title: title.to_string(),
+        }
+    }
+
+    fn modifier(name: &str, condition: &[K], remaps: &[(K, K)]) -> Layer {
+        let mut layer = Layer {
+            name: name.to_string(),
+            layer_type: LayerType::Modifier,
+            condition: condition.iter().map(|&key| key.into()).collect(),
+            ..Default::default()
+        };
+        for &(from, to) in remaps {
+            layer.policy[Button::from(from)] = RemapPolicy::Remap(smallvec![to.into()]);
+        }
+        layer
+    }
+
+    fn problems(config: &Config) -> Vec<(Option<usize>, Problem)> {
+        check(config)
+            .into_iter()
+            .map(|warning| (warning.layer.map(|(idx, _)| idx), warning.problem))
+            .collect()
+    }
+
+    #[test]
+    fn remaps_and_layers_are_checked() {
+        let mut profile = Profile {
+            condition: process("game.exe"),
+            layers: vec![
+                modifier("Empty", &[], &[]),
+                modifier("Ctrl", &[K::LCONTROL], &[(K::A, K::B)]),
+                modifier("Ctrl Shift", &[K::LCONTROL, K::LSHIFT], &[(K::A, K::C)]),
+                modifier(
+                    "Ctrl Alt",
+                    &[K::LCONTROL, K::LMENU],
+                    &[(K::A, K::C), (K::D, K::E)],
+                ),
+                modifier("Caps", &[K::CAPITAL], &[]),
+            ],
+            ..Default::default()
+        };
+        let long: config::Output = (0..=REMAP_SMALLVEC_LEN).map(|_| K::X.into()).collect();
+        profile.base.policy[Button::from(K::F1)] = BaseRemapPolicy::Remap(smallvec![]);
+        profile.base.policy[Button::from(K::F2)] = BaseRemapPolicy::Remap(long);
+        profile.base.policy[Button::from(K::CAPITAL)] =
+            BaseRemapPolicy::Remap(smallvec![K::ESCAPE.into()]);
+        profile.layers[4].policy[Button::from(K::F3)] = RemapPolicy::Remap(smallvec![]);
+        let config = Config {
+            profiles: vec![profile],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            problems(&config),
+            [
+                (
+                    None,
+                    Problem::EmptyRemap {
+                        button: K::F1.into()
+                    }
+                ),
+                (
+                    None,
+                    Problem::LongRemap {
+                        button: K::F2.into(),
+                        len: REMAP_SMALLVEC_LEN + 1,
+                    }
+                ),
+                (Some(0), Problem::EmptyCondition),
+                (Some(2), Problem::LayerNeverUsed),
+                (
+                    Some(3),
+                    Problem::HiddenRemap {
+                        button: K::A.into(),
+                        by_layer: String::from("Ctrl"),
+                    }
+                ),
+                (
+                    Some(4),
+                    Problem::ConditionRemappedInBase {
+                        button: K::CAPITAL.into(),
+                        output: smallvec![K::ESCAPE.into()],
+                    }
+                ),
+                (
+                    Some(4),
+                    Problem::EmptyRemap {
+                        button: K::F3.into()
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn earlier_profiles_that_match_first_are_reported() {
+        let profile = |name: &str, condition| Profile {
+            name: name.to_string(),
+            condition,
+            ..Default::default()
+        };
+        let mut config = Config {
+            profiles: vec![
+                profile("Game", process("game.exe")),
+                profile("Game again", process("game.exe")),
+                profile(
+                    "Game menu",
+                    ProfileCondition::TitleAndProcess {
+                        title: String::from("Menu"),
+                        process: String::from("game.exe"),
+                    },
+                ),
+                profile("Everywhere", ProfileCondition::Always),
+                profile("Everywhere too", ProfileCondition::Always),
+                profile("Editor", process("editor.exe")),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            problems(&config),
+            [
+                (
+                    None,
+                    Problem::DuplicateCondition {
+                        profile: String::from("Game"),
+                    }
+                ),
+                (
+                    None,
+                    Problem::Shadowed {
+                        profile: String::from("Game"),
+                    }
+                ),
+            ]
+        );
+
+        // Disabled profiles hide nothing.
+        config.profiles[0].enabled = false;
+        assert_eq!(
+            problems(&config),
+            [(
+                None,
+                Problem::Shadowed {
+                    profile: String::from("Game again"),
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn conditions_cover_only_what_they_surely_match() {
+        let game = process("game.exe");
+        let menu = ProfileCondition::TitleAndProcess {
+            title: String::from("Menu"),
+            process: String::from("game.exe"),
+        };
+        assert!(covers(&game, &game));
+        assert!(covers(&game, &menu));
+        assert!(covers(&title("Menu"), &menu));
+        assert!(!covers(&menu, &game));
+        assert!(!covers(&game, &process("editor.exe")));
+
+        let either = ProfileCondition::Any(vec![game.clone(), process("editor.exe")]);
+        assert!(covers(&either, &game));
+        assert!(!covers(&game, &either));
+        assert!(covers(
+            &either,
+            &ProfileCondition::Any(vec![menu.clone(), process("editor.exe")])
+        ));
+
+        let both = ProfileCondition::All(vec![game.clone(), title("Menu")]);
+        assert!(covers(&game, &both));
+        assert!(!covers(&both, &game));
+        assert!(covers(
+            &both,
+            &ProfileCondition::All(vec![menu, title("Menu")])
+        ));
+    }
+}
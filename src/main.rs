@@ -2,20 +2,46 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+// Outside Windows there is no UI, so much of the rest is never reached there.
+#![cfg_attr(not(windows), allow(dead_code))]
 
 mod buttons;
+mod cli;
 mod config;
+mod config_file;
+mod config_formats;
+mod config_migrations;
+mod config_salvage;
+#[cfg(windows)]
+mod config_watcher;
+mod events;
+#[cfg(windows)]
 mod gui;
 mod hooks;
+mod ipc;
+mod lint;
+mod overlay;
+mod platform;
+#[cfg(windows)]
 mod query_windows;
+#[cfg(windows)]
 mod registry;
+mod stats;
+mod tables;
+#[cfg(windows)]
 mod unique;
 
 use clap::Parser;
-use etcetera::BaseStrategy;
-use tracing::{error, info, instrument, warn};
+#[cfg(windows)]
+use std::sync::Arc;
+#[cfg(windows)]
+use tracing::{error, warn};
+use tracing::{info, instrument};
 
+#[cfg(windows)]
 use crate::gui::ReemapGuiEvent;
+#[cfg(windows)]
+use crate::platform::{Autostart, SingleInstance};
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
@@ -25,35 +51,36 @@ struct Args {
 
     #[clap(long, short, action, help = "Start minimized to the tray")]
     background: bool,
+
+    #[clap(
+        long,
+        value_name = "PROFILE",
+        help = "Keep a profile active regardless of the window in focus"
+    )]
+    pin: Option<String>,
+
+    #[clap(
+        long,
+        value_name = "FILE",
+        help = "Write the configuration to a file and exit"
+    )]
+    export_config: Option<std::path::PathBuf>,
+
+    #[clap(
+        long,
+        value_enum,
+        help = "Format for --export-config [default: guessed from the file extension, else RON]"
+    )]
+    format: Option<config_formats::Format>,
+
+    #[command(subcommand)]
+    command: Option<cli::Command>,
 }
 
 #[instrument]
 fn main() {
     tracing_subscriber::fmt::init();
 
-    fn display_error(text: &str, ctx: impl std::fmt::Display) {
-        let body_text = format!("{text}\n\n{ctx}");
-        native_dialog::DialogBuilder::message()
-            .set_level(native_dialog::MessageLevel::Error)
-            .set_title("Error opening Reemap")
-            .set_text(&body_text)
-            .alert()
-            .show()
-            .unwrap();
-        error!("error opening Reemap: {}", &body_text);
-    }
-
-    fn display_error_no_ctx(text: &str) {
-        native_dialog::DialogBuilder::message()
-            .set_level(native_dialog::MessageLevel::Error)
-            .set_title("Error opening Reemap")
-            .set_text(text)
-            .alert()
-            .show()
-            .unwrap();
-        error!("error opening Reemap: {}", text);
-    }
-
     /*
         Initialization sequence:
 
@@ -61,23 +88,30 @@ fn main() {
 
         2.  Check if called with the --uninstall flag. This is a special case where Reemap removes
             itself from the run-on-login entries.
+            Subcommands, like `reemap profile list` and `reemap config check`, are handled here
+            too. They do their job without the UI and exit.
 
-        3.  Check this is the only running instance of Reemap.
-
-        4.  See if %APPDATA%\Reemap exists. It usually should, except on first launch.
+        3.  See if %APPDATA%\Reemap exists. It usually should, except on first launch.
             If it doesn't exist, create the directory.
             Now, %APPDATA%\Reemap certainly exists.
 
-        5.  See if %APPDATA%\Reemap\config.ron exists. It usually should, except on first launch.
+        4.  See if %APPDATA%\Reemap\config.ron exists. It usually should, except on first launch.
             If it doesn't exist, initialize it with a brand new (default) configuration.
             Now, %APPDATA%\Reemap\config.ron certainly exists.
 
-        6.  Read %APPDATA%\Reemap\config.ron and try to parse it into a VersionedConfig struct.
-            If that fails, the configuration is probably corrupted. Rather than silently resetting
-            it, which may be a destructive action, offer the user the option to reset the
-            configuration.
+        5.  Read %APPDATA%\Reemap\config.ron and try to parse it into a VersionedConfig struct.
+            If that fails, the configuration is probably corrupted or from a newer version. Copy
+            it to config.corrupt-<timestamp>.ron first, then salvage every profile that still
+            parses and ask the user whether to continue with those.
+
+        6.  Check if called with --export-config. If so, write the configuration out and exit.
 
-        7.  Convert VersionedConfig to ConfigUI. We want two copies: one to give to the hookthread,
+        7.  Check this is the only running instance of Reemap. This comes after loading the
+            configuration so --export-config still works while Reemap is running.
+            If another instance is running, hand it --pin and bring its window up through the
+            control channel instead.
+
+        8.  Convert VersionedConfig to ConfigUI. We want two copies: one to give to the hookthread,
             and one to give to the UI thread.
     */
 
@@ -88,25 +122,93 @@ fn main() {
             "called with uninstall flag. NOTE: this feature is intended only to be used by the uninstaller."
         );
         info!("removing entry to start Reemap on login, if such an entry exists");
-        let result = registry::unregister_run_on_login();
-        if let Err(e) = result {
+        #[cfg(windows)]
+        if let Err(e) = platform::Native.set_run_on_login(false) {
             error!("could not access registry: {e}");
         }
         return;
     }
 
+    if let Some(command) = args.command {
+        cli::attach_console();
+        if !cli::run(command) {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    run_app(args);
+}
+
+// Steps 3 onwards.
+#[cfg(windows)]
+fn run_app(args: Args) {
+    fn display_error(text: &str, ctx: impl std::fmt::Display) {
+        let body_text = format!("{text}\n\n{ctx}");
+        native_dialog::DialogBuilder::message()
+            .set_level(native_dialog::MessageLevel::Error)
+            .set_title("Error opening Reemap")
+            .set_text(&body_text)
+            .alert()
+            .show()
+            .unwrap();
+        error!("error opening Reemap: {}", &body_text);
+    }
+
+    fn display_error_no_ctx(text: &str) {
+        native_dialog::DialogBuilder::message()
+            .set_level(native_dialog::MessageLevel::Error)
+            .set_title("Error opening Reemap")
+            .set_text(text)
+            .alert()
+            .show()
+            .unwrap();
+        error!("error opening Reemap: {}", text);
+    }
+
+    // Exporting only reads the configuration, so it does not need to be the only instance, and
+    // does not create or repair anything.
+    if let Some(export_path) = args.export_config {
+        let config = match cli::load(&config_file::config_path()) {
+            Ok(config) => config,
+            Err(e) => {
+                display_error("Reemap could not read the configuration file.", e);
+                return;
+            }
+        };
+        let versioned_config = config::VersionedConfig::from(config);
+        let format = args
+            .format
+            .or_else(|| config_formats::Format::from_path(&export_path))
+            .unwrap_or(config_formats::Format::Ron);
+        let config_str = match config_formats::config_to_string(&versioned_config, format) {
+            Ok(s) => s,
+            Err(e) => {
+                display_error("Reemap could not export the configuration.", e);
+                return;
+            }
+        };
+        match std::fs::write(&export_path, config_str) {
+            Ok(()) => info!("exported configuration as {format}"),
+            Err(e) => display_error("Reemap could not write the exported configuration.", e),
+        }
+        return;
+    }
+
     // Ensure this is the only running instance
-    let unique_guard = match unique::UniqueGuard::try_lock() {
+    let unique_guard = match platform::Native.try_lock() {
         Ok(guard) => guard,
         Err(_e) => {
-            display_error_no_ctx("Reemap is already running.\n\nIs it hiding in the tray?");
+            if let Err(e) = forward_to_running_instance(args.pin) {
+                warn!("could not reach the running instance: {e}");
+                display_error_no_ctx("Reemap is already running.\n\nIs it hiding in the tray?");
+            }
             return;
         }
     };
 
     // Check %APPDATA%\Reemap
-    let mut reemap_dir = etcetera::choose_base_strategy().unwrap().config_dir();
-    reemap_dir.push("Reemap");
+    let reemap_dir = config_file::reemap_dir();
     let reemap_dir_exists = match reemap_dir.try_exists() {
         Ok(exists) => exists,
         Err(e) => {
@@ -128,7 +230,7 @@ fn main() {
     }
 
     // Check %APPDATA%\Reemap\config.ron
-    let config_path = reemap_dir.join("config.ron");
+    let config_path = config_file::config_path();
     let config_file_exists = match config_path.try_exists() {
         Ok(exists) => exists,
         Err(e) => {
@@ -140,7 +242,7 @@ fn main() {
         let default_config = config::VersionedConfig::default();
         let default_config =
             ron::ser::to_string_pretty(&default_config, ron::ser::PrettyConfig::new()).unwrap();
-        match std::fs::write(&config_path, default_config) {
+        match config_file::write_atomic(&config_path, &default_config) {
             Ok(()) => (),
             Err(e) => {
                 display_error("Reemap could not create the configuration file.", e);
@@ -158,26 +260,102 @@ fn main() {
         }
     };
 
-    let versioned_config: config::VersionedConfig = match ron::from_str(&config_str) {
+    // Keep a file Reemap could not fully read before anything gets a chance to overwrite it. Says
+    // where the copy went, for the dialog.
+    let copy_aside = |why: &str| match config_file::copy_aside(&config_path, why) {
+        Ok(copy_path) => {
+            info!("copied configuration to {}", copy_path.display());
+            format!("The original file was copied to {}.", copy_path.display())
+        }
+        Err(copy_error) => {
+            error!("could not copy configuration: {copy_error}");
+            format!(
+                "Reemap could not make a copy of the original file ({copy_error}). Back up {} \
+                yourself before pressing Yes.",
+                config_path.display()
+            )
+        }
+    };
+
+    let (parsed, skipped) =
+        config::count_skipped_remaps(|| ron::from_str::<config::VersionedConfig>(&config_str));
+    let versioned_config: config::VersionedConfig = match parsed {
+        Ok(c) if skipped > 0 => {
+            warn!("skipped {skipped} remaps this version does not understand");
+            let copy_note = copy_aside("newer");
+            let proceed = native_dialog::DialogBuilder::message()
+                .set_level(native_dialog::MessageLevel::Warning)
+                .set_title("Configuration from a newer version")
+                .set_text(format!(
+                    "The configuration file has {skipped} remaps that this version of Reemap \
+                    does not understand, probably because a newer version saved it. They will be \
+                    left out.\n\n\
+                    {copy_note}\n\n\
+                    Press Yes to continue without them. Reemap will overwrite the old \
+                    configuration the next time you click \"Apply\".\n\n\
+                    Press No to cancel opening the software."
+                ))
+                .confirm()
+                .show()
+                .unwrap();
+            if !proceed {
+                info!("exiting now");
+                return;
+            }
+            c
+        }
         Ok(c) => c,
         Err(e) => {
             warn!("failed to parse config file: {e}");
-            let regenerate = native_dialog::DialogBuilder::message()
+            let copy_note = copy_aside("corrupt");
+
+            let (salvaged_config, salvage_note) = match config_salvage::salvage(&config_str) {
+                Some(salvaged) => {
+                    let recovered: Vec<&str> = salvaged
+                        .config
+                        .profiles
+                        .iter()
+                        .map(|profile| profile.name.as_str())
+                        .collect();
+                    let mut note = if recovered.is_empty() {
+                        String::from("No profiles could be recovered.")
+                    } else {
+                        format!("Recovered profiles: {}.", recovered.join(", "))
+                    };
+                    if !salvaged.lost.is_empty() {
+                        note.push_str("\n\nThese profiles could not be read and will be left out:");
+                        for lost in &salvaged.lost {
+                            warn!("could not salvage profile {lost}");
+                            note.push_str(&format!("\n    {lost}"));
+                        }
+                    }
+                    (salvaged.config, note)
+                }
+                None => (
+                    config::Config::from(config::VersionedConfig::default()),
+                    String::from("Nothing in it could be recovered."),
+                ),
+            };
+
+            let proceed = native_dialog::DialogBuilder::message()
                 .set_level(native_dialog::MessageLevel::Warning)
                 .set_title("Corrupted configuration file")
-                .set_text(
+                .set_text(format!(
                     "Reemap could not parse the configuration file. This may happen if you \
-                use a configuration file created in a newer version of the software.\n\n\
-                Press Yes to continue with the default configuration. Reemap will overwrite the \
-                old configuration the next time you click \"Apply\".\n\n\
-                Press No to cancel opening the software.",
-                )
+                    use a configuration file created in a newer version of the software.\n\n\
+                    {e}\n\n\
+                    {salvage_note}\n\n\
+                    {copy_note}\n\n\
+                    Press Yes to continue with what was recovered. Reemap will overwrite the \
+                    old configuration the next time you click \"Apply\".\n\n\
+                    Press No to cancel opening the software."
+                ))
                 .confirm()
                 .show()
                 .unwrap();
-            if regenerate {
-                info!("going forward with the default configuration");
-                config::VersionedConfig::default()
+            if proceed {
+                info!("going forward with the salvaged configuration");
+                config::VersionedConfig::from(salvaged_config)
             } else {
                 info!("exiting now");
                 return;
@@ -188,6 +366,25 @@ fn main() {
     // Update to latest version
     let config = config::Config::from(versioned_config);
 
+    // The hook thread only ever sees profiles with their inheritance already merged in.
+    let resolved_config = match config.resolve_inheritance() {
+        Ok(resolved_config) => resolved_config,
+        Err(e) => {
+            warn!("failed to resolve profile inheritance: {e}");
+            native_dialog::DialogBuilder::message()
+                .set_level(native_dialog::MessageLevel::Warning)
+                .set_title("Profile inheritance")
+                .set_text(format!(
+                    "Reemap could not resolve profile inheritance.\n\n{e}\n\n\
+                    Profiles will run without anything they inherit until this is fixed."
+                ))
+                .alert()
+                .show()
+                .unwrap();
+            config.clone()
+        }
+    };
+
     let start_visible = !args.background;
 
     // Reminder: all threads are joined at the end of a std::thread::scope
@@ -198,18 +395,109 @@ fn main() {
             .unwrap();
         let ui_proxy = event_loop.create_proxy();
 
+        // Watch for edits to the configuration file made outside Reemap.
+        let config_watcher_proxy =
+            match config_watcher::spawn_scoped(s, config_path.clone(), ui_proxy.clone()) {
+                Ok(proxy) => Some(proxy),
+                Err(e) => {
+                    warn!("{e}; changes to the configuration file will need a restart");
+                    None
+                }
+            };
+
         // Then run the hook thread, giving the UI thread proxy and also getting a proxy to the
         // hookthread at the same time.
-        let hookthread_proxy = hooks::spawn_scoped(s, config.clone(), ui_proxy);
+        let events = events::EventBus::default();
+        let hookthread_proxy = hooks::spawn_scoped(
+            s,
+            resolved_config,
+            Arc::new(ui_proxy.clone()),
+            events.clone(),
+        );
+
+        // Let other programs control this instance.
+        let controls = ipc::Controls {
+            hookthread_proxy: hookthread_proxy.clone(),
+            ui_proxy,
+            events: events.clone(),
+        };
+        if let Err(e) = ipc::spawn(controls) {
+            warn!("{e}; other programs will not be able to control Reemap");
+        }
+
+        if let Some(pin) = args.pin {
+            match config.profiles.iter().find(|profile| profile.name == pin) {
+                Some(profile) if profile.enabled => {
+                    if let Err(e) = hookthread_proxy.pin(Some(pin)) {
+                        warn!("{e}");
+                    }
+                }
+                Some(_) => warn!("cannot pin profile {pin}; it is disabled"),
+                None => warn!("cannot pin profile {pin}; no profile has that name"),
+            }
+        }
 
         // Run the GUI. It will be ran on this thread, the main thread.
-        let app = gui::reemapp::ReemApp::new(hookthread_proxy.clone(), config, config_path);
+        let app = gui::reemapp::ReemApp::new(hookthread_proxy.clone(), config, config_path, events);
         gui::run(app, event_loop, start_visible);
 
         // At this point, the GUI closed and is done running.
         // We should close Reemap, so let's stop the hookthread.
         hookthread_proxy.quit();
+        if let Some(config_watcher_proxy) = config_watcher_proxy {
+            config_watcher_proxy.quit();
+        }
     });
 
     drop(unique_guard);
 }
+
+// There is no UI on Linux yet. Reemap remaps with the configuration file as it was when it started,
+// until it is stopped.
+#[cfg(target_os = "linux")]
+fn run_app(args: Args) {
+    let config = match cli::load(&config_file::config_path()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Reemap could not load the configuration: {e}");
+            std::process::exit(1);
+        }
+    };
+    let config = match config.resolve_inheritance() {
+        Ok(resolved_config) => resolved_config,
+        Err(e) => {
+            eprintln!(
+                "Reemap could not resolve profile inheritance: {e}\n\
+                Profiles will run without anything they inherit until this is fixed."
+            );
+            config
+        }
+    };
+    if let Err(e) = hooks::evdev_capture::run(config, args.pin, events::EventBus::default()) {
+        eprintln!("Reemap stopped remapping: {e}");
+        std::process::exit(1);
+    }
+}
+
+// Remapping needs input capture, which only Windows and Linux have so far. The subcommands work
+// everywhere.
+#[cfg(not(any(windows, target_os = "linux")))]
+fn run_app(_args: Args) {
+    eprintln!(
+        "Reemap cannot remap inputs on this platform yet. Its subcommands still work; see `reemap --help`."
+    );
+    std::process::exit(1);
+}
+
+// Launching Reemap again brings up the one already running, pinning a profile if asked to.
+#[cfg(windows)]
+fn forward_to_running_instance(pin: Option<String>) -> ipc::Result<()> {
+    let mut client = ipc::Client::connect()?;
+    if let Some(profile) = pin {
+        client.request(&ipc::Request::Pin {
+            profile: Some(profile),
+        })?;
+    }
+    client.request(&ipc::Request::Show)?;
+    Ok(())
+}
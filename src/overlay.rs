@@ -0,0 +1,404 @@
+// Copyright 2025 Jordan Johnson
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! An input display for streaming. Serves a web page showing which buttons are held and what
+//! Reemap remapped them to, which can be added to OBS as a browser source.
+//!
+//! The page is served at http://localhost:<port>/ and gets live updates over a WebSocket at
+//! ws://localhost:<port>/ws, one JSON message per change:
+//!
+//! ```text
+//! {"type":"held","buttons":[{"button":"Space","remapped_to":["Left Click"]}]}
+//! {"type":"tap","button":"Scroll Up"}
+//! {"type":"profiles","profiles":["Game"]}
+//! ```
+//!
+//! Only pages served from here may connect to the WebSocket, since it reports every button
+//! pressed. Browsers tell where a page came from, and any other website is refused; OBS and other
+//! programs that say nothing are let in.
+//!
+//! To customize it, put an overlay.html next to the configuration file and Reemap serves that
+//! instead. The built-in page can also be restyled with OBS's custom CSS.
+
+use crate::buttons::Button;
+use crate::config_file;
+use crate::events::{self, Event};
+use serde::Serialize;
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{debug, info, warn};
+
+pub const DEFAULT_PORT: u16 = 7331;
+
+const BUILT_IN_PAGE: &str = include_str!("overlay.html");
+
+// How often idle connections check whether the server stopped.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// A page that takes longer than this to take an update is disconnected. It reconnects by itself.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Requests with longer headers than this are refused.
+const MAX_HEAD_LEN: usize = 8 * 1024;
+
+// What the page is sent. Buttons are given by name, so a custom page does not need to know how
+// Reemap identifies them.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Message {
+    Held { buttons: Vec<HeldButton> },
+    Tap { button: String },
+    Profiles { profiles: Vec<String> },
+}
+
+#[derive(Debug, Serialize)]
+struct HeldButton {
+    button: String,
+    // None if the button was not remapped. Empty if it was suppressed.
+    remapped_to: Option<Vec<String>>,
+}
+
+impl Message {
+    fn from_event(event: Event) -> Option<Self> {
+        let message = match event {
+            Event::HeldButtons { buttons } => Self::Held {
+                buttons: buttons
+                    .list()
+                    .into_iter()
+                    .map(|held| HeldButton {
+                        button: held.button.to_string(),
+                        remapped_to: held
+                            .remapped_to
+                            .map(|output| output.iter().map(Button::to_string).collect()),
+                    })
+                    .collect(),
+            },
+            // Held buttons are covered above. Taps are never held, so they get their own message.
+            Event::ButtonPressed {
+                button: button @ Button::Wheel(_),
+            } => Self::Tap {
+                button: button.to_string(),
+            },
+            Event::ProfilesChanged { profiles } => Self::Profiles { profiles },
+            _ => return None,
+        };
+        Some(message)
+    }
+}
+
+// -------------------- Server --------------------
+
+/// A running overlay server. It stops when told to quit.
+#[derive(Debug)]
+pub struct OverlayServer {
+    port: u16,
+    stop: Arc<AtomicBool>,
+}
+
+/// Start serving the overlay on this computer only.
+pub fn spawn(port: u16, events: events::EventBus) -> Result<OverlayServer> {
+    let listener =
+        TcpListener::bind((Ipv4Addr::LOCALHOST, port)).map_err(|e| Error::CannotListen(port, e))?;
+    let stop = Arc::new(AtomicBool::new(false));
+    let server_stop = stop.clone();
+    std::thread::spawn(move || {
+        info!("serving input overlay on port {port}");
+        for stream in listener.incoming() {
+            if server_stop.load(Ordering::Relaxed) {
+                break;
+            }
+            match stream {
+                Ok(stream) => {
+                    let events = events.clone();
+                    let stop = server_stop.clone();
+                    std::thread::spawn(move || {
+                        if let Err(e) = serve(stream, port, &events, &stop) {
+                            debug!("overlay connection ended: {e}");
+                        }
+                    });
+                }
+                Err(e) => warn!("failed to accept an overlay connection: {e}"),
+            }
+        }
+        info!("stopped serving input overlay");
+    });
+    Ok(OverlayServer { port, stop })
+}
+
+impl OverlayServer {
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn quit(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // The listener is blocked waiting for a connection. Give it one so it sees the flag.
+        let _ = TcpStream::connect((Ipv4Addr::LOCALHOST, self.port));
+    }
+}
+
+fn serve(
+    mut stream: TcpStream,
+    port: u16,
+    events: &events::EventBus,
+    stop: &AtomicBool,
+) -> Result<()> {
+    let head = read_head(&mut stream)?;
+    let head_str = String::from_utf8_lossy(&head);
+    let mut request_line = head_str.lines().next().unwrap_or_default().split(' ');
+    let method = request_line.next().unwrap_or_default();
+    // Browser sources sometimes add a query string. It is not used.
+    let path = request_line
+        .next()
+        .unwrap_or_default()
+        .split('?')
+        .next()
+        .unwrap_or_default();
+    let header = |wanted: &str| {
+        head_str.lines().skip(1).find_map(|line| {
+            line.split_once(':')
+                .filter(|(name, _)| name.trim().eq_ignore_ascii_case(wanted))
+                .map(|(_, value)| value.trim())
+        })
+    };
+    let upgrade = header("upgrade").is_some_and(|value| value.eq_ignore_ascii_case("websocket"));
+
+    match (method, path) {
+        ("GET", "/ws") if upgrade && !is_allowed_origin(header("origin"), port) => {
+            warn!(
+                origin = header("origin"),
+                "refused an overlay connection from another website"
+            );
+            respond(&mut stream, "403 Forbidden", "text/plain", "forbidden")
+        }
+        ("GET", "/ws") if upgrade => {
+            stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+            // The WebSocket library wants to read the request itself.
+            let replay = Replay {
+                head: std::io::Cursor::new(head),
+                stream,
+            };
+            let socket =
+                tungstenite::accept(replay).map_err(|e| Error::Handshake(e.to_string()))?;
+            stream_events(socket, events, stop)
+        }
+        ("GET", "/") => respond(&mut stream, "200 OK", "text/html; charset=utf-8", &page()),
+        _ => respond(&mut stream, "404 Not Found", "text/plain", "not found"),
+    }
+}
+
+// Pages served from here, or programs that are not browsers and send no origin.
+fn is_allowed_origin(origin: Option<&str>, port: u16) -> bool {
+    match origin {
+        None => true,
+        Some(origin) => {
+            origin == format!("http://localhost:{port}")
+                || origin == format!("http://127.0.0.1:{port}")
+        }
+    }
+}
+
+// Send updates until the page closes or the server stops. The page only ever needs the latest
+// held buttons, so while it is busy they are collapsed into one message. That way a slow page
+// falls behind by a handful of messages at most, and one that stops taking them is timed out; the
+// event bus would disconnect it before then anyway.
+fn stream_events(
+    mut socket: tungstenite::WebSocket<Replay>,
+    events: &events::EventBus,
+    stop: &AtomicBool,
+) -> Result<()> {
+    debug!("overlay page connected");
+    let receiver = events.subscribe(true);
+    // Start the page off with nothing held, rather than whatever it last showed.
+    let mut pending = vec![Message::Held {
+        buttons: Vec::new(),
+    }];
+    loop {
+        for message in pending.drain(..) {
+            let text = serde_json::to_string(&message).expect("messages should always serialize");
+            socket.send(tungstenite::Message::text(text))?;
+        }
+        if stop.load(Ordering::Relaxed) {
+            let _ = socket.close(None);
+            return Ok(());
+        }
+        match receiver.recv_timeout(POLL_INTERVAL) {
+            Ok(event) => {
+                pending = collapse(std::iter::once(event).chain(receiver.try_iter()));
+            }
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => (),
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+                debug!("overlay page fell behind");
+                return Ok(());
+            }
+        }
+    }
+}
+
+// The messages for these events, in order, with back-to-back held buttons reduced to the last.
+fn collapse(events: impl Iterator<Item = Event>) -> Vec<Message> {
+    let mut messages: Vec<Message> = Vec::new();
+    for message in events.filter_map(Message::from_event) {
+        if matches!(message, Message::Held { .. })
+            && matches!(messages.last(), Some(Message::Held { .. }))
+        {
+            messages.pop();
+        }
+        messages.push(message);
+    }
+    messages
+}
+
+fn read_head(stream: &mut TcpStream) -> Result<Vec<u8>> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut head = Vec::new();
+    let mut buf = [0; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        if head.len() > MAX_HEAD_LEN {
+            return Err(Error::BadRequest);
+        }
+        let len = stream.read(&mut buf)?;
+        if len == 0 {
+            return Err(Error::BadRequest);
+        }
+        head.extend_from_slice(&buf[..len]);
+    }
+    stream.set_read_timeout(None)?;
+    Ok(head)
+}
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &str) -> Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n\
+        Cache-Control: no-store\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()?;
+    Ok(())
+}
+
+// The user's own page, if they made one. Read on every request so edits show up on refresh.
+fn page() -> String {
+    let custom_path = config_file::reemap_dir().join("overlay.html");
+    match std::fs::read_to_string(&custom_path) {
+        Ok(page) => page,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => BUILT_IN_PAGE.to_string(),
+        Err(e) => {
+            warn!("could not read {}: {e}", custom_path.display());
+            BUILT_IN_PAGE.to_string()
+        }
+    }
+}
+
+// A stream that gives back the request head already read from it, then carries on as normal.
+#[derive(Debug)]
+struct Replay {
+    head: std::io::Cursor<Vec<u8>>,
+    stream: TcpStream,
+}
+
+impl Read for Replay {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self.head.read(buf)? {
+            0 => self.stream.read(buf),
+            len => Ok(len),
+        }
+    }
+}
+
+impl Write for Replay {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.stream.write(buf)
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("could not serve the input overlay on port {0}: {1}")]
+    CannotListen(u16, #[source] std::io::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("not an HTTP request")]
+    BadRequest,
+    #[error("WebSocket handshake failed: {0}")]
+    Handshake(String),
+    // Boxed, as it is much larger than the other errors.
+    #[error(transparent)]
+    WebSocket(Box<tungstenite::Error>),
+}
+
+impl From<tungstenite::Error> for Error {
+    fn from(value: tungstenite::Error) -> Self {
+        Self::WebSocket(Box::new(value))
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_pages_served_here_may_connect() {
+        assert!(is_allowed_origin(None, 7331));
+        assert!(is_allowed_origin(Some("http://localhost:7331"), 7331));
+        assert!(is_allowed_origin(Some("http://127.0.0.1:7331"), 7331));
+
+        assert!(!is_allowed_origin(Some("http://localhost:8080"), 7331));
+        assert!(!is_allowed_origin(Some("https://example.com"), 7331));
+        assert!(!is_allowed_origin(
+            Some("http://localhost:7331.example.com"),
+            7331
+        ));
+        assert!(!is_allowed_origin(Some("null"), 7331));
+    }
+
+    #[test]
+    fn only_the_latest_held_buttons_are_sent() {
+        use crate::buttons::HoldButton;
+        use crate::buttons::key::KeyButton;
+        use crate::buttons::wheel::MouseWheelButton;
+
+        let held = |key| {
+            let mut buttons = events::HeldButtons::default();
+            buttons.0[HoldButton::Key(key)] = Some(events::HeldAs::Unchanged);
+            Event::HeldButtons { buttons }
+        };
+        let tap = Event::ButtonPressed {
+            button: Button::Wheel(MouseWheelButton::Up),
+        };
+        let messages = collapse(
+            [
+                held(KeyButton::A),
+                held(KeyButton::B),
+                tap,
+                held(KeyButton::C),
+                Event::PausedChanged { paused: true },
+                held(KeyButton::D),
+            ]
+            .into_iter(),
+        );
+
+        let json: Vec<String> = messages
+            .iter()
+            .map(|message| serde_json::to_string(message).unwrap())
+            .collect();
+        assert_eq!(
+            json,
+            [
+                r#"{"type":"held","buttons":[{"button":"B","remapped_to":null}]}"#,
+                r#"{"type":"tap","button":"Scroll Up"}"#,
+                r#"{"type":"held","buttons":[{"button":"D","remapped_to":null}]}"#,
+            ]
+        );
+    }
+}
@@ -0,0 +1,232 @@
+// Copyright 2025 Jordan Johnson
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! The platform traits on Linux.
+//!
+//! Keyboards and mice are grabbed through evdev, so what they send only reaches Reemap (see
+//! hooks/evdev_capture.rs). Everything the rest of the desktop sees comes out of one uinput virtual
+//! device: the inputs Reemap leaves alone, and the ones it sends in place of the others.
+//!
+//! Which window is in focus, confining the cursor and starting on login all depend on the display
+//! server, and are not supported yet. Every window looks the same, so only "Always" profiles apply.
+
+use super::{CursorClip, InputCapture, InputInjector, Rect, WindowInfo, WindowQuery};
+use crate::buttons::key::KeyButton;
+use crate::buttons::mouse::MouseButton;
+use crate::buttons::{HoldButton, InputEvent, TapButton};
+use crate::config;
+use crate::hooks::INPUT_BUFFER_LEN;
+use evdev::uinput::VirtualDevice;
+use evdev::{
+    AttributeSet, Device, EventType, KeyCode, LedCode, RelativeAxisCode, SynchronizationCode,
+};
+use smallvec::SmallVec;
+use std::io;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use strum::IntoEnumIterator;
+use tracing::warn;
+
+// Reemap never grabs a device with this name, so it does not read back what it sends.
+pub const VIRTUAL_DEVICE_NAME: &str = "Reemap virtual input";
+
+#[derive(Debug)]
+pub struct LinuxDesktop {
+    output: Mutex<VirtualDevice>,
+    scroll_lock: AtomicBool,
+    pin_hotkey: Mutex<Option<config::Hotkey>>,
+}
+
+impl LinuxDesktop {
+    /// Create the virtual device. It can send every button Reemap knows, and every key and axis the
+    /// grabbed devices have, so inputs Reemap leaves alone can be passed on.
+    pub fn new(devices: &[Device]) -> io::Result<Self> {
+        let mut keys = AttributeSet::<KeyCode>::new();
+        for key in KeyButton::iter().filter_map(KeyButton::to_evdev) {
+            keys.insert(key);
+        }
+        for mouse in MouseButton::iter() {
+            keys.insert(mouse.to_evdev());
+        }
+        let mut axes = AttributeSet::<RelativeAxisCode>::new();
+        for axis in [
+            RelativeAxisCode::REL_X,
+            RelativeAxisCode::REL_Y,
+            RelativeAxisCode::REL_WHEEL,
+            RelativeAxisCode::REL_HWHEEL,
+        ] {
+            axes.insert(axis);
+        }
+
+        let mut scroll_lock = false;
+        for device in devices {
+            if let Some(supported) = device.supported_keys() {
+                for key in supported.iter() {
+                    keys.insert(key);
+                }
+            }
+            if let Some(supported) = device.supported_relative_axes() {
+                for axis in supported.iter().filter(|&axis| !is_high_resolution(axis)) {
+                    axes.insert(axis);
+                }
+            }
+            scroll_lock |= device
+                .get_led_state()
+                .is_ok_and(|leds| leds.contains(LedCode::LED_SCROLLL));
+        }
+
+        let output = VirtualDevice::builder()?
+            .name(VIRTUAL_DEVICE_NAME)
+            .with_keys(&keys)?
+            .with_relative_axes(&axes)?
+            .build()?;
+        Ok(Self {
+            output: Mutex::new(output),
+            scroll_lock: AtomicBool::new(scroll_lock),
+            pin_hotkey: Mutex::new(None),
+        })
+    }
+
+    /// Pass inputs from a grabbed device on as they are, as one report.
+    pub fn forward(&self, events: &[evdev::InputEvent]) {
+        if events.is_empty() {
+            return;
+        }
+        if let Err(e) = self.output.lock().unwrap().emit(events) {
+            warn!(?e, "could not pass inputs on");
+        }
+    }
+
+    /// Scroll Lock was pressed on a grabbed keyboard.
+    pub fn toggle_scroll_lock(&self) {
+        self.scroll_lock.fetch_xor(true, Ordering::Relaxed);
+    }
+
+    /// The pin hotkey last registered. The capture looks for it itself.
+    pub fn pin_hotkey(&self) -> Option<config::Hotkey> {
+        self.pin_hotkey.lock().unwrap().clone()
+    }
+
+    // Where to read what the virtual device sends, like /dev/input/event7.
+    #[cfg(test)]
+    pub fn dev_node(&self) -> io::Result<std::path::PathBuf> {
+        let mut output = self.output.lock().unwrap();
+        output
+            .enumerate_dev_nodes_blocking()?
+            .next()
+            .unwrap_or_else(|| Err(io::Error::from(io::ErrorKind::NotFound)))
+    }
+}
+
+// Mice that scroll in finer steps than a notch send both kinds of axis. Reemap remaps notches, so
+// the finer ones are dropped, and the desktop scrolls a notch at a time.
+pub fn is_high_resolution(axis: RelativeAxisCode) -> bool {
+    axis == RelativeAxisCode::REL_WHEEL_HI_RES || axis == RelativeAxisCode::REL_HWHEEL_HI_RES
+}
+
+pub fn to_event(event: InputEvent) -> Option<evdev::InputEvent> {
+    let key = |code: KeyCode, value: i32| evdev::InputEvent::new(EventType::KEY.0, code.0, value);
+    let event = match event {
+        InputEvent::Down(HoldButton::Key(k)) => key(k.to_evdev()?, 1),
+        InputEvent::Down(HoldButton::Mouse(mouse)) => key(mouse.to_evdev(), 1),
+        InputEvent::Up(HoldButton::Key(k)) => key(k.to_evdev()?, 0),
+        InputEvent::Up(HoldButton::Mouse(mouse)) => key(mouse.to_evdev(), 0),
+        InputEvent::Tap(TapButton::Wheel(wheel)) => {
+            let (axis, value) = wheel.to_evdev();
+            evdev::InputEvent::new(EventType::RELATIVE.0, axis.0, value)
+        }
+    };
+    Some(event)
+}
+
+impl InputInjector for LinuxDesktop {
+    // Nothing to mark: the virtual device is never grabbed, so Reemap does not see these again.
+    // Each input is its own report, so a press and release sent together still count as both.
+    fn inject(&self, inputs: &[InputEvent]) {
+        if inputs.is_empty() {
+            return;
+        }
+        let report = evdev::InputEvent::new(
+            EventType::SYNCHRONIZATION.0,
+            SynchronizationCode::SYN_REPORT.0,
+            0,
+        );
+        let mut events: SmallVec<[evdev::InputEvent; 2 * INPUT_BUFFER_LEN]> = SmallVec::new();
+        for input in inputs.iter().copied() {
+            let Some(event) = to_event(input) else {
+                warn!(?input, "no Linux key code; not sent");
+                continue;
+            };
+            if !events.is_empty() {
+                events.push(report);
+            }
+            events.push(event);
+        }
+        self.forward(&events);
+    }
+}
+
+impl InputCapture for LinuxDesktop {
+    fn escape_hatch(&self) -> bool {
+        self.scroll_lock.load(Ordering::Relaxed)
+    }
+
+    // Grabbed devices come to Reemap before anyone else, so the hotkey needs no registering.
+    fn register_pin_hotkey(&self, hotkey: Option<&config::Hotkey>) -> io::Result<()> {
+        *self.pin_hotkey.lock().unwrap() = hotkey.cloned();
+        Ok(())
+    }
+}
+
+impl WindowQuery for LinuxDesktop {
+    fn foreground_window(&self) -> io::Result<WindowInfo> {
+        Ok(WindowInfo::default())
+    }
+    fn open_windows(&self) -> Vec<WindowInfo> {
+        Vec::new()
+    }
+}
+
+impl CursorClip for LinuxDesktop {
+    // Windows never have bounds here, so there is never anything to clip to.
+    fn clip_cursor(&self, rect: Option<Rect>) -> io::Result<()> {
+        match rect {
+            None => Ok(()),
+            Some(_) => Err(io::Error::from(io::ErrorKind::Unsupported)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buttons::wheel::MouseWheelButton;
+
+    #[test]
+    fn key_codes_round_trip() {
+        // Every key code Linux has; KEY_MAX is 0x2ff.
+        for code in 0..0x300 {
+            let code = KeyCode(code);
+            if let Some(key) = KeyButton::from_evdev(code) {
+                assert_eq!(key.to_evdev(), Some(code), "{key:?}");
+            }
+            if let Some(mouse) = MouseButton::from_evdev(code) {
+                assert_eq!(mouse.to_evdev(), code, "{mouse:?}");
+                assert_eq!(KeyButton::from_evdev(code), None, "{mouse:?}");
+            }
+        }
+        for wheel in MouseWheelButton::iter() {
+            let (axis, value) = wheel.to_evdev();
+            assert_eq!(MouseWheelButton::from_evdev(axis, 3 * value), Some(wheel));
+        }
+    }
+
+    #[test]
+    fn keys_used_on_every_keyboard_have_codes() {
+        let missing: Vec<KeyButton> = KeyButton::iter()
+            .filter(|key| key.key_type() == crate::buttons::key::KeyType::Common)
+            .filter(|key| key.to_evdev().is_none())
+            .collect();
+        assert!(missing.is_empty(), "no Linux key code for {missing:?}");
+    }
+}
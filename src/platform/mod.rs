@@ -0,0 +1,133 @@
+// Copyright 2025 Jordan Johnson
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! What Reemap needs from the operating system, behind traits.
+//!
+//! The configuration, the buttons and the remap engine only talk to the operating system through
+//! these, so they build and are tested anywhere. Each operating system implements them in its own
+//! module; the Windows one is in win32.rs and the Linux one in linux.rs. [`Detached`] implements
+//! them with no operating system at all, for running the engine on its own.
+
+use crate::buttons::InputEvent;
+use crate::config;
+
+#[cfg(target_os = "linux")]
+pub mod linux;
+#[cfg(windows)]
+pub mod win32;
+#[cfg(windows)]
+pub use win32::WindowsDesktop;
+
+/// The desktop Reemap runs on.
+#[cfg(windows)]
+pub type Native = WindowsDesktop;
+
+/// A rectangle in screen coordinates. Right and bottom are just outside it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+/// What Reemap knows about a window, to match it against profile conditions.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct WindowInfo {
+    pub title: String,
+    pub process: String,
+    pub class: String,
+    pub path: String,
+    pub ancestors: Vec<String>, // Nearest first: parent, grandparent, ...
+    pub rect: Option<Rect>,
+    pub client_rect: Option<Rect>,  // In screen coordinates
+    pub monitor_rect: Option<Rect>, // The monitor the window is mostly on
+}
+
+// -------------------- Traits --------------------
+
+/// Sends inputs as if they came from a keyboard or mouse. Inputs sent this way are marked, so
+/// input capture lets them through instead of remapping them again.
+pub trait InputInjector {
+    fn inject(&self, inputs: &[InputEvent]);
+}
+
+/// The parts of input capture the remap engine asks about. The inputs themselves come in however
+/// the operating system delivers them, and go to the engine from there.
+pub trait InputCapture {
+    /// Whether the user turned remapping off from the keyboard. On Windows, that's Scroll Lock.
+    fn escape_hatch(&self) -> bool;
+    /// Listen for the pin hotkey, in place of any earlier one. None stops listening.
+    fn register_pin_hotkey(&self, hotkey: Option<&config::Hotkey>) -> std::io::Result<()>;
+}
+
+pub trait WindowQuery {
+    /// The window in focus.
+    fn foreground_window(&self) -> std::io::Result<WindowInfo>;
+    /// Every window a profile could be made for.
+    fn open_windows(&self) -> Vec<WindowInfo>;
+}
+
+pub trait CursorClip {
+    /// Keep the cursor inside a rectangle, or let it go with None.
+    fn clip_cursor(&self, rect: Option<Rect>) -> std::io::Result<()>;
+}
+
+/// Starting Reemap when the user logs in.
+pub trait Autostart {
+    fn runs_on_login(&self) -> std::io::Result<bool>;
+    fn set_run_on_login(&self, run: bool) -> std::io::Result<()>;
+}
+
+/// Making sure only one Reemap runs for each user. The lock is held until the guard is dropped.
+pub trait SingleInstance {
+    type Guard;
+    /// Fails if another instance holds the lock.
+    fn try_lock(&self) -> std::io::Result<Self::Guard>;
+}
+
+/// Everything the remap engine needs from the desktop while it runs.
+pub trait Desktop:
+    InputInjector + InputCapture + WindowQuery + CursorClip + Send + Sync + std::fmt::Debug
+{
+}
+
+impl<T> Desktop for T where
+    T: InputInjector + InputCapture + WindowQuery + CursorClip + Send + Sync + std::fmt::Debug
+{
+}
+
+// -------------------- Detached --------------------
+
+/// No desktop at all. Nothing is sent, no window is in focus, and the cursor is never clipped.
+/// The benchmarks and tests run the engine against this.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Detached;
+
+impl InputInjector for Detached {
+    fn inject(&self, _inputs: &[InputEvent]) {}
+}
+
+impl InputCapture for Detached {
+    fn escape_hatch(&self) -> bool {
+        false
+    }
+    fn register_pin_hotkey(&self, _hotkey: Option<&config::Hotkey>) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl WindowQuery for Detached {
+    fn foreground_window(&self) -> std::io::Result<WindowInfo> {
+        Ok(WindowInfo::default())
+    }
+    fn open_windows(&self) -> Vec<WindowInfo> {
+        Vec::new()
+    }
+}
+
+impl CursorClip for Detached {
+    fn clip_cursor(&self, _rect: Option<Rect>) -> std::io::Result<()> {
+        Ok(())
+    }
+}
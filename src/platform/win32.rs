@@ -0,0 +1,144 @@
+// Copyright 2025 Jordan Johnson
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! The platform traits on Windows.
+//!
+//! Most of the work is done elsewhere: the hooks in hooks/, window queries in query_windows.rs,
+//! the registry in registry.rs and the instance lock in unique.rs. This joins them up.
+
+use super::{
+    Autostart, CursorClip, InputCapture, InputInjector, Rect, SingleInstance, WindowInfo,
+    WindowQuery,
+};
+use crate::buttons::{HoldButton, InputEvent, TapButton};
+use crate::config;
+use crate::hooks::INPUT_BUFFER_LEN;
+use crate::query_windows;
+use crate::registry;
+use crate::unique;
+use smallvec::SmallVec;
+use windows::Win32::Foundation as FN;
+use windows::Win32::UI::Input::KeyboardAndMouse as KBM;
+use windows::Win32::UI::WindowsAndMessaging as WM;
+
+// The ID of the hotkey used to pin and unpin the current profile.
+pub const PIN_HOTKEY_ID: i32 = 1;
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WindowsDesktop;
+
+impl From<FN::RECT> for Rect {
+    fn from(rect: FN::RECT) -> Self {
+        Self {
+            left: rect.left,
+            top: rect.top,
+            right: rect.right,
+            bottom: rect.bottom,
+        }
+    }
+}
+
+impl From<Rect> for FN::RECT {
+    fn from(rect: Rect) -> Self {
+        Self {
+            left: rect.left,
+            top: rect.top,
+            right: rect.right,
+            bottom: rect.bottom,
+        }
+    }
+}
+
+pub fn to_input(event: InputEvent) -> KBM::INPUT {
+    match event {
+        InputEvent::Down(HoldButton::Key(key)) => key.to_keydown_input(),
+        InputEvent::Down(HoldButton::Mouse(mouse)) => mouse.to_mousedown_input(),
+        InputEvent::Up(HoldButton::Key(key)) => key.to_keyup_input(),
+        InputEvent::Up(HoldButton::Mouse(mouse)) => mouse.to_mouseup_input(),
+        InputEvent::Tap(TapButton::Wheel(wheel)) => wheel.to_input(),
+    }
+}
+
+impl InputInjector for WindowsDesktop {
+    // The hooks let through every injected input, so there is nothing to mark.
+    fn inject(&self, inputs: &[InputEvent]) {
+        if inputs.is_empty() {
+            return;
+        }
+        let inputs: SmallVec<[KBM::INPUT; INPUT_BUFFER_LEN]> =
+            inputs.iter().copied().map(to_input).collect();
+        let cbsize = std::mem::size_of::<KBM::INPUT>() as i32;
+        unsafe {
+            KBM::SendInput(&inputs, cbsize);
+        }
+    }
+}
+
+impl InputCapture for WindowsDesktop {
+    fn escape_hatch(&self) -> bool {
+        unsafe { KBM::GetKeyState(KBM::VK_SCROLL.0.into()) & 1 > 0 }
+    }
+
+    // The hotkey is registered with no window, so Windows posts WM_HOTKEY to the calling thread.
+    // Only the hook thread should call this.
+    fn register_pin_hotkey(&self, hotkey: Option<&config::Hotkey>) -> std::io::Result<()> {
+        let _ = unsafe { KBM::UnregisterHotKey(None, PIN_HOTKEY_ID) };
+        let Some(hotkey) = hotkey else {
+            return Ok(());
+        };
+        let mut modifiers = KBM::MOD_NOREPEAT;
+        if hotkey.ctrl {
+            modifiers |= KBM::MOD_CONTROL;
+        }
+        if hotkey.alt {
+            modifiers |= KBM::MOD_ALT;
+        }
+        if hotkey.shift {
+            modifiers |= KBM::MOD_SHIFT;
+        }
+        if hotkey.win {
+            modifiers |= KBM::MOD_WIN;
+        }
+        unsafe { KBM::RegisterHotKey(None, PIN_HOTKEY_ID, modifiers, hotkey.key.to_vk().into()) }?;
+        Ok(())
+    }
+}
+
+impl WindowQuery for WindowsDesktop {
+    fn foreground_window(&self) -> std::io::Result<WindowInfo> {
+        query_windows::get_foreground_window().map_err(std::io::Error::other)
+    }
+    fn open_windows(&self) -> Vec<WindowInfo> {
+        query_windows::enumerate_open_windows()
+    }
+}
+
+impl CursorClip for WindowsDesktop {
+    fn clip_cursor(&self, rect: Option<Rect>) -> std::io::Result<()> {
+        let rect = rect.map(FN::RECT::from);
+        // as_ref() is preferable to this, except Option<&T> doesn't get coerced to Option<*const T>
+        unsafe {
+            match rect {
+                None => WM::ClipCursor(None),
+                Some(rect) => WM::ClipCursor(Some(&rect)),
+            }
+        }?;
+        Ok(())
+    }
+}
+
+impl Autostart for WindowsDesktop {
+    fn runs_on_login(&self) -> std::io::Result<bool> {
+        registry::is_registered_run_on_login().map_err(std::io::Error::other)
+    }
+    fn set_run_on_login(&self, run: bool) -> std::io::Result<()> {
+        registry::run_on_login(run).map_err(std::io::Error::other)
+    }
+}
+
+impl SingleInstance for WindowsDesktop {
+    type Guard = unique::UniqueGuard;
+    fn try_lock(&self) -> std::io::Result<Self::Guard> {
+        unique::UniqueGuard::try_lock().map_err(std::io::Error::other)
+    }
+}
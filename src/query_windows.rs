@@ -3,22 +3,24 @@
 
 //! Utility functions for Windows
 
+use crate::platform::Rect;
+pub use crate::platform::WindowInfo;
+use std::collections::HashMap;
 use std::path::PathBuf;
 use thiserror::Error;
 use tracing::warn;
 use windows::Win32::Foundation as FN;
+use windows::Win32::Graphics::Gdi;
+use windows::Win32::System::Diagnostics::ToolHelp as TLH;
 use windows::Win32::System::Threading as TH;
 use windows::Win32::UI::WindowsAndMessaging as WM;
 use windows::core::PWSTR;
 
 const CAP: usize = 512;
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct WindowInfo {
-    pub title: String,
-    pub process: String,
-    pub rect: Option<FN::RECT>,
-}
+// How far up the process tree to look for ancestors. Process IDs can be reused, so a long enough
+// chain could in theory loop back on itself; this bounds the walk either way.
+const MAX_ANCESTORS: usize = 16;
 
 #[derive(Debug, Error, Clone)]
 pub enum WindowError {
@@ -45,11 +47,15 @@ pub fn get_window_info(hwnd: FN::HWND) -> WindowResult<WindowInfo> {
     if hwnd.is_invalid() {
         return Err(WindowError::InvalidHWND);
     }
-    unsafe { get_window_info_unchecked(hwnd) }
+    let processes = ProcessTree::snapshot();
+    unsafe { get_window_info_unchecked(hwnd, &processes) }
 }
 
 // SAFETY: hwnd must be valid
-pub unsafe fn get_window_info_unchecked(hwnd: FN::HWND) -> WindowResult<WindowInfo> {
+pub unsafe fn get_window_info_unchecked(
+    hwnd: FN::HWND,
+    processes: &ProcessTree,
+) -> WindowResult<WindowInfo> {
     let process_id = {
         let mut process_id = 0u32;
         let process_id_ptr: *mut u32 = &mut process_id;
@@ -89,7 +95,12 @@ pub unsafe fn get_window_info_unchecked(hwnd: FN::HWND) -> WindowResult<WindowIn
         return Ok(WindowInfo {
             title: String::from("Reemap"),
             process: String::from("reemap.exe"),
+            class: String::new(),
+            path: String::new(),
+            ancestors: Vec::new(),
             rect: None,
+            client_rect: None,
+            monitor_rect: None,
         });
     }
 
@@ -103,7 +114,17 @@ pub unsafe fn get_window_info_unchecked(hwnd: FN::HWND) -> WindowResult<WindowIn
         String::from_utf16_lossy(&title[0..len])
     };
 
-    let process = {
+    let class = {
+        let mut class = [0u16; CAP];
+        let len = unsafe { WM::GetClassNameW(hwnd, &mut class) };
+        if len < 0 {
+            return Err(WindowError::NegativeTextLen(len));
+        }
+        let len = std::cmp::min(len as usize, CAP - 1);
+        String::from_utf16_lossy(&class[0..len])
+    };
+
+    let (process, path) = {
         let hprocess = unsafe {
             match TH::OpenProcess(TH::PROCESS_QUERY_LIMITED_INFORMATION, false, process_id) {
                 Ok(hprocess) => hprocess,
@@ -129,20 +150,21 @@ pub unsafe fn get_window_info_unchecked(hwnd: FN::HWND) -> WindowResult<WindowIn
             }
         };
         let len = std::cmp::min(len as usize, CAP - 1);
-        let title = String::from_utf16_lossy(&title[0..len]);
-        let title = PathBuf::from(title);
-        let Some(title) = title.file_name() else {
+        let path = String::from_utf16_lossy(&title[0..len]);
+        let Some(title) = PathBuf::from(&path).file_name() else {
             return Err(WindowError::ExtractFilenameError);
         };
-        String::from(title.to_string_lossy())
+        (String::from(title.to_string_lossy()), path)
     };
 
+    let ancestors = processes.ancestors(process_id);
+
     let rect = {
         let mut rect = FN::RECT::default();
         let rect_ptr = &mut rect as *mut FN::RECT;
         let result = unsafe { WM::GetWindowRect(hwnd, rect_ptr) };
         match result {
-            Ok(()) => Some(rect),
+            Ok(()) => Some(rect.into()),
             Err(e) => {
                 warn!(?e, "could not get window rect");
                 None
@@ -150,20 +172,85 @@ pub unsafe fn get_window_info_unchecked(hwnd: FN::HWND) -> WindowResult<WindowIn
         }
     };
 
+    // GetClientRect gives coordinates relative to the client area itself. Convert its corners to
+    // screen coordinates.
+    let client_rect = {
+        let mut rect = FN::RECT::default();
+        let result = unsafe { WM::GetClientRect(hwnd, &mut rect) };
+        match result {
+            Ok(()) => {
+                let mut top_left = FN::POINT {
+                    x: rect.left,
+                    y: rect.top,
+                };
+                let mut bottom_right = FN::POINT {
+                    x: rect.right,
+                    y: rect.bottom,
+                };
+                let converted = unsafe {
+                    Gdi::ClientToScreen(hwnd, &mut top_left).as_bool()
+                        && Gdi::ClientToScreen(hwnd, &mut bottom_right).as_bool()
+                };
+                if converted {
+                    Some(Rect {
+                        left: top_left.x,
+                        top: top_left.y,
+                        right: bottom_right.x,
+                        bottom: bottom_right.y,
+                    })
+                } else {
+                    warn!("could not convert client rect to screen coordinates");
+                    None
+                }
+            }
+            Err(e) => {
+                warn!(?e, "could not get client rect");
+                None
+            }
+        }
+    };
+
+    let monitor_rect = {
+        let hmonitor = unsafe { Gdi::MonitorFromWindow(hwnd, Gdi::MONITOR_DEFAULTTONEAREST) };
+        let mut monitor_info = Gdi::MONITORINFO {
+            cbSize: std::mem::size_of::<Gdi::MONITORINFO>() as u32,
+            ..Default::default()
+        };
+        if unsafe { Gdi::GetMonitorInfoW(hmonitor, &mut monitor_info) }.as_bool() {
+            Some(monitor_info.rcMonitor.into())
+        } else {
+            warn!("could not get monitor info");
+            None
+        }
+    };
+
     Ok(WindowInfo {
         title,
         process,
+        class,
+        path,
+        ancestors,
         rect,
+        client_rect,
+        monitor_rect,
     })
 }
 
 /// Get every visible window.
 pub fn enumerate_open_windows() -> Vec<WindowInfo> {
-    let mut windows = Vec::new();
-    let windows_ptr = &mut windows as *mut Vec<_>;
-    let windows_ptr_isize = windows_ptr as isize;
-    unsafe { WM::EnumWindows(Some(enum_windows_proc), FN::LPARAM(windows_ptr_isize)) }.unwrap();
-    windows
+    let mut ctx = EnumWindowsCtx {
+        processes: ProcessTree::snapshot(),
+        windows: Vec::new(),
+    };
+    let ctx_ptr = &mut ctx as *mut EnumWindowsCtx;
+    let ctx_ptr_isize = ctx_ptr as isize;
+    unsafe { WM::EnumWindows(Some(enum_windows_proc), FN::LPARAM(ctx_ptr_isize)) }.unwrap();
+    ctx.windows
+}
+
+struct EnumWindowsCtx {
+    processes: ProcessTree,
+    windows: Vec<WindowInfo>,
 }
 
 // This is an EnumWindowsProc.
@@ -172,16 +259,77 @@ unsafe extern "system" fn enum_windows_proc(
     hwnd: FN::HWND,
     lparam: FN::LPARAM,
 ) -> windows::core::BOOL {
-    let FN::LPARAM(windows_ptr_isize) = lparam;
-    let windows_ptr = windows_ptr_isize as *mut Vec<WindowInfo>;
-    let windows = unsafe { &mut *windows_ptr };
+    let FN::LPARAM(ctx_ptr_isize) = lparam;
+    let ctx_ptr = ctx_ptr_isize as *mut EnumWindowsCtx;
+    let ctx = unsafe { &mut *ctx_ptr };
 
-    if let Ok(info) = unsafe { get_window_info_unchecked(hwnd) }
+    if let Ok(info) = unsafe { get_window_info_unchecked(hwnd, &ctx.processes) }
         && !info.title.is_empty()
         && unsafe { WM::IsWindowVisible(hwnd) }.as_bool()
     {
-        windows.push(info);
+        ctx.windows.push(info);
     }
 
     true.into()
 }
+
+// -------------------- ProcessTree --------------------
+
+/// A snapshot of every running process, used to look up a process's ancestors.
+#[derive(Debug, Default, Clone)]
+pub struct ProcessTree {
+    // process ID -> (parent process ID, executable file name)
+    processes: HashMap<u32, (u32, String)>,
+}
+
+impl ProcessTree {
+    /// Take a snapshot of the running processes. If the snapshot fails, the tree is empty, and
+    /// every process will appear to have no ancestors.
+    pub fn snapshot() -> Self {
+        let mut processes = HashMap::new();
+
+        let hsnapshot = match unsafe { TLH::CreateToolhelp32Snapshot(TLH::TH32CS_SNAPPROCESS, 0) } {
+            Ok(hsnapshot) => hsnapshot,
+            Err(e) => {
+                warn!(?e, "could not snapshot processes");
+                return Self { processes };
+            }
+        };
+
+        let mut entry = TLH::PROCESSENTRY32W {
+            dwSize: std::mem::size_of::<TLH::PROCESSENTRY32W>() as u32,
+            ..Default::default()
+        };
+        let mut next = unsafe { TLH::Process32FirstW(hsnapshot, &mut entry) };
+        while next.is_ok() {
+            let len = entry
+                .szExeFile
+                .iter()
+                .position(|&c| c == 0)
+                .unwrap_or(entry.szExeFile.len());
+            let name = String::from_utf16_lossy(&entry.szExeFile[0..len]);
+            processes.insert(entry.th32ProcessID, (entry.th32ParentProcessID, name));
+            next = unsafe { TLH::Process32NextW(hsnapshot, &mut entry) };
+        }
+
+        let _ = unsafe { FN::CloseHandle(hsnapshot) };
+        Self { processes }
+    }
+
+    /// The executable file names of this process's parent, grandparent, and so on.
+    pub fn ancestors(&self, process_id: u32) -> Vec<String> {
+        let mut ancestors = Vec::new();
+        let mut visited = vec![process_id];
+        let mut current = process_id;
+        while ancestors.len() < MAX_ANCESTORS
+            && let Some((parent_id, _)) = self.processes.get(&current)
+            && !visited.contains(parent_id)
+            && let Some((_, parent_name)) = self.processes.get(parent_id)
+        {
+            ancestors.push(parent_name.clone());
+            visited.push(*parent_id);
+            current = *parent_id;
+        }
+        ancestors
+    }
+}
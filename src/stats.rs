@@ -0,0 +1,171 @@
+// Copyright 2025 Jordan Johnson
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Counts of button presses, so players can see which remaps they actually use and which buttons
+//! are free to take on new ones.
+//!
+//! The hook thread does the counting. Counts only live as long as Reemap is running; export them
+//! as CSV to keep them.
+
+use crate::buttons::Button;
+use enum_map::EnumMap;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+pub type Counts = EnumMap<Button, u64>;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ButtonStats {
+    // Every press this session, whatever profile was active.
+    pub session: Counts,
+    // The same presses, split by the profile on top when they happened. Presses made while no
+    // profile was active only count towards the session.
+    pub profiles: BTreeMap<String, Counts>,
+}
+
+impl ButtonStats {
+    /// Make room for these profiles' counts, keeping any they already have. Call this when a
+    /// configuration is applied, so that counting never allocates on the hook thread.
+    pub fn add_profiles<'a>(&mut self, names: impl IntoIterator<Item = &'a str>) {
+        for name in names {
+            if !self.profiles.contains_key(name) {
+                self.profiles.insert(name.to_string(), Counts::default());
+            }
+        }
+    }
+
+    /// Start counting from zero again, keeping room for every profile.
+    pub fn reset(&mut self) {
+        self.session = Counts::default();
+        for counts in self.profiles.values_mut() {
+            *counts = Counts::default();
+        }
+    }
+
+    /// Count one press of a physical button. Key repeats should not be counted. Presses under a
+    /// profile that was not added with add_profiles only count towards the session.
+    pub fn record(&mut self, button: Button, profile: Option<&str>) {
+        self.session[button] += 1;
+        if let Some(counts) = profile.and_then(|name| self.profiles.get_mut(name)) {
+            counts[button] += 1;
+        }
+    }
+
+    /// The counts for one profile, or for the whole session with None.
+    pub fn counts(&self, profile: Option<&str>) -> Option<&Counts> {
+        match profile {
+            None => Some(&self.session),
+            Some(name) => self.profiles.get(name),
+        }
+    }
+
+    /// One row per button pressed at least once, most pressed first, with a column for the
+    /// session and one for each profile.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("button,session");
+        for name in self.profiles.keys() {
+            csv.push(',');
+            csv.push_str(&csv_field(name));
+        }
+        csv.push('\n');
+
+        for (button, total) in sorted(&self.session) {
+            csv.push_str(&csv_field(&button.to_string()));
+            csv.push(',');
+            csv.push_str(&total.to_string());
+            for counts in self.profiles.values() {
+                csv.push(',');
+                csv.push_str(&counts[button].to_string());
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+/// Buttons pressed at least once, most pressed first.
+pub fn sorted(counts: &Counts) -> Vec<(Button, u64)> {
+    let mut sorted: Vec<(Button, u64)> = counts
+        .iter()
+        .filter(|(_, count)| **count > 0)
+        .map(|(button, count)| (button, *count))
+        .collect();
+    sorted.sort_by(|(button_a, count_a), (button_b, count_b)| {
+        count_b.cmp(count_a).then(button_a.cmp(button_b))
+    });
+    sorted
+}
+
+// Quote a field if it would otherwise break the row. Profile names can contain anything.
+fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buttons::key::KeyButton as K;
+    use crate::buttons::mouse::MouseButton as M;
+
+    #[test]
+    fn presses_count_towards_the_session_and_the_profile_on_top() {
+        let mut stats = ButtonStats::default();
+        stats.add_profiles(["Game", "Editor"]);
+        stats.record(K::A.into(), Some("Game"));
+        stats.record(K::A.into(), Some("Game"));
+        stats.record(K::A.into(), None);
+        stats.record(M::Left.into(), Some("Editor"));
+
+        assert_eq!(stats.session[Button::from(K::A)], 3);
+        assert_eq!(stats.counts(Some("Game")).unwrap()[Button::from(K::A)], 2);
+        assert_eq!(stats.counts(Some("Editor")).unwrap()[Button::from(K::A)], 0);
+        assert!(stats.counts(Some("Nobody")).is_none());
+        assert_eq!(
+            sorted(stats.counts(None).unwrap()),
+            [(K::A.into(), 3), (M::Left.into(), 1)]
+        );
+
+        stats.record(K::A.into(), Some("Nobody"));
+        assert!(stats.counts(Some("Nobody")).is_none());
+        assert_eq!(stats.session[Button::from(K::A)], 4);
+
+        // Applying a configuration again keeps what was counted.
+        stats.add_profiles(["Game", "Nobody"]);
+        assert_eq!(stats.counts(Some("Game")).unwrap()[Button::from(K::A)], 2);
+        assert_eq!(stats.counts(Some("Nobody")).unwrap()[Button::from(K::A)], 0);
+
+        stats.reset();
+        assert_eq!(stats.session[Button::from(K::A)], 0);
+        assert_eq!(stats.counts(Some("Game")).unwrap()[Button::from(K::A)], 0);
+        stats.record(K::A.into(), Some("Game"));
+        assert_eq!(stats.counts(Some("Game")).unwrap()[Button::from(K::A)], 1);
+    }
+
+    #[test]
+    fn csv_has_a_column_per_profile_and_quotes_names() {
+        let mut stats = ButtonStats::default();
+        stats.add_profiles(["Game, \"Ranked\"", "Editor"]);
+        stats.record(K::A.into(), Some("Game, \"Ranked\""));
+        stats.record(K::A.into(), Some("Game, \"Ranked\""));
+        stats.record(M::Left.into(), Some("Editor"));
+        stats.record(M::Left.into(), Some("Editor"));
+        stats.record(M::Left.into(), None);
+
+        let a = csv_field(&Button::from(K::A).to_string()).into_owned();
+        let left = csv_field(&Button::from(M::Left).to_string()).into_owned();
+        assert_eq!(
+            stats.to_csv(),
+            format!(
+                "button,session,Editor,\"Game, \"\"Ranked\"\"\"\n\
+                {left},3,2,0\n\
+                {a},2,0,2\n"
+            )
+        );
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_field("plain"), "plain");
+    }
+}
@@ -0,0 +1,305 @@
+// Copyright 2025 Jordan Johnson
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Lookup tables for deciding what a profile does with a button.
+//!
+//! Which of a profile's layers gets to decide depends only on the button and which layers are
+//! active. So when a configuration is applied, each profile is compiled into one table per
+//! combination of active layers, and the hooks look the answer up instead of walking the layers on
+//! every press.
+//!
+//! Working out the tables also shows which remaps can never be used: a modifier layer whose
+//! condition buttons include all of another's is only ever on while that one is, so wherever the
+//! other one comes first and remaps the same button, it always wins. `reemap config check` and the
+//! UI report these.
+
+use crate::buttons::Button;
+use crate::config::{BaseRemapPolicy, LayerType, Output, Profile, RemapPolicy};
+use enum_map::EnumMap;
+use smallvec::SmallVec;
+
+// Each enabled layer doubles the number of tables, so profiles with more enabled layers than this
+// are not compiled. Their layers are walked on every press instead, as before.
+pub const MAX_TABLE_LAYERS: usize = 8;
+
+/// What to do with a button, once every active profile and layer has had its say.
+#[derive(Debug)]
+pub enum Resolution<'a> {
+    NoRemap,
+    Remap(&'a Output),
+    Suppress,
+}
+
+// For each button, the layer that decides what happens to it, by its position among the profile's
+// enabled layers. None leaves it to the base layer.
+type Table = EnumMap<Button, Option<u8>>;
+
+#[derive(Debug, Default, Clone)]
+pub struct ProfileTables {
+    // The indices of the profile's enabled layers, highest priority first. A layer set is a bitmask
+    // over these: bit i is set if layers[i] is active.
+    layers: SmallVec<[usize; MAX_TABLE_LAYERS]>,
+    // Indexed by layer set. None if the profile has too many enabled layers.
+    tables: Option<Vec<Table>>,
+}
+
+/// A remap that never happens, because a layer that comes first is always on when this one is and
+/// has its own policy for the button.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HiddenRemap {
+    pub layer_idx: usize,
+    pub button: Button,
+    pub hidden_by: usize,
+}
+
+impl ProfileTables {
+    pub fn new(profile: &Profile) -> Self {
+        let layers: SmallVec<[usize; MAX_TABLE_LAYERS]> = profile
+            .layers
+            .iter()
+            .enumerate()
+            .filter(|(_, layer)| layer.enabled)
+            .map(|(layer_idx, _)| layer_idx)
+            .collect();
+        if layers.len() > MAX_TABLE_LAYERS {
+            return Self {
+                layers,
+                tables: None,
+            };
+        }
+        let tables = (0..1usize << layers.len())
+            .map(|layer_set| {
+                Table::from_fn(|button| {
+                    (0..layers.len())
+                        .filter(|&bit| layer_set & (1 << bit) != 0)
+                        .find(|&bit| {
+                            profile.layers[layers[bit]].policy[button] != RemapPolicy::Defer
+                        })
+                        .map(|bit| bit as u8)
+                })
+            })
+            .collect();
+        Self {
+            layers,
+            tables: Some(tables),
+        }
+    }
+
+    /// The set of active layers, as the hooks pass it back to resolve(). Call this whenever the
+    /// active layers change.
+    pub fn layer_set(&self, layer_actives: &[bool]) -> usize {
+        self.layers
+            .iter()
+            .take(MAX_TABLE_LAYERS)
+            .enumerate()
+            .filter(|&(_, &layer_idx)| layer_actives[layer_idx])
+            .fold(0, |layer_set, (bit, _)| layer_set | 1 << bit)
+    }
+
+    /// What the profile does with a button. None means no layer decides and its base layer leaves
+    /// it to be inherited, so the next profile in the stack decides.
+    pub fn resolve<'a>(
+        &self,
+        profile: &'a Profile,
+        layer_actives: &[bool],
+        layer_set: usize,
+        button: Button,
+    ) -> Option<Resolution<'a>> {
+        let deciding_layer = match &self.tables {
+            Some(tables) => tables[layer_set][button].map(|bit| self.layers[bit as usize]),
+            None => self
+                .layers
+                .iter()
+                .copied()
+                .filter(|&layer_idx| layer_actives[layer_idx])
+                .find(|&layer_idx| profile.layers[layer_idx].policy[button] != RemapPolicy::Defer),
+        };
+        if let Some(layer_idx) = deciding_layer {
+            match &profile.layers[layer_idx].policy[button] {
+                RemapPolicy::Defer => (),
+                RemapPolicy::Remap(output) => return Some(Resolution::Remap(output)),
+                RemapPolicy::Suppress => return Some(Resolution::Suppress),
+                RemapPolicy::NoRemap => return Some(Resolution::NoRemap),
+            }
+        }
+        match &profile.base.policy[button] {
+            BaseRemapPolicy::Remap(output) => Some(Resolution::Remap(output)),
+            BaseRemapPolicy::Suppress => Some(Resolution::Suppress),
+            BaseRemapPolicy::NoRemap => Some(Resolution::NoRemap),
+            BaseRemapPolicy::Inherit => None,
+        }
+    }
+
+    /// Every layer policy that never gets used. Only compiled profiles are checked.
+    pub fn hidden_remaps(&self, profile: &Profile) -> Vec<HiddenRemap> {
+        let Some(tables) = &self.tables else {
+            return Vec::new();
+        };
+        let reachable: Vec<usize> = (0..tables.len())
+            .filter(|&layer_set| self.is_reachable(profile, layer_set))
+            .collect();
+
+        let mut hidden = Vec::new();
+        for (bit, &layer_idx) in self.layers.iter().enumerate() {
+            let with_layer: Vec<usize> = reachable
+                .iter()
+                .copied()
+                .filter(|&layer_set| layer_set & (1 << bit) != 0)
+                .collect();
+            // A layer that can never turn on is reported elsewhere.
+            if with_layer.is_empty() {
+                continue;
+            }
+            for (button, policy) in profile.layers[layer_idx].policy.iter() {
+                if *policy == RemapPolicy::Defer {
+                    continue;
+                }
+                let used = with_layer
+                    .iter()
+                    .any(|&layer_set| tables[layer_set][button] == Some(bit as u8));
+                if used {
+                    continue;
+                }
+                let hidden_by = tables[with_layer[0]][button]
+                    .map(|bit| self.layers[bit as usize])
+                    .expect("a layer with a policy for the button decides it");
+                hidden.push(HiddenRemap {
+                    layer_idx,
+                    button,
+                    hidden_by,
+                });
+            }
+        }
+        hidden
+    }
+
+    // Whether these layers can all be on, and every other layer off, at the same time. A modifier
+    // layer is on exactly while its condition buttons are held, so it is on whenever another
+    // modifier layer that needs all of the same buttons, and more, is on.
+    fn is_reachable(&self, profile: &Profile, layer_set: usize) -> bool {
+        let in_set = |bit: usize| layer_set & (1 << bit) != 0;
+        self.layers.iter().enumerate().all(|(bit, &layer_idx)| {
+            if !in_set(bit) {
+                return true;
+            }
+            let layer = &profile.layers[layer_idx];
+            if layer.condition.is_empty() {
+                return false;
+            }
+            if layer.layer_type != LayerType::Modifier {
+                return true;
+            }
+            self.layers
+                .iter()
+                .enumerate()
+                .filter(|&(other_bit, _)| other_bit != bit && !in_set(other_bit))
+                .all(|(_, &other_idx)| {
+                    let other = &profile.layers[other_idx];
+                    other.layer_type != LayerType::Modifier
+                        || other.condition.is_empty()
+                        || !other
+                            .condition
+                            .iter()
+                            .all(|button| layer.condition.contains(button))
+                })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buttons::key::KeyButton as K;
+    use crate::buttons::mouse::MouseButton as M;
+    use crate::config::Layer;
+    use smallvec::smallvec;
+
+    fn layer(name: &str, layer_type: LayerType, condition: &[K], remaps: &[(K, K)]) -> Layer {
+        let mut layer = Layer {
+            name: name.to_string(),
+            layer_type,
+            condition: condition.iter().map(|&key| key.into()).collect(),
+            ..Default::default()
+        };
+        for &(from, to) in remaps {
+            layer.policy[Button::from(from)] = RemapPolicy::Remap(smallvec![to.into()]);
+        }
+        layer
+    }
+
+    fn remapped_to(resolution: Option<Resolution>) -> Option<Vec<Button>> {
+        match resolution {
+            Some(Resolution::Remap(output)) => Some(output.to_vec()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn tables_agree_with_walking_the_layers() {
+        let mut profile = Profile {
+            layers: vec![
+                layer("Shift", LayerType::Modifier, &[K::LSHIFT], &[(K::A, K::B)]),
+                layer(
+                    "Caps",
+                    LayerType::Toggle,
+                    &[K::CAPITAL],
+                    &[(K::A, K::C), (K::D, K::E)],
+                ),
+            ],
+            ..Default::default()
+        };
+        profile.base.policy[Button::from(K::A)] = BaseRemapPolicy::Remap(smallvec![M::Left.into()]);
+        let tables = ProfileTables::new(&profile);
+        let mut walked = tables.clone();
+        walked.tables = None;
+
+        for layer_actives in [[false, false], [true, false], [false, true], [true, true]] {
+            let layer_set = tables.layer_set(&layer_actives);
+            for button in [K::A, K::D, K::F].map(Button::from) {
+                assert_eq!(
+                    remapped_to(tables.resolve(&profile, &layer_actives, layer_set, button)),
+                    remapped_to(walked.resolve(&profile, &layer_actives, layer_set, button)),
+                    "{button} with layers {layer_actives:?}"
+                );
+            }
+        }
+        let both = tables.layer_set(&[true, true]);
+        assert_eq!(
+            remapped_to(tables.resolve(&profile, &[true, true], both, K::A.into())),
+            Some(vec![K::B.into()])
+        );
+    }
+
+    #[test]
+    fn remaps_under_a_broader_modifier_are_hidden() {
+        let mut profile = Profile {
+            layers: vec![
+                layer("Ctrl", LayerType::Modifier, &[K::LCONTROL], &[(K::A, K::B)]),
+                layer(
+                    "Ctrl Shift",
+                    LayerType::Modifier,
+                    &[K::LCONTROL, K::LSHIFT],
+                    &[(K::A, K::C), (K::D, K::E)],
+                ),
+            ],
+            ..Default::default()
+        };
+        let hidden = ProfileTables::new(&profile).hidden_remaps(&profile);
+        assert_eq!(
+            hidden,
+            vec![HiddenRemap {
+                layer_idx: 1,
+                button: K::A.into(),
+                hidden_by: 0,
+            }]
+        );
+
+        // Toggle layers can be off while the other is on, so nothing is hidden.
+        profile.layers[0].layer_type = LayerType::Toggle;
+        assert!(
+            ProfileTables::new(&profile)
+                .hidden_remaps(&profile)
+                .is_empty()
+        );
+    }
+}